@@ -0,0 +1,152 @@
+-- 初始表结构
+--
+-- 引入版本化迁移之前，表结构由启动时的 init_tables 创建，并用 ADD COLUMN IF NOT EXISTS 逐步补齐字段。
+-- 本迁移中的语句全部可重复执行，已有部署运行后表结构和数据保持不变，全新数据库则得到完整的表结构。
+
+-- 用户表
+CREATE TABLE IF NOT EXISTS users (
+    student_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    class TEXT,
+    token TEXT,
+    avatar_url TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar_url TEXT;
+
+-- 用户认证缓存表
+-- 上游凭证使用信封加密保存：key_id 为包装数据密钥的主密钥，wrapped_key 为包装后的数据密钥
+-- key_id 为空的记录是加密前写入的明文，启动时由 encrypt_auth_cache 迁移
+CREATE TABLE IF NOT EXISTS user_auth_cache (
+    student_id TEXT PRIMARY KEY,
+    sourceid_tgc TEXT,
+    rg_objectid TEXT,
+    access_token TEXT,
+    route TEXT,
+    jwglxt_jsession TEXT,
+    ronghemenhu_jsession TEXT,
+    code TEXT,
+    cached_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+ALTER TABLE user_auth_cache ADD COLUMN IF NOT EXISTS key_id TEXT;
+ALTER TABLE user_auth_cache ADD COLUMN IF NOT EXISTS wrapped_key TEXT;
+
+-- 学生禁用名单（独立于 users 表，强制登出后依然有效）
+CREATE TABLE IF NOT EXISTS disabled_students (
+    student_id TEXT PRIMARY KEY,
+    reason TEXT,
+    disabled_by INTEGER,
+    disabled_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 登录统计表（旧版本只有学号和时间）
+CREATE TABLE IF NOT EXISTS login_stats (
+    id SERIAL PRIMARY KEY,
+    student_id TEXT NOT NULL,
+    login_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    success BOOLEAN NOT NULL DEFAULT true,
+    duration_ms INTEGER,
+    client TEXT,
+    user_agent TEXT,
+    ip TEXT,
+    failure_reason TEXT
+);
+ALTER TABLE login_stats ADD COLUMN IF NOT EXISTS success BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE login_stats ADD COLUMN IF NOT EXISTS duration_ms INTEGER;
+ALTER TABLE login_stats ADD COLUMN IF NOT EXISTS client TEXT;
+ALTER TABLE login_stats ADD COLUMN IF NOT EXISTS user_agent TEXT;
+ALTER TABLE login_stats ADD COLUMN IF NOT EXISTS ip TEXT;
+ALTER TABLE login_stats ADD COLUMN IF NOT EXISTS failure_reason TEXT;
+CREATE INDEX IF NOT EXISTS idx_login_stats_login_time ON login_stats (login_time);
+
+-- 学期配置表
+CREATE TABLE IF NOT EXISTS semester_config (
+    id SERIAL PRIMARY KEY,
+    semester_name TEXT NOT NULL,
+    semester_start_date TEXT NOT NULL,
+    is_active BOOLEAN DEFAULT true,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 管理员表（旧版本没有角色和启用状态字段，已有账户默认为超级管理员）
+CREATE TABLE IF NOT EXISTS admins (
+    id SERIAL PRIMARY KEY,
+    username TEXT UNIQUE NOT NULL,
+    password_hash TEXT NOT NULL,
+    role TEXT NOT NULL DEFAULT 'super_admin',
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    token_version INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+ALTER TABLE admins ADD COLUMN IF NOT EXISTS role TEXT NOT NULL DEFAULT 'super_admin';
+ALTER TABLE admins ADD COLUMN IF NOT EXISTS is_active BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE admins ADD COLUMN IF NOT EXISTS token_version INTEGER NOT NULL DEFAULT 0;
+
+-- 管理员 refresh token 表（只保存摘要）
+CREATE TABLE IF NOT EXISTS admin_refresh_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL REFERENCES admins(id) ON DELETE CASCADE,
+    token_hash TEXT UNIQUE NOT NULL,
+    token_version INTEGER NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 学生会话表，每次登录对应一条会话记录，会话ID即 access token 中的 jti
+-- 设备信息用于学生查看和管理自己登录的设备
+CREATE TABLE IF NOT EXISTS student_sessions (
+    id TEXT PRIMARY KEY,
+    student_id TEXT NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_student_sessions_student ON student_sessions (student_id);
+ALTER TABLE student_sessions ADD COLUMN IF NOT EXISTS device_name TEXT;
+ALTER TABLE student_sessions ADD COLUMN IF NOT EXISTS client TEXT NOT NULL DEFAULT 'other';
+ALTER TABLE student_sessions ADD COLUMN IF NOT EXISTS ip TEXT;
+ALTER TABLE student_sessions ADD COLUMN IF NOT EXISTS last_seen_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP;
+
+-- 学生 refresh token 表（只保存摘要）
+-- 同一会话产生的 token 属于同一个 family（family_id 即会话ID），检测到重复使用时整个 family 一起吊销
+CREATE TABLE IF NOT EXISTS student_refresh_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    student_id TEXT NOT NULL,
+    family_id TEXT NOT NULL,
+    token_hash TEXT UNIQUE NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_student_refresh_tokens_family ON student_refresh_tokens (family_id);
+CREATE INDEX IF NOT EXISTS idx_student_refresh_tokens_student ON student_refresh_tokens (student_id);
+
+-- 管理员审计日志表（只允许追加）
+CREATE TABLE IF NOT EXISTS admin_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    admin_id INTEGER,
+    admin_username TEXT,
+    action TEXT NOT NULL,
+    target TEXT,
+    before_data JSONB,
+    after_data JSONB,
+    ip TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_created_at ON admin_audit_log (created_at);
+
+-- 禁止修改或删除审计日志
+CREATE OR REPLACE FUNCTION admin_audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'admin_audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+DROP TRIGGER IF EXISTS admin_audit_log_append_only ON admin_audit_log;
+CREATE TRIGGER admin_audit_log_append_only
+    BEFORE UPDATE OR DELETE ON admin_audit_log
+    FOR EACH ROW EXECUTE FUNCTION admin_audit_log_append_only();
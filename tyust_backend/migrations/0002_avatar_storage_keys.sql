@@ -0,0 +1,5 @@
+-- 头像改为保存文件存储中的键，访问地址在返回时按存储配置生成
+-- 旧数据中的 /static/avatars/... 地址对应本地存储的 avatars/... 键
+UPDATE users
+SET avatar_url = substring(avatar_url FROM char_length('/static/') + 1)
+WHERE avatar_url LIKE '/static/avatars/%';
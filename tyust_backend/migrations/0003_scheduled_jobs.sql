@@ -0,0 +1,24 @@
+-- 定时任务状态表，每个任务一行，多个实例通过租约保证同一任务同时只有一个实例执行
+-- locked_by/locked_until 为当前持有租约的实例和租约到期时间，实例崩溃后租约过期即可被其他实例接管
+CREATE TABLE IF NOT EXISTS scheduled_jobs (
+    name TEXT PRIMARY KEY,
+    next_run_at TIMESTAMP,
+    locked_by TEXT,
+    locked_until TIMESTAMP,
+    run_requested_at TIMESTAMP,
+    last_started_at TIMESTAMP,
+    last_finished_at TIMESTAMP,
+    last_status TEXT,
+    last_message TEXT,
+    last_duration_ms BIGINT
+);
+
+-- 成绩轮询保存的上一次成绩，用于发现新出的成绩
+CREATE TABLE IF NOT EXISTS score_snapshots (
+    student_id TEXT PRIMARY KEY,
+    scores JSONB NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 预先设置的下一学期，到开学日期由学期切换任务设为当前学期
+ALTER TABLE semester_config ADD COLUMN IF NOT EXISTS scheduled BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,11 @@
+-- 推送给学生的事件，student_id 为空表示发送给所有学生
+-- 事件ID即 SSE 的 id，客户端断线重连时通过 Last-Event-ID 补发之后的事件；超过保留时间的事件由定时任务清理
+CREATE TABLE IF NOT EXISTS student_events (
+    id BIGSERIAL PRIMARY KEY,
+    student_id TEXT,
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_student_events_student ON student_events (student_id, id);
+CREATE INDEX IF NOT EXISTS idx_student_events_created_at ON student_events (created_at);
@@ -0,0 +1,12 @@
+-- 学生授权的微信订阅消息，一次性订阅每授权一次可发送一条，发送前扣减 remaining
+-- template 为事件类型（new_scores、announcement、schedule_changed），openid 为授权时的小程序用户
+CREATE TABLE IF NOT EXISTS wechat_subscriptions (
+    student_id TEXT NOT NULL,
+    template TEXT NOT NULL,
+    openid TEXT NOT NULL,
+    remaining INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_sent_at TIMESTAMP,
+    PRIMARY KEY (student_id, template)
+);
+CREATE INDEX IF NOT EXISTS idx_wechat_subscriptions_template ON wechat_subscriptions (template) WHERE remaining > 0;
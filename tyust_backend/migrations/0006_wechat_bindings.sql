@@ -0,0 +1,5 @@
+-- 学生绑定的微信小程序用户，绑定后可以直接用 wx.login 的 code 登录
+-- 一个 openid 只能绑定一个学号，绑定到新学号时从原学号解除
+ALTER TABLE users ADD COLUMN IF NOT EXISTS wechat_openid TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS wechat_bound_at TIMESTAMP;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_wechat_openid ON users (wechat_openid) WHERE wechat_openid IS NOT NULL;
@@ -0,0 +1,2 @@
+-- 学生的 access token 按会话签发（见 student_sessions），不再保存在 users 表中
+ALTER TABLE users DROP COLUMN IF EXISTS token;
@@ -1,211 +1,513 @@
 use axum::{
     Json,
-    extract::{Extension},
-    http::StatusCode,
+    extract::{Extension, State},
 };
 use serde::{Deserialize, Serialize};
-use sqlx::Row;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    api_types::{ApiResponse},
-    db,
+    api_types::{AdminClaims, AdminPermission, AdminRole, ApiResponse, EmptyResponse, PageResponse},
+    audit::{self, AuditEvent, ClientIp},
+    auth::{generate_refresh_token, hash_refresh_token},
+    cache::{self, CacheKind}, config, db, jwt_keys, metrics, storage,
+    events::{self, StudentEvent},
+    scheduler::{self, JobName, JobStatus},
+    entity::{AdminAccount, AuditLogEntry, LoginTrend, PeakHours, StudentSummary},
+    error::ApiError,
+    extract::{ApiJson, ApiPath, ApiQuery},
 };
 
 // 管理员登录请求参数
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct AdminLoginRequest {
     pub username: String,
     pub password: String,
 }
 
 // 管理员登录响应
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AdminLoginResponse {
     pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64, // access token 有效期（秒）
     pub username: String,
+    pub role: AdminRole,
+    pub permissions: Vec<AdminPermission>,
+}
+
+// 刷新/注销管理员会话请求
+#[derive(Deserialize, ToSchema)]
+pub struct AdminRefreshRequest {
+    pub refresh_token: String,
+}
+
+// 为管理员签发 access token 和 refresh token
+async fn issue_admin_session(admin: &AdminAccount) -> Result<AdminLoginResponse, ApiError> {
+    let role = AdminRole::parse(&admin.role).ok_or_else(|| {
+        error!(admin_id = admin.id, role = %admin.role, "Unknown admin role");
+        ApiError::InvalidAdminRole
+    })?;
+    
+    let auth_config = &config::get().auth;
+    let now = chrono::Utc::now();
+    let claims = AdminClaims {
+        sub: admin.id.to_string(),
+        username: admin.username.clone(),
+        role,
+        permissions: role.permissions(),
+        ver: admin.token_version,
+        exp: (now.timestamp() + auth_config.admin_access_token_ttl_secs) as usize,
+        iat: now.timestamp() as usize,
+    };
+    
+    let token = jwt_keys::admin()
+        .encode(&claims)
+        .map_err(|_| ApiError::TokenIssueFailed)?;
+    
+    let refresh_token = generate_refresh_token();
+    let refresh_expires_at = now + chrono::Duration::days(auth_config.admin_refresh_token_ttl_days);
+    let db_pool = db::get_db_pool().await;
+    db::save_admin_refresh_token(
+        db_pool,
+        admin.id,
+        &hash_refresh_token(&refresh_token),
+        admin.token_version,
+        refresh_expires_at.naive_utc(),
+    )
+    .await
+    .map_err(|e| {
+        error!(error = %e, "Failed to save admin refresh token");
+        ApiError::TokenIssueFailed
+    })?;
+    
+    Ok(AdminLoginResponse {
+        token,
+        refresh_token,
+        expires_in: auth_config.admin_access_token_ttl_secs,
+        username: admin.username.clone(),
+        role,
+        permissions: claims.permissions,
+    })
+}
+
+// 使管理员的所有会话失效（递增 token 版本并吊销所有 refresh token）
+async fn revoke_admin_sessions(admin_id: i32) -> Result<(), ApiError> {
+    let db_pool = db::get_db_pool().await;
+    db::revoke_admin_sessions(db_pool, admin_id).await.map_err(|e| {
+        error!(admin_id, error = %e, "Failed to revoke admin sessions");
+        ApiError::DatabaseError
+    })
 }
 
 // 修改管理员密码请求
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UpdateAdminPasswordRequest {
     pub old_password: String,
     pub new_password: String,
 }
 
 // 修改管理员用户名请求
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UpdateAdminUsernameRequest {
     pub new_username: String,
     pub password: String,
 }
 
 // 管理员登录接口
+#[utoipa::path(
+    post,
+    path = "/api/admin/login",
+    tag = "admin-auth",
+    summary = "管理员登录",
+    request_body = AdminLoginRequest,
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<AdminLoginResponse>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 413, description = "请求内容过大", body = EmptyResponse),
+        (status = 429, description = "请求过于频繁，响应头 Retry-After 为需等待的秒数", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+)]
 pub async fn admin_login(
-    Json(params): Json<AdminLoginRequest>,
-) -> Result<Json<ApiResponse<AdminLoginResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    ip: ClientIp,
+    ApiJson(params): ApiJson<AdminLoginRequest>,
+) -> Result<Json<ApiResponse<AdminLoginResponse>>, ApiError> {
+    let result = admin_login_inner(&ip, &params).await;
+    metrics::record_login("admin", result.is_ok());
+    result
+}
+
+async fn admin_login_inner(
+    ip: &ClientIp,
+    params: &AdminLoginRequest,
+) -> Result<Json<ApiResponse<AdminLoginResponse>>, ApiError> {
     let db_pool = db::get_db_pool().await;
     
     // 从数据库获取管理员信息
     match db::get_admin(db_pool, &params.username).await {
-        Ok(Some((admin_id, username, password_hash))) => {
+        Ok(Some(admin)) => {
             // 验证密码（使用 bcrypt 哈希算法）
-            match verify(&params.password, &password_hash) {
+            match verify(&params.password, &admin.password_hash) {
                 Ok(true) => {
-                    // 生成 JWT token
-                    use jsonwebtoken::{encode, Header, EncodingKey};
-                    use crate::api_types::AdminClaims;
-                    
-                    let claims = AdminClaims {
-                        sub: admin_id.to_string(),
-                        username: username.clone(),
-                        exp: chrono::Utc::now().timestamp() as usize + 3600, // 1小时过期
-                        iat: chrono::Utc::now().timestamp() as usize,
-                    };
-                    
-                    // 使用环境变量中的密钥
-                    let jwt_secret = std::env::var("ADMIN_JWT_SECRET")
-                        .unwrap_or_else(|_| "fallback_admin_secret_key".to_string());
+                    if !admin.is_active {
+                        return Err(ApiError::AdminDisabled);
+                    }
                     
-                    let token = match encode(&Header::default(), &claims, &EncodingKey::from_secret(jwt_secret.as_ref())) {
-                        Ok(t) => t,
-                        Err(_) => {
-                            return Err((
-                                StatusCode::INTERNAL_SERVER_ERROR,
-                                Json(ApiResponse::error(500, "Failed to generate token".to_string())),
-                            ));
-                        }
-                    };
+                    let response = issue_admin_session(&admin).await?;
                     
-                    let response = AdminLoginResponse {
-                        token,
-                        username: username,
-                    };
+                    audit::record_as(Some(admin.id), &response.username, ip, AuditEvent::new("admin.login")).await;
                     
                     Ok(Json(ApiResponse::success(response)))
                 },
                 Ok(false) | Err(_) => {
-                    Err((
-                        StatusCode::UNAUTHORIZED,
-                        Json(ApiResponse::error(401, "用户名或密码错误".to_string())),
-                    ))
+                    audit::record_as(Some(admin.id), &admin.username, ip, AuditEvent::new("admin.login_failed")).await;
+                    Err(ApiError::InvalidCredentials)
                 }
             }
         },
         Ok(None) => {
-            Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(401, "用户名或密码错误".to_string())),
-            ))
+            Err(ApiError::InvalidCredentials)
         },
         Err(_) => {
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::error(500, "数据库查询失败".to_string())),
-            ))
+            Err(ApiError::DatabaseError)
         }
     }
 }
 
+// 学生列表查询参数
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StudentListQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+    pub search: Option<String>, // 按学号或姓名模糊搜索
+    pub sort_by: Option<db::StudentSortField>,
+    pub order: Option<String>, // asc 或 desc，默认 desc
+}
+
 // 获取学生列表
+#[utoipa::path(
+    get,
+    path = "/api/admin/students",
+    tag = "admin",
+    summary = "获取学生列表",
+    params(StudentListQuery),
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<PageResponse<StudentSummary>>),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
 pub async fn get_students(
-    Extension(_admin_id): Extension<String>,
-) -> Result<Json<ApiResponse<Vec<crate::entity::UserLoginInfo>>>, (StatusCode, Json<ApiResponse<()>>)> {
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiQuery(params): ApiQuery<StudentListQuery>,
+) -> Result<Json<ApiResponse<PageResponse<StudentSummary>>>, ApiError> {
     let db_pool = db::get_db_pool().await;
     
-    // 查询所有用户
-    let users = sqlx::query("SELECT student_id, name, class, token, avatar_url FROM users")
-        .fetch_all(db_pool)
+    let (page, page_size, offset) = pagination(params.page, params.page_size)?;
+    let query = db::StudentQuery {
+        search: params.search.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+        sort_by: params.sort_by.unwrap_or_default(),
+        descending: !params.order.as_deref().is_some_and(|order| order.eq_ignore_ascii_case("asc")),
+    };
+    
+    let (mut students, total) = db::list_students(db_pool, &query, page_size, offset)
         .await
         .map_err(|e| {
-            eprintln!("Failed to fetch users: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::error(500, "获取学生列表失败".to_string())),
-            )
+            error!(error = %e, "Failed to fetch users");
+            ApiError::DatabaseError
         })?;
+    for student in &mut students {
+        student.avatar_url = storage::resolve_url(student.avatar_url.as_deref()).await;
+    }
     
-    let students: Vec<crate::entity::UserLoginInfo> = users
-        .into_iter()
-        .map(|row| crate::entity::UserLoginInfo {
-            student_id: row.get::<&str, _>(0).to_string(),
-            name: row.get::<&str, _>(1).to_string(),
-            class: row.get::<Option<&str>, _>(2).map(|s| s.to_string()).unwrap_or_default(),
-            token: row.get::<Option<&str>, _>(3).map(|s| s.to_string()).unwrap_or_default(),
-            avatar_url: row.get::<Option<&str>, _>(4).map(|s| s.to_string()), // 添加头像URL字段
-        })
-        .collect();
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("students.list").after(serde_json::json!({
+            "search": query.search,
+            "page": page,
+            "count": students.len(),
+        })),
+    )
+    .await;
     
-    Ok(Json(ApiResponse::success(students)))
+    Ok(Json(ApiResponse::success(PageResponse {
+        items: students,
+        total,
+        page,
+        page_size,
+    })))
+}
+
+// 清除学生的登录状态（注销全部会话并删除上游认证缓存，保留用户资料）
+async fn clear_student_session(student_id: &str) -> Result<(), ApiError> {
+    let db_pool = db::get_db_pool().await;
+    db::revoke_student_sessions(db_pool, student_id)
+        .await
+        .and(db::delete_auth_cache(db_pool, student_id).await)
+        .map_err(|e| {
+            error!(student_id = %student_id, error = %e, "Failed to clear student session");
+            ApiError::DatabaseError
+        })?;
+    cache::invalidate_student(student_id).await;
+    Ok(())
+}
+
+// 强制学生登出
+#[utoipa::path(
+    post,
+    path = "/api/admin/students/{student_id}/logout",
+    tag = "admin",
+    summary = "强制学生登出",
+    params(("student_id" = String, Path, description = "学号")),
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn force_logout_student(
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiPath(student_id): ApiPath<String>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    clear_student_session(&student_id).await?;
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("student.force_logout").target(format!("student:{}", student_id)),
+    )
+    .await;
+    
+    Ok(Json(ApiResponse::success(())))
+}
+
+// 禁用/启用学生请求
+#[derive(Deserialize, ToSchema)]
+pub struct SetStudentStatusRequest {
+    pub disabled: bool,
+    pub reason: Option<String>,
+}
+
+// 禁用或启用学生访问，禁用时同时强制登出
+#[utoipa::path(
+    post,
+    path = "/api/admin/students/{student_id}/status",
+    tag = "admin",
+    summary = "禁用或启用学生",
+    params(("student_id" = String, Path, description = "学号")),
+    request_body = SetStudentStatusRequest,
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn set_student_status(
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiPath(student_id): ApiPath<String>,
+    ApiJson(params): ApiJson<SetStudentStatusRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let db_pool = db::get_db_pool().await;
+    
+    let was_disabled = db::is_student_disabled(db_pool, &student_id)
+        .await
+        .map_err(|_| ApiError::DatabaseError)?;
+    
+    let result = if params.disabled {
+        db::disable_student(db_pool, &student_id, params.reason.as_deref(), claims.admin_id()).await
+    } else {
+        db::enable_student(db_pool, &student_id).await
+    };
+    result.map_err(|e| {
+        error!(student_id = %student_id, error = %e, "Failed to update student status");
+        ApiError::DatabaseError
+    })?;
+    cache::invalidate(CacheKind::Session, &student_id).await;
+    
+    if params.disabled {
+        clear_student_session(&student_id).await?;
+    }
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("student.status.update")
+            .target(format!("student:{}", student_id))
+            .before(serde_json::json!({ "disabled": was_disabled }))
+            .after(serde_json::json!({ "disabled": params.disabled, "reason": params.reason })),
+    )
+    .await;
+    
+    Ok(Json(ApiResponse::success(())))
 }
 
 // 获取学期配置
+#[utoipa::path(
+    get,
+    path = "/api/admin/semester",
+    tag = "admin",
+    summary = "获取学期配置",
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<crate::api_types::SemesterConfig>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 404, description = "资源不存在", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
 pub async fn get_semester(
-    Extension(_admin_id): Extension<String>,
-) -> Result<Json<ApiResponse<crate::api_types::SemesterConfig>>, (StatusCode, Json<ApiResponse<()>>)> {
+    Extension(_claims): Extension<AdminClaims>,
+) -> Result<Json<ApiResponse<crate::api_types::SemesterConfig>>, ApiError> {
     let db_pool = db::get_db_pool().await;
     
     let config = db::get_active_semester_config(db_pool)
         .await
         .map_err(|e| {
-            eprintln!("Failed to fetch semester config: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::error(500, "获取学期配置失败".to_string())),
-            )
+            error!(error = %e, "Failed to fetch semester config");
+            ApiError::DatabaseError
         })?;
     
     match config {
         Some(config) => Ok(Json(ApiResponse::success(config))),
-        None => Err((
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse::error(404, "学期配置未设置".to_string())),
-        )),
+        None => Err(ApiError::SemesterNotConfigured),
     }
 }
 
 // 设置学期配置
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct SetSemesterRequest {
     pub semester_name: String,
     pub start_date: String,
+    #[serde(default)]
+    pub scheduled: bool, // 为 true 时作为下一学期保存，到开学日期由学期切换任务设为当前学期
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/admin/semester",
+    tag = "admin",
+    summary = "设置学期配置",
+    request_body = SetSemesterRequest,
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
 pub async fn set_semester(
-    Extension(_admin_id): Extension<String>,
-    Json(params): Json<SetSemesterRequest>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiJson(params): ApiJson<SetSemesterRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
     let db_pool = db::get_db_pool().await;
     
+    // 记录修改前的配置用于审计
+    let previous = db::get_active_semester_config(db_pool).await.ok().flatten();
+    
     let config = crate::api_types::SemesterConfig {
         semester_name: params.semester_name,
         semester_start_date: params.start_date,
     };
     
+    if params.scheduled {
+        // 学期切换任务按日期字符串比较，必须是规范的 YYYY-MM-DD
+        if chrono::NaiveDate::parse_from_str(&config.semester_start_date, "%Y-%m-%d").is_err() {
+            return Err(ApiError::InvalidRequest);
+        }
+        db::schedule_semester_config(db_pool, &config)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to schedule semester config");
+                ApiError::DatabaseError
+            })?;
+        
+        audit::record(
+            &claims,
+            &ip,
+            AuditEvent::new("semester.schedule")
+                .target("semester_config")
+                .after(serde_json::json!(config)),
+        )
+        .await;
+        
+        return Ok(Json(ApiResponse::success(())));
+    }
+    
     db::save_semester_config(db_pool, &config)
         .await
         .map_err(|e| {
-            eprintln!("Failed to save semester config: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::error(500, "保存学期配置失败".to_string())),
-            )
+            error!(error = %e, "Failed to save semester config");
+            ApiError::DatabaseError
         })?;
+    if let Err(e) = crate::handlers::init_semester_config().await {
+        error!(error = %e, "Failed to reload semester config");
+    }
+    
+    // 通知在线的学生重新获取课表
+    let event = StudentEvent::ScheduleChanged {
+        semester_name: config.semester_name.clone(),
+        semester_start_date: config.semester_start_date.clone(),
+    };
+    if let Err(e) = events::broadcast(&event).await {
+        error!(error = %e, "Failed to publish schedule change");
+    }
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("semester.update")
+            .target("semester_config")
+            .before(serde_json::json!(previous))
+            .after(serde_json::json!(config)),
+    )
+    .await;
     
     Ok(Json(ApiResponse::success(())))
 }
 
 // 获取统计信息
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct StatisticsResponse {
     pub total_students: i64,
     pub active_students: i64,
     pub total_logins: i64,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/admin/statistics",
+    tag = "admin",
+    summary = "获取统计信息",
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<StatisticsResponse>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
 pub async fn get_statistics(
-    Extension(_admin_id): Extension<String>,
-) -> Result<Json<ApiResponse<StatisticsResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
+    Extension(_claims): Extension<AdminClaims>,
+) -> Result<Json<ApiResponse<StatisticsResponse>>, ApiError> {
     let db_pool = db::get_db_pool().await;
     
     // 获取总学生数
@@ -213,44 +515,30 @@ pub async fn get_statistics(
         .fetch_one(db_pool)
         .await
         .map_err(|e| {
-            eprintln!("Failed to fetch total students: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::error(500, "获取统计信息失败".to_string())),
-            )
+            error!(error = %e, "Failed to fetch total students");
+            ApiError::DatabaseError
         })?;
     
-    // 获取活跃学生数（最近24小时内登录的）
-    let cutoff_time = chrono::Utc::now() - chrono::Duration::hours(24);
-    let cutoff_time_str = cutoff_time.format("%Y-%m-%d %H:%M:%S").to_string();
-    let active_students: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM users WHERE updated_at > $1::timestamp"
-    )
-    .bind(&cutoff_time_str)
-    .fetch_one(db_pool)
-    .await
-    .map_err(|e| {
-        eprintln!("Failed to fetch active students: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(500, "获取统计信息失败".to_string())),
-        )
-    })?;
+    // 获取活跃学生数（最近24小时内成功登录过的）
+    let cutoff_time = chrono::Utc::now().naive_utc() - chrono::Duration::hours(24);
+    let active_students = db::count_active_students_since(db_pool, cutoff_time)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to fetch active students");
+            ApiError::DatabaseError
+        })?;
     
     // 获取总登录次数
     let total_logins = db::get_total_logins(db_pool)
         .await
         .map_err(|e| {
-            eprintln!("Failed to fetch total logins: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::error(500, "获取统计信息失败".to_string())),
-            )
+            error!(error = %e, "Failed to fetch total logins");
+            ApiError::DatabaseError
         })?;
     
     let stats = StatisticsResponse {
         total_students: total_students.0,
-        active_students: active_students.0,
+        active_students,
         total_logins,
     };
     
@@ -258,169 +546,928 @@ pub async fn get_statistics(
 }
 
 // 更新管理员密码
+#[utoipa::path(
+    post,
+    path = "/api/admin/password",
+    tag = "admin-auth",
+    summary = "修改管理员密码",
+    request_body = UpdateAdminPasswordRequest,
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
 pub async fn update_admin_password(
-    Extension(admin_username): Extension<String>,
-    Json(params): Json<UpdateAdminPasswordRequest>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiJson(params): ApiJson<UpdateAdminPasswordRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    validate_admin_password(&params.new_password)?;
     let db_pool = db::get_db_pool().await;
+    let admin_id = claims.admin_id().unwrap_or_default();
     
     // 获取管理员信息
-    match db::get_admin(db_pool, &admin_username).await {
-        Ok(Some((admin_id, _, password_hash))) => {
+    match db::get_admin_by_id(db_pool, admin_id).await {
+        Ok(Some(admin)) => {
             // 验证旧密码
-            match verify(&params.old_password, &password_hash) {
+            match verify(&params.old_password, &admin.password_hash) {
                 Ok(true) => {
                     // 对新密码进行哈希处理
                     match hash(&params.new_password, DEFAULT_COST) {
                         Ok(hashed_password) => {
                             // 更新密码
-                            match db::update_admin_password(db_pool, admin_id, &hashed_password).await {
-                                Ok(()) => Ok(Json(ApiResponse::success(()))),
-                                Err(_) => Err((
-                                    StatusCode::INTERNAL_SERVER_ERROR,
-                                    Json(ApiResponse::error(500, "更新密码失败".to_string())),
-                                )),
+                            match db::update_admin_password(db_pool, admin.id, &hashed_password).await {
+                                Ok(()) => {
+                                    // 修改密码后所有已签发的会话失效
+                                    revoke_admin_sessions(admin.id).await?;
+                                    audit::record(
+                                        &claims,
+                                        &ip,
+                                        AuditEvent::new("admin.password.update").target(format!("admin:{}", admin.id)),
+                                    )
+                                    .await;
+                                    Ok(Json(ApiResponse::success(())))
+                                },
+                                Err(_) => Err(ApiError::DatabaseError),
                             }
                         },
-                        Err(_) => Err((
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ApiResponse::error(500, "密码加密失败".to_string())),
-                        )),
+                        Err(_) => Err(ApiError::InternalError),
                     }
                 },
                 Ok(false) | Err(_) => {
-                    Err((
-                        StatusCode::UNAUTHORIZED,
-                        Json(ApiResponse::error(401, "旧密码错误".to_string())),
-                    ))
+                    Err(ApiError::WrongPassword)
                 }
             }
         },
         Ok(None) => {
-            Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(401, "管理员不存在".to_string())),
-            ))
+            Err(ApiError::LoginRequired)
         },
         Err(_) => {
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::error(500, "数据库查询失败".to_string())),
-            ))
+            Err(ApiError::DatabaseError)
         }
     }
 }
 
 // 更新管理员用户名
+#[utoipa::path(
+    post,
+    path = "/api/admin/username",
+    tag = "admin-auth",
+    summary = "修改管理员用户名",
+    request_body = UpdateAdminUsernameRequest,
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
 pub async fn update_admin_username(
-    Extension(admin_username): Extension<String>,
-    Json(params): Json<UpdateAdminUsernameRequest>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiJson(params): ApiJson<UpdateAdminUsernameRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
     let db_pool = db::get_db_pool().await;
+    let admin_id = claims.admin_id().unwrap_or_default();
     
     // 获取管理员信息
-    match db::get_admin(db_pool, &admin_username).await {
-        Ok(Some((admin_id, _, password_hash))) => {
+    match db::get_admin_by_id(db_pool, admin_id).await {
+        Ok(Some(admin)) => {
             // 验证密码
-            match verify(&params.password, &password_hash) {
+            match verify(&params.password, &admin.password_hash) {
                 Ok(true) => {
                     // 检查新用户名是否已存在
                     match db::get_admin(db_pool, &params.new_username).await {
                         Ok(Some(_)) => {
                             // 用户名已存在
-                            Err((
-                                StatusCode::CONFLICT,
-                                Json(ApiResponse::error(409, "用户名已存在".to_string())),
-                            ))
+                            Err(ApiError::UsernameTaken)
                         },
                         Ok(None) => {
                             // 用户名可用，更新用户名
-                            match db::update_admin_username(db_pool, admin_id, &params.new_username).await {
-                                Ok(()) => Ok(Json(ApiResponse::success(()))),
-                                Err(_) => Err((
-                                    StatusCode::INTERNAL_SERVER_ERROR,
-                                    Json(ApiResponse::error(500, "更新用户名失败".to_string())),
-                                )),
+                            match db::update_admin_username(db_pool, admin.id, &params.new_username).await {
+                                Ok(()) => {
+                                    // 修改用户名后所有已签发的会话失效
+                                    revoke_admin_sessions(admin.id).await?;
+                                    audit::record(
+                                        &claims,
+                                        &ip,
+                                        AuditEvent::new("admin.username.update")
+                                            .target(format!("admin:{}", admin.id))
+                                            .before(serde_json::json!({ "username": admin.username }))
+                                            .after(serde_json::json!({ "username": params.new_username })),
+                                    )
+                                    .await;
+                                    Ok(Json(ApiResponse::success(())))
+                                },
+                                Err(_) => Err(ApiError::DatabaseError),
                             }
                         },
                         Err(_) => {
-                            Err((
-                                StatusCode::INTERNAL_SERVER_ERROR,
-                                Json(ApiResponse::error(500, "数据库查询失败".to_string())),
-                            ))
+                            Err(ApiError::DatabaseError)
                         }
                     }
                 },
                 Ok(false) | Err(_) => {
-                    Err((
-                        StatusCode::UNAUTHORIZED,
-                        Json(ApiResponse::error(401, "密码错误".to_string())),
-                    ))
+                    Err(ApiError::WrongPassword)
                 }
             }
         },
         Ok(None) => {
-            Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(401, "管理员不存在".to_string())),
-            ))
+            Err(ApiError::LoginRequired)
         },
         Err(_) => {
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::error(500, "数据库查询失败".to_string())),
-            ))
+            Err(ApiError::DatabaseError)
         }
     }
 }
 
 // 管理员认证中间件
+//
+// 每个管理员路由通过 `middleware::from_fn_with_state` 传入该路由所需的权限，
+// 中间件在验证 token 之后检查 token 中携带的权限是否包含该权限。
 pub async fn admin_auth_middleware(
+    State(required_permission): State<AdminPermission>,
     headers: axum::http::HeaderMap,
     mut request: axum::extract::Request,
     next: axum::middleware::Next,
-) -> Result<axum::response::Response, (StatusCode, Json<ApiResponse<()>>)> {
+) -> Result<axum::response::Response, ApiError> {
     // 从Authorization头获取token
     let auth_header = headers
         .get("authorization")
         .and_then(|header| header.to_str().ok())
-        .and_then(|header| {
-            if header.starts_with("Bearer ") {
-                Some(&header[7..])
-            } else {
-                None
-            }
-        });
+        .and_then(|header| header.strip_prefix("Bearer "));
 
     let token = match auth_header {
         Some(token) => token,
         None => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(401, "缺少认证token".to_string())),
-            ));
+            return Err(ApiError::MissingToken);
         }
     };
 
     // 验证JWT token
-    use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
-    use crate::api_types::AdminClaims;
-    
-    // 使用环境变量中的密钥
-    let jwt_secret = std::env::var("ADMIN_JWT_SECRET")
-        .unwrap_or_else(|_| "fallback_admin_secret_key".to_string());
-    
-    match decode::<AdminClaims>(&token, &DecodingKey::from_secret(jwt_secret.as_ref()), &Validation::new(Algorithm::HS256)) {
+    match jwt_keys::admin().decode::<AdminClaims>(token) {
         Ok(token_data) => {
-            // 将管理员用户名添加到请求扩展中
-            request.extensions_mut().insert(token_data.claims.username);
+            // 检查账户状态和 token 版本，修改密码、禁用账户或"全部登出"后旧 token 立即失效
+            let db_pool = db::get_db_pool().await;
+            let admin_id = token_data.claims.admin_id().unwrap_or_default();
+            match db::get_admin_by_id(db_pool, admin_id).await {
+                Ok(Some(admin)) if admin.is_active && admin.token_version == token_data.claims.ver => {}
+                Ok(_) => {
+                    return Err(ApiError::LoginRequired);
+                }
+                Err(_) => {
+                    return Err(ApiError::DatabaseError);
+                }
+            }
+            
+            // 检查该路由所需的权限
+            if !token_data.claims.has_permission(required_permission) {
+                return Err(ApiError::PermissionDenied);
+            }
+            
+            // 将管理员身份信息添加到请求扩展中
+            request.extensions_mut().insert(token_data.claims);
             Ok(next.run(request).await)
         },
         Err(_) => {
-            Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(401, "无效的认证token".to_string())),
-            ))
+            Err(ApiError::InvalidToken)
         }
     }
-}
\ No newline at end of file
+}
+
+// 管理员账户信息（不包含密码哈希）
+#[derive(Serialize, ToSchema)]
+pub struct AdminAccountInfo {
+    pub id: i32,
+    pub username: String,
+    pub role: String,
+    pub is_active: bool,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl From<AdminAccount> for AdminAccountInfo {
+    fn from(admin: AdminAccount) -> Self {
+        Self {
+            id: admin.id,
+            username: admin.username,
+            role: admin.role,
+            is_active: admin.is_active,
+            created_at: admin.created_at,
+            updated_at: admin.updated_at,
+        }
+    }
+}
+
+// 创建管理员请求
+#[derive(Deserialize, ToSchema)]
+pub struct CreateAdminRequest {
+    pub username: String,
+    pub password: String,
+    pub role: AdminRole,
+}
+
+// 启用/禁用管理员请求
+#[derive(Deserialize, ToSchema)]
+pub struct SetAdminStatusRequest {
+    pub is_active: bool,
+}
+
+// 重置管理员密码请求
+#[derive(Deserialize, ToSchema)]
+pub struct ResetAdminPasswordRequest {
+    pub new_password: String,
+}
+
+// 分配管理员角色请求
+#[derive(Deserialize, ToSchema)]
+pub struct AssignAdminRoleRequest {
+    pub role: AdminRole,
+}
+
+// 新密码的最小长度
+const MIN_ADMIN_PASSWORD_LEN: usize = 6;
+
+// 校验新设置的管理员密码
+fn validate_admin_password(password: &str) -> Result<(), ApiError> {
+    if password.len() < MIN_ADMIN_PASSWORD_LEN {
+        return Err(ApiError::PasswordTooShort);
+    }
+    Ok(())
+}
+
+// 获取目标管理员，不存在时返回 404
+async fn find_admin(admin_id: i32) -> Result<AdminAccount, ApiError> {
+    let db_pool = db::get_db_pool().await;
+    match db::get_admin_by_id(db_pool, admin_id).await {
+        Ok(Some(admin)) => Ok(admin),
+        Ok(None) => Err(ApiError::AdminNotFound),
+        Err(e) => {
+            error!(admin_id, error = %e, "Failed to fetch admin");
+            Err(ApiError::DatabaseError)
+        }
+    }
+}
+
+// 禁止管理员修改自己的角色或启用状态，避免把自己锁在系统之外
+fn ensure_not_self(claims: &AdminClaims, admin_id: i32) -> Result<(), ApiError> {
+    if claims.admin_id() == Some(admin_id) {
+        return Err(ApiError::CannotModifySelf);
+    }
+    Ok(())
+}
+
+// 确保操作后系统中至少保留一个启用的超级管理员
+async fn ensure_not_last_super_admin(target: &AdminAccount) -> Result<(), ApiError> {
+    if target.role != AdminRole::SuperAdmin.as_str() || !target.is_active {
+        return Ok(());
+    }
+    let db_pool = db::get_db_pool().await;
+    match db::count_active_super_admins(db_pool).await {
+        Ok(count) if count <= 1 => Err(ApiError::LastSuperAdmin),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!(error = %e, "Failed to count super admins");
+            Err(ApiError::DatabaseError)
+        }
+    }
+}
+
+// 获取管理员列表
+#[utoipa::path(
+    get,
+    path = "/api/admin/admins",
+    tag = "admin",
+    summary = "获取管理员列表",
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<Vec<AdminAccountInfo>>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn list_admins(
+    Extension(_claims): Extension<AdminClaims>,
+) -> Result<Json<ApiResponse<Vec<AdminAccountInfo>>>, ApiError> {
+    let db_pool = db::get_db_pool().await;
+    
+    let admins = db::list_admins(db_pool).await.map_err(|e| {
+        error!(error = %e, "Failed to fetch admins");
+        ApiError::DatabaseError
+    })?;
+    
+    Ok(Json(ApiResponse::success(admins.into_iter().map(AdminAccountInfo::from).collect())))
+}
+
+// 创建管理员
+#[utoipa::path(
+    post,
+    path = "/api/admin/admins",
+    tag = "admin",
+    summary = "创建管理员",
+    request_body = CreateAdminRequest,
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<AdminAccountInfo>),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn create_admin(
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiJson(params): ApiJson<CreateAdminRequest>,
+) -> Result<Json<ApiResponse<AdminAccountInfo>>, ApiError> {
+    let db_pool = db::get_db_pool().await;
+    
+    let username = params.username.trim();
+    if username.is_empty() {
+        return Err(ApiError::UsernameRequired);
+    }
+    validate_admin_password(&params.password)?;
+    
+    match db::get_admin(db_pool, username).await {
+        Ok(Some(_)) => return Err(ApiError::UsernameTaken),
+        Ok(None) => {}
+        Err(_) => return Err(ApiError::DatabaseError),
+    }
+    
+    let password_hash = hash(&params.password, DEFAULT_COST)
+        .map_err(|_| ApiError::InternalError)?;
+    
+    let admin_id = db::create_admin(db_pool, username, &password_hash, params.role.as_str())
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to create admin");
+            ApiError::DatabaseError
+        })?;
+    
+    let admin = find_admin(admin_id).await?;
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("admin.create")
+            .target(format!("admin:{}", admin.id))
+            .after(serde_json::json!({ "username": admin.username, "role": admin.role })),
+    )
+    .await;
+    
+    Ok(Json(ApiResponse::success(AdminAccountInfo::from(admin))))
+}
+
+// 启用或禁用管理员
+#[utoipa::path(
+    post,
+    path = "/api/admin/admins/{id}/status",
+    tag = "admin",
+    summary = "启用或禁用管理员",
+    params(("id" = i32, Path, description = "管理员ID")),
+    request_body = SetAdminStatusRequest,
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 404, description = "资源不存在", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn set_admin_status(
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiPath(admin_id): ApiPath<i32>,
+    ApiJson(params): ApiJson<SetAdminStatusRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    ensure_not_self(&claims, admin_id)?;
+    let target = find_admin(admin_id).await?;
+    if !params.is_active {
+        ensure_not_last_super_admin(&target).await?;
+    }
+    
+    let db_pool = db::get_db_pool().await;
+    db::set_admin_active(db_pool, admin_id, params.is_active)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to update admin status");
+            ApiError::DatabaseError
+        })?;
+    revoke_admin_sessions(admin_id).await?;
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("admin.status.update")
+            .target(format!("admin:{}", admin_id))
+            .before(serde_json::json!({ "is_active": target.is_active }))
+            .after(serde_json::json!({ "is_active": params.is_active })),
+    )
+    .await;
+    
+    Ok(Json(ApiResponse::success(())))
+}
+
+// 重置管理员密码
+#[utoipa::path(
+    post,
+    path = "/api/admin/admins/{id}/password",
+    tag = "admin",
+    summary = "重置管理员密码",
+    params(("id" = i32, Path, description = "管理员ID")),
+    request_body = ResetAdminPasswordRequest,
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 404, description = "资源不存在", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn reset_admin_password(
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiPath(admin_id): ApiPath<i32>,
+    ApiJson(params): ApiJson<ResetAdminPasswordRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    validate_admin_password(&params.new_password)?;
+    let target = find_admin(admin_id).await?;
+    
+    let password_hash = hash(&params.new_password, DEFAULT_COST)
+        .map_err(|_| ApiError::InternalError)?;
+    
+    let db_pool = db::get_db_pool().await;
+    db::update_admin_password(db_pool, target.id, &password_hash)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to reset admin password");
+            ApiError::DatabaseError
+        })?;
+    revoke_admin_sessions(target.id).await?;
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("admin.password.reset").target(format!("admin:{}", target.id)),
+    )
+    .await;
+    
+    Ok(Json(ApiResponse::success(())))
+}
+
+// 分配管理员角色
+#[utoipa::path(
+    post,
+    path = "/api/admin/admins/{id}/role",
+    tag = "admin",
+    summary = "分配管理员角色",
+    params(("id" = i32, Path, description = "管理员ID")),
+    request_body = AssignAdminRoleRequest,
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 404, description = "资源不存在", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn assign_admin_role(
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiPath(admin_id): ApiPath<i32>,
+    ApiJson(params): ApiJson<AssignAdminRoleRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    ensure_not_self(&claims, admin_id)?;
+    let target = find_admin(admin_id).await?;
+    if params.role != AdminRole::SuperAdmin {
+        ensure_not_last_super_admin(&target).await?;
+    }
+    
+    let db_pool = db::get_db_pool().await;
+    db::update_admin_role(db_pool, admin_id, params.role.as_str())
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to update admin role");
+            ApiError::DatabaseError
+        })?;
+    // 角色变化后旧 token 中的权限已过时
+    revoke_admin_sessions(admin_id).await?;
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("admin.role.update")
+            .target(format!("admin:{}", admin_id))
+            .before(serde_json::json!({ "role": target.role }))
+            .after(serde_json::json!({ "role": params.role.as_str() })),
+    )
+    .await;
+    
+    Ok(Json(ApiResponse::success(())))
+}
+
+// 审计日志查询参数
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditLogQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+    pub admin_id: Option<i32>,
+    pub action: Option<String>,
+    pub target: Option<String>,
+    pub from: Option<chrono::NaiveDateTime>,
+    pub to: Option<chrono::NaiveDateTime>,
+}
+
+// 查询审计日志
+#[utoipa::path(
+    get,
+    path = "/api/admin/audit",
+    tag = "admin",
+    summary = "查询审计日志",
+    params(AuditLogQuery),
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<PageResponse<AuditLogEntry>>),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn get_audit_logs(
+    Extension(_claims): Extension<AdminClaims>,
+    ApiQuery(query): ApiQuery<AuditLogQuery>,
+) -> Result<Json<ApiResponse<PageResponse<AuditLogEntry>>>, ApiError> {
+    let db_pool = db::get_db_pool().await;
+    
+    let (page, page_size, offset) = pagination(query.page, query.page_size)?;
+    let filter = db::AuditLogFilter {
+        admin_id: query.admin_id,
+        action: query.action.filter(|s| !s.is_empty()),
+        target: query.target.filter(|s| !s.is_empty()),
+        from: query.from,
+        to: query.to,
+    };
+    
+    let (items, total) = db::query_audit_logs(db_pool, &filter, page_size, offset)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to fetch audit logs");
+            ApiError::DatabaseError
+        })?;
+    
+    Ok(Json(ApiResponse::success(PageResponse {
+        items,
+        total,
+        page,
+        page_size,
+    })))
+}
+
+// 分页页码的上限，超出时返回 InvalidRequest，避免计算偏移量时溢出
+const MAX_PAGE: i64 = 1_000_000;
+
+// 解析分页参数，返回 (页码, 每页条数, 偏移量)
+fn pagination(page: Option<i64>, page_size: Option<i64>) -> Result<(i64, i64, i64), ApiError> {
+    let page = page.unwrap_or(1).max(1);
+    if page > MAX_PAGE {
+        return Err(ApiError::InvalidRequest);
+    }
+    let page_size = page_size.unwrap_or(20).clamp(1, 100);
+    Ok((page, page_size, (page - 1) * page_size))
+}
+
+// 登录趋势查询参数
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LoginTrendQuery {
+    #[serde(default)]
+    pub granularity: db::Granularity,
+    pub from: Option<chrono::NaiveDateTime>, // 起始时间（UTC）
+    pub to: Option<chrono::NaiveDateTime>,   // 结束时间（UTC）
+}
+
+// 按日/周/月统计活跃用户数和登录成功率，按 analytics.timezone 时区分组
+#[utoipa::path(
+    get,
+    path = "/api/admin/analytics/logins",
+    tag = "admin",
+    summary = "按日/周/月统计活跃用户和登录成功率",
+    params(LoginTrendQuery),
+    responses(
+        (status = 200, description = "成功，period 为 timezone 时区的时间", body = ApiResponse<LoginTrend>),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn get_login_trend(
+    Extension(_claims): Extension<AdminClaims>,
+    ApiQuery(query): ApiQuery<LoginTrendQuery>,
+) -> Result<Json<ApiResponse<LoginTrend>>, ApiError> {
+    let db_pool = db::get_db_pool().await;
+    
+    let to = query.to.unwrap_or_else(|| chrono::Utc::now().naive_utc());
+    let from = query.from.unwrap_or(to - query.granularity.default_span());
+    if from >= to {
+        return Err(ApiError::InvalidTimeRange);
+    }
+    
+    let timezone = &config::get().analytics.timezone;
+    let periods = db::login_time_series(db_pool, query.granularity, from, to, timezone)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to fetch login trend");
+            ApiError::DatabaseError
+        })?;
+    
+    Ok(Json(ApiResponse::success(LoginTrend {
+        timezone: timezone.clone(),
+        periods,
+    })))
+}
+
+// 登录高峰时段查询参数
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PeakHoursQuery {
+    pub days: Option<i64>,
+}
+
+// 按小时统计最近若干天的登录次数，小时按 analytics.timezone 时区计算
+#[utoipa::path(
+    get,
+    path = "/api/admin/analytics/peak-hours",
+    tag = "admin",
+    summary = "登录高峰时段",
+    params(PeakHoursQuery),
+    responses(
+        (status = 200, description = "成功，hour 为 timezone 时区的小时", body = ApiResponse<PeakHours>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn get_login_peak_hours(
+    Extension(_claims): Extension<AdminClaims>,
+    ApiQuery(query): ApiQuery<PeakHoursQuery>,
+) -> Result<Json<ApiResponse<PeakHours>>, ApiError> {
+    let db_pool = db::get_db_pool().await;
+    
+    let days = query.days.unwrap_or(30).clamp(1, 365);
+    let since = chrono::Utc::now().naive_utc() - chrono::Duration::days(days);
+    let timezone = &config::get().analytics.timezone;
+    let hours = db::login_peak_hours(db_pool, since, timezone)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to fetch login peak hours");
+            ApiError::DatabaseError
+        })?;
+    
+    Ok(Json(ApiResponse::success(PeakHours {
+        timezone: timezone.clone(),
+        hours,
+    })))
+}
+
+// 刷新管理员会话（refresh token 单次有效，每次刷新都会轮换）
+#[utoipa::path(
+    post,
+    path = "/api/admin/refresh",
+    tag = "admin-auth",
+    summary = "刷新管理员会话",
+    request_body = AdminRefreshRequest,
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<AdminLoginResponse>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+)]
+pub async fn admin_refresh(
+    ApiJson(params): ApiJson<AdminRefreshRequest>,
+) -> Result<Json<ApiResponse<AdminLoginResponse>>, ApiError> {
+    let db_pool = db::get_db_pool().await;
+    let invalid = || ApiError::LoginRequired;
+    
+    let token_hash = hash_refresh_token(&params.refresh_token);
+    let (admin_id, token_version) = db::consume_admin_refresh_token(db_pool, &token_hash)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to consume admin refresh token");
+            ApiError::DatabaseError
+        })?
+        .ok_or_else(invalid)?;
+    
+    let admin = match db::get_admin_by_id(db_pool, admin_id).await {
+        Ok(Some(admin)) if admin.is_active && admin.token_version == token_version => admin,
+        Ok(_) => return Err(invalid()),
+        Err(_) => return Err(ApiError::DatabaseError),
+    };
+    
+    let response = issue_admin_session(&admin).await?;
+    Ok(Json(ApiResponse::success(response)))
+}
+
+// 注销当前会话（吊销提交的 refresh token）
+#[utoipa::path(
+    post,
+    path = "/api/admin/logout",
+    tag = "admin-auth",
+    summary = "注销当前会话",
+    request_body = AdminRefreshRequest,
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 401, description = "未登录、凭证失效或 refresh token 不属于当前管理员", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn admin_logout(
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiJson(params): ApiJson<AdminRefreshRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let db_pool = db::get_db_pool().await;
+    let admin_id = claims.admin_id().unwrap_or_default();
+    // 只能吊销自己的 refresh token
+    let revoked = db::revoke_admin_refresh_token(db_pool, admin_id, &hash_refresh_token(&params.refresh_token))
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to revoke admin refresh token");
+            ApiError::DatabaseError
+        })?;
+    if !revoked {
+        return Err(ApiError::LoginRequired);
+    }
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("admin.logout").target(format!("admin:{}", admin_id)),
+    )
+    .await;
+    
+    Ok(Json(ApiResponse::success(())))
+}
+
+// 在所有设备上登出
+#[utoipa::path(
+    post,
+    path = "/api/admin/logout-all",
+    tag = "admin-auth",
+    summary = "在所有设备上登出",
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn admin_logout_all(
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let admin_id = claims.admin_id().unwrap_or_default();
+    revoke_admin_sessions(admin_id).await?;
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("admin.logout_all").target(format!("admin:{}", admin_id)),
+    )
+    .await;
+    
+    Ok(Json(ApiResponse::success(())))
+}
+
+// 定时任务状态
+#[utoipa::path(
+    get,
+    path = "/api/admin/jobs",
+    tag = "admin",
+    summary = "获取定时任务状态",
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<Vec<JobStatus>>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn list_jobs(
+    Extension(_claims): Extension<AdminClaims>,
+) -> Result<Json<ApiResponse<Vec<JobStatus>>>, ApiError> {
+    let jobs = scheduler::job_statuses().await.map_err(|e| {
+        error!(error = %e, "Failed to fetch scheduled jobs");
+        ApiError::DatabaseError
+    })?;
+    
+    Ok(Json(ApiResponse::success(jobs)))
+}
+
+// 手动触发定时任务，由下一个检查到触发请求的实例尽快执行
+#[utoipa::path(
+    post,
+    path = "/api/admin/jobs/{name}/run",
+    tag = "admin",
+    summary = "手动触发定时任务",
+    params(("name" = JobName, Path, description = "任务名")),
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 404, description = "资源不存在", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn run_job(
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiPath(name): ApiPath<String>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let job = JobName::parse(&name).ok_or(ApiError::JobNotFound)?;
+    
+    let requested = scheduler::trigger(job).await.map_err(|e| {
+        error!(job = job.as_str(), error = %e, "Failed to trigger scheduled job");
+        ApiError::DatabaseError
+    })?;
+    if !requested {
+        return Err(ApiError::JobNotFound);
+    }
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("job.run").target(format!("job:{}", job.as_str())),
+    )
+    .await;
+    
+    Ok(Json(ApiResponse::success(())))
+}
+
+// 发布公告
+#[derive(Deserialize, ToSchema)]
+pub struct PublishAnnouncementRequest {
+    pub title: String,
+    pub content: String,
+}
+
+// 向所有学生推送公告，在线的学生通过事件流立即收到，断线重连后补发
+#[utoipa::path(
+    post,
+    path = "/api/admin/announcements",
+    tag = "admin",
+    summary = "发布公告",
+    request_body = PublishAnnouncementRequest,
+    responses(
+        (status = 200, description = "成功，返回事件ID", body = ApiResponse<i64>),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("admin_token" = [])),
+)]
+pub async fn publish_announcement(
+    Extension(claims): Extension<AdminClaims>,
+    ip: ClientIp,
+    ApiJson(params): ApiJson<PublishAnnouncementRequest>,
+) -> Result<Json<ApiResponse<i64>>, ApiError> {
+    let title = params.title.trim().to_string();
+    if title.is_empty() {
+        return Err(ApiError::InvalidRequest);
+    }
+    
+    let event = StudentEvent::Announcement { title, content: params.content };
+    let id = events::broadcast(&event).await.map_err(|e| {
+        error!(error = %e, "Failed to publish announcement");
+        ApiError::DatabaseError
+    })?;
+    
+    audit::record(
+        &claims,
+        &ip,
+        AuditEvent::new("announcement.publish")
+            .target(format!("event:{}", id))
+            .after(serde_json::json!(event)),
+    )
+    .await;
+    
+    Ok(Json(ApiResponse::success(id)))
+}
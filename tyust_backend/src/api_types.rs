@@ -1,15 +1,21 @@
 use crate::entity::{Kb, ScoreItem};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 // 重新导出FullUserInfo作为UserInfo以保持API兼容性
 pub use crate::entity::UserLoginInfo as UserInfo;
 
 /// 统一API响应格式
-#[derive(Debug, Serialize)]
+///
+/// code 为 0 表示成功；失败时 code 与 HTTP 状态码相同，data 为 null，
+/// error 为错误码（见 `error::ApiError`），message 为按 Accept-Language 翻译的说明。
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ApiResponse<T> {
     pub code: i32,
     pub message: String,
     pub data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'static str>,
 }
 
 impl<T> ApiResponse<T> {
@@ -18,24 +24,77 @@ impl<T> ApiResponse<T> {
             code: 0,
             message: "success".to_string(),
             data: Some(data),
+            error: None,
         }
     }
+}
 
-    pub fn error(code: i32, message: String) -> Self {
-        Self {
-            code,
-            message,
-            data: None,
-        }
-    }
+/// 不带数据的响应（错误响应和无返回数据的操作），即 `ApiResponse<()>`
+// 仅用于生成接口文档，不会被构造
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct EmptyResponse {
+    pub code: i32, // 0 表示成功，否则与 HTTP 状态码相同
+    pub message: String,
+    #[schema(value_type = Option<Object>)]
+    pub data: Option<()>, // 始终为 null
+    pub error: Option<String>, // 错误码，成功时不返回
+}
+
+/// 分页响应数据
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PageResponse<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
 }
 
 /// 登录请求参数
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct LoginParams {
     #[serde(rename = "stuId")]
     pub student_id: String,
     pub password: String,
+    #[serde(rename = "wechatCode", default)]
+    pub wechat_code: Option<String>, // wx.login 得到的 code，登录成功后将该微信绑定到学号
+}
+
+/// 微信登录请求参数
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct WechatLoginParams {
+    pub code: String, // wx.login 得到的 code
+}
+
+/// 登录响应数据（用户信息 + 刷新凭证）
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LoginResponse {
+    #[serde(flatten)]
+    pub user: UserInfo,
+    pub token: String, // 本次登录会话的 access token
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    #[serde(rename = "expiresIn")]
+    pub expires_in: i64, // access token 有效期（秒）
+    #[serde(rename = "wechatBound")]
+    pub wechat_bound: bool, // 是否已绑定微信（登录时提供了 wechatCode 且绑定成功）
+}
+
+/// 刷新凭证请求参数
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RefreshTokenParams {
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+}
+
+/// 刷新凭证响应数据
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenResponse {
+    pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    #[serde(rename = "expiresIn")]
+    pub expires_in: i64, // access token 有效期（秒）
 }
 
 /// 验证码登录请求参数
@@ -52,7 +111,7 @@ pub struct LoginWithVerifyParams {
 }
 
 /// 登录初始化响应数据
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct LoginInitData {
     pub cookie: String,
     #[serde(rename = "formData")]
@@ -66,7 +125,7 @@ pub struct LoginWithVerifyData {
 }
 
 /// 课程信息（转换自Kb结构）
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Course {
     pub id: String,
     pub name: String,
@@ -185,13 +244,15 @@ fn parse_weeks_range(zcd: &str) -> Vec<i32> {
 }
 
 /// 获取课表请求参数
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ScheduleParams {
     pub week: Option<i32>,
 }
 
 /// 获取原始成绩请求参数
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct RawScoresParams {
     pub xh_id: Option<String>, // 学号ID
     pub xnm: Option<String>,   // 学年码
@@ -199,38 +260,135 @@ pub struct RawScoresParams {
 }
 
 /// 开学时间配置
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct SemesterConfig {
     pub semester_start_date: String, // 开学日期，格式: "2024-02-26"
     pub semester_name: String,       // 学期名称，如"2023-2024学年第二学期"
 }
 
 /// 设置开学时间的请求参数
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SetSemesterStartRequest {
     pub start_date: String,    // 开学日期，格式: "2024-02-26"
     pub semester_name: String, // 学期名称
 }
 
+/// 学生会话列表项
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StudentSessionInfo {
+    #[serde(flatten)]
+    pub session: crate::entity::StudentSessionRecord,
+    pub current: bool, // 是否为当前请求所用的会话
+}
+
 /// JWT Claims
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // 学号
+    pub jti: String, // 会话ID，对应 student_sessions.id
     pub exp: usize,  // 过期时间
     pub iat: usize,  // 签发时间
 }
 
 /// 管理员 JWT Claims
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AdminClaims {
     pub sub: String,      // 管理员ID
     pub username: String, // 管理员用户名
+    pub role: AdminRole,  // 管理员角色
+    pub permissions: Vec<AdminPermission>, // 角色对应的权限
+    pub ver: i32,         // token 版本，与 admins.token_version 不一致时 token 失效
     pub exp: usize,       // 过期时间
     pub iat: usize,       // 签发时间
 }
 
+impl AdminClaims {
+    /// 管理员ID（数字形式）
+    pub fn admin_id(&self) -> Option<i32> {
+        self.sub.parse().ok()
+    }
+
+    /// 是否拥有指定权限
+    pub fn has_permission(&self, permission: AdminPermission) -> bool {
+        self.permissions.contains(&permission)
+    }
+}
+
+/// 管理员角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminRole {
+    SuperAdmin, // 超级管理员，拥有全部权限
+    Operator,   // 运营人员，负责学期设置
+    Counselor,  // 辅导员，只能查看统计信息
+}
+
+impl AdminRole {
+    /// 数据库中存储的角色名
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminRole::SuperAdmin => "super_admin",
+            AdminRole::Operator => "operator",
+            AdminRole::Counselor => "counselor",
+        }
+    }
+
+    /// 从数据库中存储的角色名解析
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "super_admin" => Some(AdminRole::SuperAdmin),
+            "operator" => Some(AdminRole::Operator),
+            "counselor" => Some(AdminRole::Counselor),
+            _ => None,
+        }
+    }
+
+    /// 角色拥有的权限
+    pub fn permissions(&self) -> Vec<AdminPermission> {
+        use AdminPermission::*;
+        match self {
+            AdminRole::SuperAdmin => vec![
+                ViewStudents,
+                ViewStatistics,
+                ViewSemester,
+                ManageSemester,
+                ManageAdmins,
+                ManageOwnAccount,
+                ViewAuditLog,
+                ManageStudents,
+                ManageJobs,
+                PublishAnnouncements,
+            ],
+            AdminRole::Operator => vec![
+                ViewStatistics,
+                ViewSemester,
+                ManageSemester,
+                ManageOwnAccount,
+                PublishAnnouncements,
+            ],
+            AdminRole::Counselor => vec![ViewStatistics, ManageOwnAccount],
+        }
+    }
+}
+
+/// 管理员权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminPermission {
+    ViewStudents,     // 查看学生列表
+    ViewStatistics,   // 查看统计信息
+    ViewSemester,     // 查看学期配置
+    ManageSemester,   // 修改学期配置
+    ManageAdmins,     // 管理管理员账户
+    ManageOwnAccount, // 修改自己的用户名和密码
+    ViewAuditLog,     // 查看审计日志
+    ManageStudents,   // 强制学生登出、禁用/启用学生
+    ManageJobs,       // 查看和手动触发定时任务
+    PublishAnnouncements, // 向学生推送公告
+}
+
 /// 成绩信息（API响应格式）
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Score {
     pub semester: String, // 学期，如"2024-2025学年第一学期"
     pub course: String,   // 课程名称
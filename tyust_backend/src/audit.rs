@@ -0,0 +1,195 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+use serde_json::Value;
+use tracing::error;
+
+use crate::{api_types::AdminClaims, config, db, entity::NewAuditLog};
+
+/// 请求来源IP
+///
+/// 仅当对端是 `server.trusted_proxies` 中的反向代理时才采用代理设置的头部，否则使用对端地址，
+/// 避免客户端伪造 X-Forwarded-For 绕过限流或篡改审计日志。
+#[derive(Debug, Clone, Default)]
+pub struct ClientIp(pub Option<String>);
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let server = &config::get().server;
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| client_ip(addr.ip(), &parts.headers, |ip| server.is_trusted_proxy(ip)));
+        Ok(ClientIp(ip.map(|ip| ip.to_string())))
+    }
+}
+
+/// 根据对端地址和代理头部确定客户端IP，对端不是可信代理时忽略头部
+fn client_ip(peer: IpAddr, headers: &HeaderMap, is_trusted: impl Fn(IpAddr) -> bool) -> IpAddr {
+    let peer = peer.to_canonical();
+    if !is_trusted(peer) {
+        return peer;
+    }
+    forwarded_ip(headers, is_trusted).unwrap_or(peer)
+}
+
+/// 从 X-Forwarded-For / X-Real-IP 头中获取客户端IP
+///
+/// X-Forwarded-For 从右向左跳过可信代理，第一个不可信的地址即为客户端；
+/// 最左侧的地址由客户端自行填写，只有整条链都是可信代理时才会用到。
+/// 带端口的地址会去掉端口，无法解析的项（如 `unknown`）被跳过。
+fn forwarded_ip(headers: &HeaderMap, is_trusted: impl Fn(IpAddr) -> bool) -> Option<IpAddr> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    if let Some(forwarded_for) = header("x-forwarded-for") {
+        let chain: Vec<IpAddr> = forwarded_for.split(',').filter_map(parse_forwarded_addr).collect();
+        return chain
+            .iter()
+            .rev()
+            .find(|ip| !is_trusted(**ip))
+            .or(chain.first())
+            .copied();
+    }
+    header("x-real-ip").and_then(parse_forwarded_addr)
+}
+
+/// 解析代理头部中的地址，支持 `1.2.3.4`、`1.2.3.4:80`、`::1`、`[::1]` 和 `[::1]:80`
+fn parse_forwarded_addr(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| value.strip_prefix('[')?.strip_suffix(']')?.parse::<IpAddr>().ok())
+        .map(|ip| ip.to_canonical())
+}
+
+/// 审计日志中的一次操作
+pub struct AuditEvent<'a> {
+    pub action: &'a str,
+    pub target: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl<'a> AuditEvent<'a> {
+    pub fn new(action: &'a str) -> Self {
+        Self {
+            action,
+            target: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn before(mut self, before: Value) -> Self {
+        self.before = Some(before);
+        self
+    }
+
+    pub fn after(mut self, after: Value) -> Self {
+        self.after = Some(after);
+        self
+    }
+}
+
+/// 记录管理员操作
+///
+/// 审计日志写入失败不会影响业务请求的结果，只输出错误日志。
+pub async fn record(claims: &AdminClaims, ip: &ClientIp, event: AuditEvent<'_>) {
+    record_as(claims.admin_id(), &claims.username, ip, event).await;
+}
+
+/// 以指定管理员身份记录操作（用于尚未持有 token 的登录请求）
+pub async fn record_as(admin_id: Option<i32>, username: &str, ip: &ClientIp, event: AuditEvent<'_>) {
+    let entry = NewAuditLog {
+        admin_id,
+        admin_username: Some(username.to_string()),
+        action: event.action.to_string(),
+        target: event.target,
+        before: event.before,
+        after: event.after,
+        ip: ip.0.clone(),
+    };
+
+    let db_pool = db::get_db_pool().await;
+    if let Err(e) = db::insert_audit_log(db_pool, &entry).await {
+        error!(action = %entry.action, error = %e, "Failed to write audit log");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (axum::http::HeaderName::from_static(name), value.parse().unwrap()))
+            .collect()
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    /// 10.0.0.0/8 为可信代理
+    fn is_trusted(ip: IpAddr) -> bool {
+        matches!(ip, IpAddr::V4(v4) if v4.octets()[0] == 10)
+    }
+
+    #[test]
+    fn spoofed_leftmost_entry_is_ignored() {
+        let request = headers(&[("x-forwarded-for", "6.6.6.6, 1.2.3.4, 10.0.0.2")]);
+        assert_eq!(client_ip(ip("10.0.0.1"), &request, is_trusted), ip("1.2.3.4"));
+    }
+
+    #[test]
+    fn chain_of_trusted_proxies_uses_leftmost_entry() {
+        let request = headers(&[("x-forwarded-for", "10.0.0.3, 10.0.0.2")]);
+        assert_eq!(client_ip(ip("10.0.0.1"), &request, is_trusted), ip("10.0.0.3"));
+    }
+
+    #[test]
+    fn untrusted_peer_headers_are_ignored() {
+        let request = headers(&[("x-forwarded-for", "1.2.3.4"), ("x-real-ip", "1.2.3.4")]);
+        assert_eq!(client_ip(ip("5.6.7.8"), &request, is_trusted), ip("5.6.7.8"));
+    }
+
+    #[test]
+    fn entries_with_ports_are_stripped() {
+        let request = headers(&[("x-forwarded-for", "1.2.3.4:5678")]);
+        assert_eq!(client_ip(ip("10.0.0.1"), &request, is_trusted), ip("1.2.3.4"));
+        let request = headers(&[("x-forwarded-for", "[2001:db8::1]:443, 10.0.0.2")]);
+        assert_eq!(client_ip(ip("10.0.0.1"), &request, is_trusted), ip("2001:db8::1"));
+    }
+
+    #[test]
+    fn unparsable_entries_are_skipped() {
+        let request = headers(&[("x-forwarded-for", "1.2.3.4, unknown")]);
+        assert_eq!(client_ip(ip("10.0.0.1"), &request, is_trusted), ip("1.2.3.4"));
+        let request = headers(&[("x-forwarded-for", "unknown")]);
+        assert_eq!(client_ip(ip("10.0.0.1"), &request, is_trusted), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn real_ip_is_used_without_forwarded_for() {
+        let request = headers(&[("x-real-ip", " 1.2.3.4 ")]);
+        assert_eq!(client_ip(ip("10.0.0.1"), &request, is_trusted), ip("1.2.3.4"));
+    }
+
+    #[test]
+    fn ipv4_mapped_peer_is_canonicalized() {
+        let request = headers(&[("x-forwarded-for", "1.2.3.4")]);
+        assert_eq!(client_ip(ip("::ffff:10.0.0.1"), &request, is_trusted), ip("1.2.3.4"));
+    }
+}
@@ -1,19 +1,14 @@
 use axum::{
     extract::Request,
-    http::{HeaderMap, StatusCode},
+    http::HeaderMap,
     middleware::Next,
     response::Response,
-    Json,
 };
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
-use once_cell::sync::Lazy;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
 
-use crate::api_types::{ApiResponse, Claims, UserInfo};
-
-/// JWT密钥
-static JWT_SECRET: &str = "tyust_course_system_secret_key_2024";
+use crate::api_types::{Claims, UserInfo};
+use crate::cache::{self, CacheKey, CacheKind};
+use crate::entity::SessionDevice;
+use crate::error::ApiError;
 
 /// 用户认证缓存信息
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -28,94 +23,168 @@ pub struct UserAuthCache {
     pub cached_at: chrono::DateTime<chrono::Utc>,
 }
 
-/// 用户会话存储（简单内存存储，生产环境应使用Redis等）
-static USER_SESSIONS: Lazy<Arc<Mutex<HashMap<String, UserInfo>>>> = 
-    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
-
-/// 用户认证缓存存储
-static USER_AUTH_CACHE: Lazy<Arc<Mutex<HashMap<String, UserAuthCache>>>> = 
-    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
-
-/// 生成JWT Token
-pub fn generate_token(student_id: &str) -> Result<String, jsonwebtoken::errors::Error> {
+/// 生成JWT Token（短期 access token，有效期见 `auth.student_access_token_ttl_secs`）
+pub fn generate_token(student_id: &str, session_id: &str) -> Result<String, jsonwebtoken::errors::Error> {
     let now = chrono::Utc::now();
-    let exp = (now + chrono::Duration::hours(24)).timestamp() as usize;
+    let ttl = chrono::Duration::seconds(crate::config::get().auth.student_access_token_ttl_secs);
+    let exp = (now + ttl).timestamp() as usize;
     let iat = now.timestamp() as usize;
 
     let claims = Claims {
         sub: student_id.to_string(),
+        jti: session_id.to_string(),
         exp,
         iat,
     };
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_ref()),
+    crate::jwt_keys::student().encode(&claims)
+}
+
+/// 生成随机 refresh token
+pub fn generate_refresh_token() -> String {
+    use rand::RngCore;
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// refresh token 只以 SHA-256 摘要形式存储
+pub fn hash_refresh_token(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// 学生登录凭证
+pub struct StudentTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_in: i64, // access token 有效期（秒）
+}
+
+/// 学生 refresh token 的过期时间
+fn student_refresh_expires_at() -> chrono::NaiveDateTime {
+    let ttl = chrono::Duration::days(crate::config::get().auth.student_refresh_token_ttl_days);
+    (chrono::Utc::now() + ttl).naive_utc()
+}
+
+/// 登录成功后创建新会话，签发 access token 和该会话的 refresh token
+pub async fn issue_student_tokens(student_id: &str, device: &SessionDevice) -> Result<StudentTokens, String> {
+    // 会话ID同时作为 access token 的 jti 和 refresh token 的 family_id
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let expires_at = student_refresh_expires_at();
+
+    let db_pool = crate::db::get_db_pool().await;
+    crate::db::create_student_session(db_pool, &session_id, student_id, device, expires_at)
+        .await
+        .map_err(|e| format!("Failed to create session: {}", e))?;
+
+    let access_token = generate_token(student_id, &session_id)
+        .map_err(|e| format!("Failed to generate token: {}", e))?;
+    let refresh_token = generate_refresh_token();
+    crate::db::save_student_refresh_token(
+        db_pool,
+        student_id,
+        &session_id,
+        &hash_refresh_token(&refresh_token),
+        expires_at,
+    )
+    .await
+    .map_err(|e| format!("Failed to save refresh token: {}", e))?;
+
+    Ok(StudentTokens {
+        access_token,
+        refresh_token,
+        expires_in: crate::config::get().auth.student_access_token_ttl_secs,
+    })
+}
+
+/// 轮换 refresh token，返回新 refresh token 及数据库中的轮换结果
+pub async fn rotate_student_refresh_token(
+    refresh_token: &str,
+) -> Result<(String, crate::db::RefreshRotation), sqlx::Error> {
+    let new_refresh_token = generate_refresh_token();
+    let db_pool = crate::db::get_db_pool().await;
+    let rotation = crate::db::rotate_student_refresh_token(
+        db_pool,
+        &hash_refresh_token(refresh_token),
+        &hash_refresh_token(&new_refresh_token),
+        student_refresh_expires_at(),
     )
+    .await?;
+    Ok((new_refresh_token, rotation))
 }
 
 /// 验证JWT Token
 pub fn verify_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.as_ref()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
+    crate::jwt_keys::student()
+        .decode::<Claims>(token)
+        .map(|data| data.claims)
 }
 
-/// 存储用户会话
-pub fn store_user_session(student_id: String, user_info: UserInfo) {
-    let mut sessions = USER_SESSIONS.lock().unwrap();
-    sessions.insert(student_id, user_info);
+/// 当前请求所属的学生会话，由 `auth_middleware` 注入请求扩展
+#[derive(Debug, Clone)]
+pub struct StudentSession {
+    pub student_id: String,
+    pub session_id: String,
 }
 
-/// 获取用户会话
-pub fn get_user_session(student_id: &str) -> Option<UserInfo> {
-    let sessions = USER_SESSIONS.lock().unwrap();
-    sessions.get(student_id).cloned()
+/// 会话状态，按会话缓存（有效期见 `cache.session_ttl_secs`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SessionState {
+    Active,
+    Revoked,  // 已注销、已过期或不属于该学生
+    Disabled, // 学生被管理员禁用
 }
 
-/// 删除用户会话
-pub fn remove_user_session(student_id: &str) {
-    let mut sessions = USER_SESSIONS.lock().unwrap();
-    sessions.remove(student_id);
+/// 获取会话状态（优先读取缓存）
+///
+/// 注销会话、禁用学生时需调用 `cache::invalidate(CacheKind::Session, ..)` 使缓存立即失效。
+pub async fn load_session_state(student_id: &str, session_id: &str) -> Result<SessionState, sqlx::Error> {
+    let db_pool = crate::db::get_db_pool().await;
+    cache::cached(&CacheKey::with_suffix(CacheKind::Session, student_id, &[session_id]), || async {
+        Ok(match crate::db::check_student_session(db_pool, session_id, student_id).await? {
+            None => SessionState::Revoked,
+            Some(true) => SessionState::Disabled,
+            Some(false) => SessionState::Active,
+        })
+    })
+    .await
 }
 
-/// 存储用户认证缓存
-pub fn store_user_auth_cache(student_id: String, auth_cache: UserAuthCache) {
-    let mut cache = USER_AUTH_CACHE.lock().unwrap();
-    cache.insert(student_id, auth_cache);
+/// 检查 token 对应的会话未被注销、学生未被禁用
+pub async fn check_student_session(claims: &Claims) -> Result<(), ApiError> {
+    match load_session_state(&claims.sub, &claims.jti).await {
+        Ok(SessionState::Active) => Ok(()),
+        Ok(SessionState::Revoked) => Err(ApiError::SessionRevoked),
+        // 被管理员禁用的学生即使持有有效token也不能访问
+        Ok(SessionState::Disabled) => Err(ApiError::AccountDisabled),
+        Err(_) => Err(ApiError::DatabaseError),
+    }
 }
 
-/// 获取用户认证缓存
-pub fn get_user_auth_cache(student_id: &str) -> Option<UserAuthCache> {
-    let cache = USER_AUTH_CACHE.lock().unwrap();
-    cache.get(student_id).cloned()
+/// 获取学生资料（优先读取缓存）
+pub async fn load_user(student_id: &str) -> Result<Option<UserInfo>, sqlx::Error> {
+    let db_pool = crate::db::get_db_pool().await;
+    cache::cached(&CacheKey::new(CacheKind::User, student_id), || {
+        crate::db::get_user(db_pool, student_id)
+    })
+    .await
 }
 
-/// 删除用户认证缓存
-pub fn remove_user_auth_cache(student_id: &str) {
-    let mut cache = USER_AUTH_CACHE.lock().unwrap();
-    cache.remove(student_id);
+/// 获取上游认证缓存（优先读取缓存）
+pub async fn load_auth_cache(student_id: &str) -> Result<Option<UserAuthCache>, sqlx::Error> {
+    let db_pool = crate::db::get_db_pool().await;
+    cache::cached(&CacheKey::new(CacheKind::Auth, student_id), || {
+        crate::db::get_auth_cache(db_pool, student_id)
+    })
+    .await
 }
 
-/// 检查认证缓存是否有效（24小时内）
+/// 检查认证缓存是否有效（有效期见 `auth.auth_cache_ttl_hours` 配置，与清理任务一致）
 pub fn is_auth_cache_valid(auth_cache: &UserAuthCache) -> bool {
     let now = chrono::Utc::now();
     let duration = now.signed_duration_since(auth_cache.cached_at);
-    duration.num_hours() < 24
-}
-
-/// 清理过期的认证缓存
-pub fn cleanup_expired_auth_cache() {
-    let mut cache = USER_AUTH_CACHE.lock().unwrap();
-    let now = chrono::Utc::now();
-    cache.retain(|_, auth_cache| {
-        let duration = now.signed_duration_since(auth_cache.cached_at);
-        duration.num_hours() < 24
-    });
+    duration < chrono::Duration::hours(crate::config::get().auth.auth_cache_ttl_hours)
 }
 
 /// JWT认证中间件
@@ -123,7 +192,7 @@ pub async fn auth_middleware(
     headers: HeaderMap,
     mut request: Request,
     next: Next,
-) -> Result<Response, (StatusCode, Json<ApiResponse<()>>)> {
+) -> Result<Response, ApiError> {
     // 首先尝试从Authorization头获取token（标准方式）
     let auth_header = headers
         .get("authorization")
@@ -143,10 +212,7 @@ pub async fn auth_middleware(
             match headers.get("token").and_then(|header| header.to_str().ok()) {
                 Some(token) => token,
                 None => {
-                    return Err((
-                        StatusCode::UNAUTHORIZED,
-                        Json(ApiResponse::error(401, "Missing authorization token".to_string())),
-                    ));
+                    return Err(ApiError::MissingToken);
                 }
             }
         }
@@ -156,14 +222,18 @@ pub async fn auth_middleware(
     let claims = match verify_token(token) {
         Ok(claims) => claims,
         Err(_) => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(401, "Invalid token".to_string())),
-            ));
+            return Err(ApiError::InvalidToken);
         }
     };
 
-    // 将用户ID添加到请求扩展中
+    // 已注销的会话或被禁用的学生不能继续使用未过期的token
+    check_student_session(&claims).await?;
+
+    // 将用户ID和会话信息添加到请求扩展中
+    request.extensions_mut().insert(StudentSession {
+        student_id: claims.sub.clone(),
+        session_id: claims.jti,
+    });
     request.extensions_mut().insert(claims.sub);
 
     Ok(next.run(request).await)
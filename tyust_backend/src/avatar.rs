@@ -0,0 +1,270 @@
+use std::io::Cursor;
+
+use axum::{
+    extract::{FromRequest, Multipart, Request},
+    http::header,
+    Json,
+};
+use base64::{engine::general_purpose, Engine as _};
+use image::{imageops::FilterType, DynamicImage, ImageFormat, ImageReader, Limits};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::{config::{self, AvatarConfig}, error::ApiError, storage};
+
+/// 头像在文件存储中的键前缀
+pub const AVATAR_KEY_PREFIX: &str = "avatars";
+
+const JPEG_CONTENT_TYPE: &str = "image/jpeg";
+
+/// 重新编码时的 JPEG 质量
+const JPEG_QUALITY: u8 = 85;
+
+/// 解码图片时允许分配的最大内存
+const MAX_DECODE_ALLOC: u64 = 64 * 1024 * 1024;
+
+/// 更新头像请求参数（JSON 方式）
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct UpdateAvatarParams {
+    #[serde(rename = "avatarData")]
+    pub avatar_data: String, // base64 数据，可带 data URL 前缀
+}
+
+/// multipart 上传的表单，字段名也可以是 file
+// 仅用于生成接口文档，不会被构造
+#[derive(utoipa::ToSchema)]
+#[allow(dead_code)]
+pub struct AvatarForm {
+    #[schema(value_type = String, format = Binary)]
+    pub avatar: Vec<u8>,
+}
+
+/// 上传的头像原始数据
+///
+/// 支持 multipart/form-data（字段名 avatar 或 file）和 JSON base64 两种方式，
+/// 两种方式都在读取过程中检查大小限制。
+pub struct AvatarUpload(pub Vec<u8>);
+
+impl<S: Send + Sync> FromRequest<S> for AvatarUpload {
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let max_bytes = config::get().avatar.max_upload_bytes;
+        let is_multipart = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+        if is_multipart {
+            let mut multipart = Multipart::from_request(request, state)
+                .await
+                .map_err(|_| ApiError::InvalidRequest)?;
+            while let Some(mut field) = multipart
+                .next_field()
+                .await
+                .map_err(|_| ApiError::InvalidRequest)?
+            {
+                if !matches!(field.name(), Some("avatar") | Some("file")) {
+                    continue;
+                }
+                let mut data = Vec::new();
+                while let Some(chunk) = field
+                    .chunk()
+                    .await
+                    .map_err(|_| ApiError::InvalidRequest)?
+                {
+                    if data.len() + chunk.len() > max_bytes {
+                        return Err(ApiError::AvatarTooLarge);
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+                return Ok(AvatarUpload(data));
+            }
+            Err(ApiError::AvatarMissing)
+        } else {
+            let Json(params) = Json::<UpdateAvatarParams>::from_request(request, state)
+                .await
+                .map_err(|_| ApiError::AvatarInvalid)?;
+            decode_base64(&params.avatar_data, max_bytes).map(AvatarUpload)
+        }
+    }
+}
+
+/// 解码 base64 数据，先按编码长度估算大小，避免解码超大数据
+fn decode_base64(data: &str, max_bytes: usize) -> Result<Vec<u8>, ApiError> {
+    let encoded = match data.strip_prefix("data:") {
+        Some(data_url) => data_url
+            .split_once(',')
+            .map(|(_, encoded)| encoded)
+            .ok_or(ApiError::AvatarInvalid)?,
+        None => data,
+    }
+    .trim();
+
+    if encoded.len() / 4 * 3 > max_bytes + 2 {
+        return Err(ApiError::AvatarTooLarge);
+    }
+    let bytes = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|_| ApiError::AvatarInvalid)?;
+    if bytes.len() > max_bytes {
+        return Err(ApiError::AvatarTooLarge);
+    }
+    Ok(bytes)
+}
+
+/// 处理后的头像
+pub struct ProcessedAvatar {
+    pub hash: String,       // 头像内容摘要，用作文件名
+    pub image: Vec<u8>,     // 统一尺寸的 JPEG
+    pub thumbnail: Vec<u8>, // 缩略图 JPEG
+}
+
+/// 识别并校验图片格式，裁剪缩放到统一尺寸并重新编码，同时生成缩略图
+///
+/// 重新编码会丢弃原图中的 EXIF 等元数据。该函数是 CPU 密集型操作，应在阻塞线程中调用。
+pub fn process(data: &[u8], config: &AvatarConfig) -> Result<ProcessedAvatar, ApiError> {
+    let format = image::guess_format(data).map_err(|_| ApiError::AvatarUnsupportedFormat)?;
+    if !matches!(format, ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP | ImageFormat::Gif) {
+        return Err(ApiError::AvatarUnsupportedFormat);
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(config.max_dimension);
+    limits.max_image_height = Some(config.max_dimension);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    let decoded = reader
+        .decode()
+        .map_err(|_| ApiError::AvatarInvalid)?;
+
+    let avatar = decoded.resize_to_fill(config.size, config.size, FilterType::Lanczos3);
+    let thumbnail = avatar.resize_to_fill(config.thumbnail_size, config.thumbnail_size, FilterType::Lanczos3);
+    let image = encode_jpeg(&avatar)?;
+    let thumbnail = encode_jpeg(&thumbnail)?;
+
+    let hash = format!("{:x}", Sha256::digest(&image))[..16].to_string();
+    Ok(ProcessedAvatar { hash, image, thumbnail })
+}
+
+fn encode_jpeg(image: &DynamicImage) -> Result<Vec<u8>, ApiError> {
+    let mut buffer = Vec::new();
+    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, JPEG_QUALITY);
+    DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_with_encoder(encoder)
+        .map_err(|_| ApiError::InternalError)?;
+    Ok(buffer)
+}
+
+/// 已保存头像的存储键
+pub struct StoredAvatar {
+    pub avatar_key: String,
+    pub thumbnail_key: String,
+}
+
+/// 每个学生的头像目录名，学号包含路径不安全的字符时使用其摘要
+fn user_dir(student_id: &str) -> String {
+    let safe = !student_id.is_empty()
+        && student_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if safe {
+        student_id.to_string()
+    } else {
+        format!("{:x}", Sha256::digest(student_id.as_bytes()))[..32].to_string()
+    }
+}
+
+/// 将头像和缩略图写入 `avatars/<学生目录>/<摘要>.jpg`
+pub async fn store(student_id: &str, avatar: ProcessedAvatar) -> anyhow::Result<StoredAvatar> {
+    let dir = format!("{}/{}", AVATAR_KEY_PREFIX, user_dir(student_id));
+    let avatar_key = format!("{}/{}.jpg", dir, avatar.hash);
+    let thumbnail_key = format!("{}/{}_thumb.jpg", dir, avatar.hash);
+
+    let storage = storage::get();
+    storage.put(&avatar_key, avatar.image, JPEG_CONTENT_TYPE).await?;
+    storage.put(&thumbnail_key, avatar.thumbnail, JPEG_CONTENT_TYPE).await?;
+    Ok(StoredAvatar { avatar_key, thumbnail_key })
+}
+
+/// 删除该学生目录下被替换的旧头像，只保留 keep_hash 对应的文件，返回删除的文件数
+pub async fn collect_garbage(student_id: &str, keep_hash: &str) -> usize {
+    let storage = storage::get();
+    let prefix = format!("{}/{}/", AVATAR_KEY_PREFIX, user_dir(student_id));
+    let keys = match storage.list(&prefix).await {
+        Ok(keys) => keys,
+        Err(e) => {
+            warn!(prefix = %prefix, error = %e, "Failed to list avatars");
+            return 0;
+        }
+    };
+    let mut removed = 0;
+    for key in keys {
+        if key.rsplit('/').next().is_some_and(|name| name.starts_with(keep_hash)) {
+            continue;
+        }
+        match storage.delete(&key).await {
+            Ok(()) => removed += 1,
+            Err(e) => warn!(key = %key, error = %e, "Failed to remove superseded avatar"),
+        }
+    }
+    removed
+}
+
+/// 学生当前头像键中的内容摘要，键不在该学生的头像目录下（如旧版本头像）时返回 None
+pub fn stored_hash<'a>(student_id: &str, key: &'a str) -> Option<&'a str> {
+    key.strip_prefix(AVATAR_KEY_PREFIX)?
+        .strip_prefix('/')?
+        .strip_prefix(user_dir(student_id).as_str())?
+        .strip_prefix('/')?
+        .strip_suffix(".jpg")
+        .filter(|hash| !hash.is_empty() && !hash.contains('/'))
+}
+
+/// 旧版本直接保存在 avatars 目录下的头像（`avatars/avatar_<时间戳>.jpg`）
+pub fn is_legacy_avatar(key: &str) -> bool {
+    key.strip_prefix(AVATAR_KEY_PREFIX)
+        .and_then(|name| name.strip_prefix('/'))
+        .is_some_and(|name| name.starts_with("avatar_") && !name.contains('/'))
+}
+
+/// 将本地 static_dir 中已有的头像上传到当前配置的文件存储，返回上传的文件数
+///
+/// 从本地存储切换到对象存储时执行一次（`tyust migrate avatars`），键保持不变，
+/// 数据库中的存储键无需修改。
+pub async fn upload_local_avatars(static_dir: &str) -> anyhow::Result<usize> {
+    let root = std::path::Path::new(static_dir);
+    let mut pending = vec![root.join(AVATAR_KEY_PREFIX)];
+    let mut uploaded = 0;
+    while let Some(dir) = pending.pop() {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if entry.file_type().await?.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let Some(key) = path
+                .strip_prefix(root)
+                .ok()
+                .and_then(|relative| relative.to_str())
+                .map(|relative| relative.replace(std::path::MAIN_SEPARATOR, "/"))
+            else {
+                continue;
+            };
+            if !key.ends_with(".jpg") {
+                continue;
+            }
+            let data = tokio::fs::read(&path).await?;
+            storage::get().put(&key, data, JPEG_CONTENT_TYPE).await?;
+            uploaded += 1;
+        }
+    }
+    Ok(uploaded)
+}
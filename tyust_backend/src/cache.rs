@@ -0,0 +1,273 @@
+use std::{
+    future::Future,
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use lru::LruCache;
+use once_cell::sync::OnceCell;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::warn;
+
+use crate::config::{self, CacheBackend, CacheConfig};
+
+/// 缓存键的统一前缀，避免与同一 Redis 中的其他数据冲突
+const KEY_PREFIX: &str = "tyust";
+
+static CACHE: OnceCell<Box<dyn CacheStore>> = OnceCell::new();
+
+pub type CacheFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// 缓存存储
+///
+/// 值统一为字符串（JSON），由 `cached` 等函数负责序列化。
+pub trait CacheStore: Send + Sync {
+    fn get<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<String>>;
+    fn set<'a>(&'a self, key: &'a str, value: String, ttl: Duration) -> CacheFuture<'a, ()>;
+    fn delete<'a>(&'a self, key: &'a str) -> CacheFuture<'a, ()>;
+    /// 删除所有以 prefix 开头的键
+    fn delete_prefix<'a>(&'a self, prefix: &'a str) -> CacheFuture<'a, ()>;
+}
+
+/// 缓存的数据类型，每种类型有独立的有效期
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    Auth,      // 上游认证凭证（加密后缓存）
+    User,      // 学生资料
+    Courses,   // 课表
+    Scores,    // 有效成绩
+    RawScores, // 原始成绩，按查询参数区分
+    Session,   // 会话状态，按会话ID区分
+}
+
+impl CacheKind {
+    const ALL: [CacheKind; 6] = [
+        CacheKind::Auth,
+        CacheKind::User,
+        CacheKind::Courses,
+        CacheKind::Scores,
+        CacheKind::RawScores,
+        CacheKind::Session,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheKind::Auth => "auth",
+            CacheKind::User => "user",
+            CacheKind::Courses => "courses",
+            CacheKind::Scores => "scores",
+            CacheKind::RawScores => "raw_scores",
+            CacheKind::Session => "session",
+        }
+    }
+
+    fn ttl(self) -> Duration {
+        let config = &config::get().cache;
+        Duration::from_secs(match self {
+            CacheKind::Auth => config.auth_ttl_secs,
+            CacheKind::User => config.user_ttl_secs,
+            CacheKind::Courses => config.courses_ttl_secs,
+            CacheKind::Scores | CacheKind::RawScores => config.scores_ttl_secs,
+            CacheKind::Session => config.session_ttl_secs,
+        })
+    }
+}
+
+/// 某个学生的一条缓存键
+#[derive(Debug, Clone)]
+pub struct CacheKey {
+    kind: CacheKind,
+    key: String,
+}
+
+impl CacheKey {
+    pub fn new(kind: CacheKind, student_id: &str) -> Self {
+        Self {
+            kind,
+            key: format!("{}:{}:{}", KEY_PREFIX, kind.as_str(), student_id),
+        }
+    }
+
+    /// 同一学生下按参数区分的键，如原始成绩的学年学期
+    pub fn with_suffix(kind: CacheKind, student_id: &str, parts: &[&str]) -> Self {
+        let mut key = Self::new(kind, student_id);
+        for part in parts {
+            key.key.push(':');
+            key.key.push_str(part);
+        }
+        key
+    }
+}
+
+/// 根据配置初始化缓存
+pub async fn init(config: &CacheConfig) -> Result<()> {
+    let store: Box<dyn CacheStore> = match config.backend {
+        CacheBackend::Memory => Box::new(MemoryCache::new(config.memory_capacity)),
+        CacheBackend::Redis => Box::new(crate::redis_cache::RedisCache::connect(&config.redis_url).await?),
+    };
+    CACHE
+        .set(store)
+        .map_err(|_| anyhow!("cache already initialized"))
+}
+
+fn store() -> &'static dyn CacheStore {
+    CACHE.get().expect("Cache not initialized").as_ref()
+}
+
+/// 读取缓存，未命中时调用 load 并写入缓存
+///
+/// 缓存读写失败只记录日志，不影响请求结果；load 返回错误时不写入缓存。
+pub async fn cached<T, E, F, Fut>(key: &CacheKey, load: F) -> Result<T, E>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    if let Some(value) = get(key).await {
+        return Ok(value);
+    }
+    let value = load().await?;
+    set(key, &value).await;
+    Ok(value)
+}
+
+/// 读取一条缓存，未命中或反序列化失败时返回 None
+pub async fn get<T: DeserializeOwned>(key: &CacheKey) -> Option<T> {
+    let ttl = key.kind.ttl();
+    if ttl.is_zero() {
+        return None;
+    }
+
+    let raw = match store().get(&key.key).await {
+        Ok(raw) => raw,
+        Err(e) => {
+            warn!(key = %key.key, error = %e, "Cache read failed");
+            None
+        }
+    };
+    let value = raw.and_then(|raw| decode(key, &raw));
+    crate::metrics::record_cache_lookup(key.kind.as_str(), value.is_some());
+    value
+}
+
+/// 写入一条缓存
+pub async fn set<T: Serialize>(key: &CacheKey, value: &T) {
+    let ttl = key.kind.ttl();
+    if ttl.is_zero() {
+        return;
+    }
+
+    let encoded = match encode(key, value) {
+        Ok(encoded) => encoded,
+        Err(e) => {
+            warn!(key = %key.key, error = %e, "Failed to encode cache value");
+            return;
+        }
+    };
+    if let Err(e) = store().set(&key.key, encoded, ttl).await {
+        warn!(key = %key.key, error = %e, "Cache write failed");
+    }
+}
+
+/// 删除某一类缓存中某个学生的所有条目
+pub async fn invalidate(kind: CacheKind, student_id: &str) {
+    let key = CacheKey::new(kind, student_id);
+    let result = match store().delete(&key.key).await {
+        Ok(()) => store().delete_prefix(&format!("{}:", key.key)).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!(key = %key.key, error = %e, "Cache invalidation failed");
+    }
+}
+
+/// 删除某个学生的所有缓存（登录、登出、强制下线时调用）
+pub async fn invalidate_student(student_id: &str) {
+    for kind in CacheKind::ALL {
+        invalidate(kind, student_id).await;
+    }
+}
+
+/// 上游凭证在缓存中同样加密保存
+fn encode<T: Serialize>(key: &CacheKey, value: &T) -> Result<String> {
+    let json = serde_json::to_string(value)?;
+    if key.kind == CacheKind::Auth {
+        crate::credential_crypto::seal_with_master_key(&key.key, &json)
+    } else {
+        Ok(json)
+    }
+}
+
+fn decode<T: DeserializeOwned>(key: &CacheKey, raw: &str) -> Option<T> {
+    let json = if key.kind == CacheKind::Auth {
+        crate::credential_crypto::open_with_master_key(&key.key, raw).ok()?
+    } else {
+        raw.to_string()
+    };
+    serde_json::from_str(&json).ok()
+}
+
+/// 进程内 LRU 缓存，适用于单实例部署
+pub struct MemoryCache {
+    entries: Mutex<LruCache<String, (String, Instant)>>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).expect("cache capacity validated at startup");
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+}
+
+impl CacheStore for MemoryCache {
+    fn get<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<String>> {
+        Box::pin(async move {
+            let mut entries = self.entries.lock().unwrap();
+            match entries.get(key) {
+                Some((value, expires_at)) if *expires_at > Instant::now() => Ok(Some(value.clone())),
+                Some(_) => {
+                    entries.pop(key);
+                    Ok(None)
+                }
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn set<'a>(&'a self, key: &'a str, value: String, ttl: Duration) -> CacheFuture<'a, ()> {
+        Box::pin(async move {
+            self.entries
+                .lock()
+                .unwrap()
+                .put(key.to_string(), (value, Instant::now() + ttl));
+            Ok(())
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> CacheFuture<'a, ()> {
+        Box::pin(async move {
+            self.entries.lock().unwrap().pop(key);
+            Ok(())
+        })
+    }
+
+    fn delete_prefix<'a>(&'a self, prefix: &'a str) -> CacheFuture<'a, ()> {
+        Box::pin(async move {
+            let mut entries = self.entries.lock().unwrap();
+            let keys: Vec<String> = entries
+                .iter()
+                .filter(|(key, _)| key.starts_with(prefix))
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in keys {
+                entries.pop(&key);
+            }
+            Ok(())
+        })
+    }
+}
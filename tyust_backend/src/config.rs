@@ -0,0 +1,1256 @@
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use axum::http::HeaderValue;
+use ipnet::IpNet;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower_http::cors::{Any, CorsLayer};
+
+/// 默认配置文件路径，可通过 TYUST_CONFIG 环境变量指定其他文件
+const DEFAULT_CONFIG_FILE: &str = "config.json";
+
+/// 环境变量覆盖的前缀，如 `TYUST_SERVER__BIND_ADDR=127.0.0.1:8080`
+const ENV_PREFIX: &str = "TYUST_";
+
+static CONFIG: OnceCell<AppConfig> = OnceCell::new();
+
+/// 服务配置
+///
+/// 加载顺序：内置默认值 -> 配置文件 -> 环境变量，后者覆盖前者。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub cache: CacheConfig,
+    pub avatar: AvatarConfig,
+    pub storage: StorageConfig,
+    pub log: LogConfig,
+    pub http: HttpConfig,
+    pub health: HealthConfig,
+    pub analytics: AnalyticsConfig,
+    pub scheduler: SchedulerConfig,
+    pub events: EventsConfig,
+    pub wechat: WechatConfig,
+    pub auth: AuthConfig,
+    pub jwt: JwtConfig,
+    pub credentials: CredentialKeyConfig,
+    pub rate_limit: RateLimitConfig,
+    pub upstream: UpstreamConfig,
+}
+
+/// HTTP 服务配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub environment: Environment,       // 运行环境
+    pub bind_addr: String,              // 监听地址
+    pub static_dir: String,             // 静态文件目录（头像等）
+    pub cors_allowed_origins: Vec<String>, // 允许跨域的来源，包含 "*" 时允许所有来源
+    pub shutdown_grace_secs: u64,       // 收到停止信号后等待进行中请求完成的最长时间
+    pub trusted_proxies: Vec<String>,   // 可信反向代理的地址（CIDR 或单个IP），仅信任来自这些地址的 X-Forwarded-For / X-Real-IP
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            environment: Environment::Development,
+            bind_addr: "0.0.0.0:3000".to_string(),
+            static_dir: "static".to_string(),
+            cors_allowed_origins: vec!["*".to_string()],
+            shutdown_grace_secs: 30,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// 监听地址（已在启动时校验）
+    pub fn socket_addr(&self) -> SocketAddr {
+        self.bind_addr.parse().expect("bind_addr validated at startup")
+    }
+
+    /// 优雅停机的最长等待时间
+    pub fn shutdown_grace_period(&self) -> Duration {
+        Duration::from_secs(self.shutdown_grace_secs)
+    }
+
+    /// 对端地址是否为可信反向代理
+    pub fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies
+            .iter()
+            .filter_map(|proxy| parse_ip_net(proxy))
+            .any(|net| net.contains(&ip))
+    }
+
+    /// 根据配置构建 CORS 策略
+    pub fn cors_layer(&self) -> CorsLayer {
+        if self.cors_allowed_origins.iter().any(|origin| origin == "*") {
+            return CorsLayer::permissive();
+        }
+
+        let origins: Vec<HeaderValue> = self
+            .cors_allowed_origins
+            .iter()
+            .filter_map(|origin| HeaderValue::from_str(origin).ok())
+            .collect();
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .expose_headers(Any)
+    }
+}
+
+/// 运行环境，production 下会拒绝使用内置的默认密钥
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Environment {
+    Development,
+    Production,
+}
+
+/// 数据库配置（连接地址仍通过 DATABASE_URL 环境变量提供）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub auto_migrate: bool, // 启动时自动应用迁移，关闭后需先执行 `tyust migrate`
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self { auto_migrate: true }
+    }
+}
+
+/// 缓存配置，各类数据的有效期为 0 时表示不缓存该类数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub backend: CacheBackend,  // 缓存实现
+    pub redis_url: String,      // backend 为 redis 时使用
+    pub memory_capacity: usize, // backend 为 memory 时最多保留的条目数（LRU 淘汰）
+    pub auth_ttl_secs: u64,     // 上游认证凭证
+    pub user_ttl_secs: u64,     // 学生资料
+    pub courses_ttl_secs: u64,  // 课表
+    pub scores_ttl_secs: u64,   // 成绩（含原始成绩）
+    pub session_ttl_secs: u64,  // 会话有效及禁用状态，注销和禁用时主动失效
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            backend: CacheBackend::Memory,
+            redis_url: "redis://127.0.0.1:6379".to_string(),
+            memory_capacity: 10_000,
+            auth_ttl_secs: 10 * 60,
+            user_ttl_secs: 10 * 60,
+            courses_ttl_secs: 30 * 60,
+            scores_ttl_secs: 10 * 60,
+            session_ttl_secs: 30,
+        }
+    }
+}
+
+/// 缓存实现，多实例部署时应使用 redis 以便各实例共享缓存和失效
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheBackend {
+    Memory,
+    Redis,
+}
+
+/// 头像上传配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AvatarConfig {
+    pub max_upload_bytes: usize, // 上传图片的最大字节数（base64 解码后）
+    pub max_dimension: u32,      // 上传图片的最大宽高，超过时拒绝解码
+    pub size: u32,               // 头像统一裁剪缩放后的边长
+    pub thumbnail_size: u32,     // 缩略图边长
+}
+
+impl Default for AvatarConfig {
+    fn default() -> Self {
+        Self {
+            max_upload_bytes: 2 * 1024 * 1024,
+            max_dimension: 4096,
+            size: 256,
+            thumbnail_size: 64,
+        }
+    }
+}
+
+/// 上传文件存储配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,  // 存储实现
+    pub url_mode: StorageUrlMode, // 返回给客户端的地址类型，local 存储始终为公开地址
+    pub signed_url_ttl_secs: u64, // url_mode 为 signed 时签名地址的有效期
+    pub s3: S3StorageConfig,      // backend 为 s3 时使用
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::Local,
+            url_mode: StorageUrlMode::Public,
+            signed_url_ttl_secs: 60 * 60,
+            s3: S3StorageConfig::default(),
+        }
+    }
+}
+
+/// 存储实现，多实例部署时应使用 s3 以便各实例共享上传的文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    Local, // 写入 server.static_dir，由 /static 路由提供访问
+    S3,    // S3 兼容的对象存储（AWS S3、MinIO 等）
+}
+
+/// 文件访问地址类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageUrlMode {
+    Public, // 拼接 s3.public_base_url，要求存储桶允许公开读取
+    Signed, // 预签名地址，每次返回用户信息时重新生成
+}
+
+/// S3 兼容对象存储配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct S3StorageConfig {
+    pub endpoint: String,          // 为空时使用 AWS 默认地址，MinIO 如 http://127.0.0.1:9000
+    pub region: String,            // 区域
+    pub bucket: String,            // 存储桶
+    pub access_key_id: String,     // 访问密钥 ID
+    pub secret_access_key: String, // 访问密钥
+    pub allow_http: bool,          // 是否允许非 HTTPS 的 endpoint（本地 MinIO）
+    pub public_base_url: String,   // url_mode 为 public 时的访问地址前缀，为空时使用 endpoint/bucket
+}
+
+impl Default for S3StorageConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            region: "us-east-1".to_string(),
+            bucket: String::new(),
+            access_key_id: String::new(),
+            secret_access_key: String::new(),
+            allow_http: false,
+            public_base_url: String::new(),
+        }
+    }
+}
+
+/// 日志配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: String,  // 日志过滤规则，RUST_LOG 环境变量优先
+    pub format: String, // text 或 json
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "tyust=info,tower_http=info".to_string(),
+            format: "text".to_string(),
+        }
+    }
+}
+
+/// 访问上游教务系统的 HTTP 客户端配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub timeout_secs: u64,             // 请求总超时
+    pub connect_timeout_secs: u64,     // 连接超时
+    pub pool_max_idle_per_host: usize, // 每个主机保留的空闲连接数
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 8,
+            connect_timeout_secs: 5,
+            pool_max_idle_per_host: 4,
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+}
+
+/// 就绪检查配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthConfig {
+    pub upstream_probe: bool,    // 就绪检查是否探测上游统一身份认证，上游不可用时报告未就绪
+    pub probe_timeout_secs: u64, // 每项检查的超时
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            upstream_probe: false,
+            probe_timeout_secs: 3,
+        }
+    }
+}
+
+impl HealthConfig {
+    pub fn probe_timeout(&self) -> Duration {
+        Duration::from_secs(self.probe_timeout_secs)
+    }
+}
+
+/// 登录统计配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyticsConfig {
+    pub timezone: String, // 按天/周/月分组和统计高峰时段使用的时区（IANA 名称，如 Asia/Shanghai）
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            timezone: "Asia/Shanghai".to_string(),
+        }
+    }
+}
+
+/// 定时任务配置
+///
+/// 计划使用带秒的 cron 表达式（秒 分 时 日 月 星期），按服务器本地时区计算；
+/// 留空表示不自动执行，只能由管理员手动触发。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerConfig {
+    pub enabled: bool,                    // 本实例是否执行定时任务（多实例时至少一个实例开启）
+    pub poll_interval_secs: u64,          // 检查到期任务和手动触发请求的间隔
+    pub lease_secs: u64,                  // 任务租约时长，执行期间自动续期，实例崩溃后租约过期即可被其他实例接管
+    pub auth_cache_cleanup: String,       // 清理过期的上游认证缓存
+    pub avatar_gc: String,                // 清理不再使用的头像文件
+    pub score_polling: String,            // 为登录状态有效的学生拉取成绩，记录新出的成绩
+    pub score_polling_concurrency: usize, // 成绩轮询时同时请求上游的学生数
+    pub term_rollover: String,            // 到开学日期时切换到预先设置的下一学期
+    pub event_cleanup: String,            // 删除超过保留时间的推送事件
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_interval_secs: 15,
+            lease_secs: 300,
+            auth_cache_cleanup: "0 0 * * * *".to_string(),
+            avatar_gc: "0 30 3 * * *".to_string(),
+            score_polling: "0 0 8-22/2 * * *".to_string(),
+            score_polling_concurrency: 4,
+            term_rollover: "0 1 0 * * *".to_string(),
+            event_cleanup: "0 15 * * * *".to_string(),
+        }
+    }
+}
+
+impl SchedulerConfig {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs)
+    }
+
+    pub fn lease(&self) -> Duration {
+        Duration::from_secs(self.lease_secs)
+    }
+}
+
+/// 学生事件推送配置（SSE / WebSocket）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventsConfig {
+    pub heartbeat_secs: u64,  // 心跳间隔，同时按此间隔检查会话是否已注销
+    pub retention_hours: i64, // 事件保留时间，断线重连时只能补发这段时间内的事件
+    pub replay_limit: i64,    // 断线重连时最多补发的事件数
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_secs: 15,
+            retention_hours: 72,
+            replay_limit: 200,
+        }
+    }
+}
+
+impl EventsConfig {
+    pub fn heartbeat(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_secs)
+    }
+}
+
+/// 微信小程序配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WechatConfig {
+    pub enabled: bool,              // 是否启用微信功能（订阅消息、微信登录）
+    pub api_base_url: String,       // 微信接口地址，测试时可指向本地模拟服务
+    pub app_id: String,             // 小程序 AppID
+    pub app_secret: String,         // 小程序 AppSecret，建议通过 TYUST_WECHAT__APP_SECRET 环境变量提供
+    pub miniprogram_state: String,  // 点击消息打开的小程序版本：formal、trial 或 developer
+    pub send_concurrency: usize,    // 向所有学生推送时同时发送的消息数
+    pub templates: SubscribeTemplates,
+}
+
+impl Default for WechatConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            api_base_url: "https://api.weixin.qq.com".to_string(),
+            app_id: String::new(),
+            app_secret: String::new(),
+            miniprogram_state: "formal".to_string(),
+            send_concurrency: 8,
+            templates: SubscribeTemplates::default(),
+        }
+    }
+}
+
+impl WechatConfig {
+    pub fn api(&self, path: &str) -> String {
+        format!("{}{}", self.api_base_url, path)
+    }
+}
+
+/// 各类事件使用的订阅消息模板，template_id 为空的事件不发送订阅消息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubscribeTemplates {
+    pub new_scores: SubscribeTemplate,       // 可用字段：count、course、score、courses、semester、time
+    pub announcement: SubscribeTemplate,     // 可用字段：title、content、time
+    pub schedule_changed: SubscribeTemplate, // 可用字段：semester_name、semester_start_date、time
+}
+
+impl SubscribeTemplates {
+    /// (事件类型, 模板, 模板字段中可用的占位符)
+    pub fn entries(&self) -> [(&'static str, &SubscribeTemplate, &'static [&'static str]); 3] {
+        [
+            ("new_scores", &self.new_scores, &["count", "course", "score", "courses", "semester", "time"]),
+            ("announcement", &self.announcement, &["title", "content", "time"]),
+            ("schedule_changed", &self.schedule_changed, &["semester_name", "semester_start_date", "time"]),
+        ]
+    }
+
+    /// 事件类型对应的模板，未配置时返回 None
+    pub fn get(&self, event_type: &str) -> Option<&SubscribeTemplate> {
+        self.entries()
+            .into_iter()
+            .find(|(name, template, _)| *name == event_type && template.is_configured())
+            .map(|(_, template, _)| template)
+    }
+}
+
+/// 订阅消息模板
+///
+/// fields 的键为模板中的字段名（如 `thing1`），值可以包含 `{course}` 这样的占位符，发送时替换为事件数据。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubscribeTemplate {
+    pub template_id: String,              // 微信公众平台中的模板ID
+    pub page: String,                     // 点击消息打开的页面，为空时不跳转
+    pub fields: BTreeMap<String, String>, // 模板字段 -> 内容
+}
+
+impl SubscribeTemplate {
+    pub fn is_configured(&self) -> bool {
+        !self.template_id.trim().is_empty()
+    }
+}
+
+/// 登录凭证有效期配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub student_access_token_ttl_secs: i64, // 学生 access token 有效期
+    pub student_refresh_token_ttl_days: i64, // 学生 refresh token 有效期
+    pub admin_access_token_ttl_secs: i64, // 管理员 access token 有效期
+    pub admin_refresh_token_ttl_days: i64, // 管理员 refresh token 有效期
+    pub auth_cache_ttl_hours: i64,        // 上游认证缓存有效期
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            student_access_token_ttl_secs: 2 * 60 * 60,
+            student_refresh_token_ttl_days: 30,
+            admin_access_token_ttl_secs: 30 * 60,
+            admin_refresh_token_ttl_days: 7,
+            auth_cache_ttl_hours: 24,
+        }
+    }
+}
+
+/// 登录接口限流配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub student_login: LoginRateLimit, // 学生登录（每次请求都会访问上游 SSO）
+    pub admin_login: LoginRateLimit,   // 管理员登录
+    pub admin_lockout: LockoutConfig,  // 管理员连续登录失败后的锁定
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            student_login: LoginRateLimit {
+                window_secs: 60,
+                per_ip: 20,
+                per_account: 5,
+            },
+            admin_login: LoginRateLimit {
+                window_secs: 60,
+                per_ip: 10,
+                per_account: 5,
+            },
+            admin_lockout: LockoutConfig::default(),
+        }
+    }
+}
+
+/// 固定时间窗口内允许的请求次数，0 表示不限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoginRateLimit {
+    pub window_secs: u64, // 时间窗口
+    pub per_ip: u32,      // 每个IP在窗口内的最大请求数
+    pub per_account: u32, // 每个账号在窗口内的最大请求数
+}
+
+impl LoginRateLimit {
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.window_secs)
+    }
+}
+
+/// 连续登录失败的指数退避锁定
+///
+/// 连续失败达到 `max_failures` 次后锁定 `base_lockout_secs`，之后每再失败一次锁定时间翻倍，
+/// 最长不超过 `max_lockout_secs`。登录成功后清零。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LockoutConfig {
+    pub max_failures: u32,
+    pub base_lockout_secs: u64,
+    pub max_lockout_secs: u64,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: 5,
+            base_lockout_secs: 60,
+            max_lockout_secs: 60 * 60,
+        }
+    }
+}
+
+/// 内置的学生 JWT 密钥（仅用于开发环境）
+const DEFAULT_STUDENT_JWT_SECRET: &str = "tyust_course_system_secret_key_2024";
+
+/// 内置的管理员 JWT 密钥（仅用于开发环境）
+const DEFAULT_ADMIN_JWT_SECRET: &str = "fallback_admin_secret_key";
+
+/// 未配置 kid 时使用的密钥ID（兼容引入 kid 之前签发的 token）
+const LEGACY_KID: &str = "legacy";
+
+/// JWT 签名密钥配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JwtConfig {
+    pub student: JwtKeySet,
+    pub admin: JwtKeySet,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            student: JwtKeySet::with_secret(DEFAULT_STUDENT_JWT_SECRET),
+            admin: JwtKeySet::with_secret(DEFAULT_ADMIN_JWT_SECRET),
+        }
+    }
+}
+
+/// 一组可轮换的密钥
+///
+/// `keys` 中每一项形如 `kid:secret`，均可用于验证；`signing_kid` 指定签发新 token 的密钥。
+/// 轮换时先加入新密钥并切换 `signing_kid`，待旧 token 全部过期后再移除旧密钥。
+/// 环境变量示例：`TYUST_JWT__STUDENT__KEYS=2025-01:<secret>,legacy:<old secret>`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JwtKeySet {
+    pub signing_kid: String,
+    pub keys: Vec<String>,
+}
+
+impl Default for JwtKeySet {
+    fn default() -> Self {
+        Self {
+            signing_kid: LEGACY_KID.to_string(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+impl JwtKeySet {
+    fn with_secret(secret: &str) -> Self {
+        Self {
+            signing_kid: LEGACY_KID.to_string(),
+            keys: vec![format!("{}:{}", LEGACY_KID, secret)],
+        }
+    }
+
+    /// 解析出 (kid, secret) 列表
+    pub fn parsed_keys(&self) -> Vec<(&str, &str)> {
+        self.keys
+            .iter()
+            .filter_map(|key| key.split_once(':'))
+            .map(|(kid, secret)| (kid.trim(), secret))
+            .collect()
+    }
+
+    fn validate(&self, name: &str, environment: Environment, errors: &mut Vec<String>) {
+        if self.keys.is_empty() {
+            errors.push(format!("jwt.{}.keys must contain at least one key", name));
+        }
+        let mut kids = Vec::new();
+        for key in &self.keys {
+            match key.split_once(':') {
+                Some((kid, secret)) if !kid.trim().is_empty() && !secret.is_empty() => {
+                    if kids.contains(&kid.trim()) {
+                        errors.push(format!("jwt.{}.keys contains duplicate kid '{}'", name, kid.trim()));
+                    }
+                    kids.push(kid.trim());
+                    let is_default = secret == DEFAULT_STUDENT_JWT_SECRET || secret == DEFAULT_ADMIN_JWT_SECRET;
+                    if environment == Environment::Production && is_default {
+                        errors.push(format!(
+                            "jwt.{}.keys '{}' uses the built-in default secret, which is not allowed in production",
+                            name,
+                            kid.trim()
+                        ));
+                    }
+                }
+                _ => errors.push(format!("jwt.{}.keys entries must look like 'kid:secret'", name)),
+            }
+        }
+        if !kids.contains(&self.signing_kid.as_str()) {
+            errors.push(format!("jwt.{}.signing_kid '{}' is not one of the configured keys", name, self.signing_kid));
+        }
+    }
+}
+
+/// 内置的上游凭证加密密钥（仅用于开发环境）
+const DEFAULT_CREDENTIAL_KEY: &str = "h0UZ52RMCxd7VwS6yZBiR+viR+mAUJcrUV2WEhTNxWA=";
+
+/// 上游凭证加密密钥配置
+///
+/// `keys` 中每一项形如 `kid:<base64 编码的 32 字节密钥>`，用于加密每条缓存记录的数据密钥；
+/// `active_kid` 指定加密新数据时使用的密钥。轮换时加入新密钥并切换 `active_kid`，
+/// 服务启动时会用新密钥重新包装旧数据密钥，之后即可移除旧密钥。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CredentialKeyConfig {
+    pub active_kid: String,
+    pub keys: Vec<String>,
+}
+
+impl Default for CredentialKeyConfig {
+    fn default() -> Self {
+        Self {
+            active_kid: LEGACY_KID.to_string(),
+            keys: vec![format!("{}:{}", LEGACY_KID, DEFAULT_CREDENTIAL_KEY)],
+        }
+    }
+}
+
+impl CredentialKeyConfig {
+    /// 解析出 (kid, 密钥) 列表，格式错误的项会被跳过（已在启动时校验）
+    pub fn parsed_keys(&self) -> Vec<(&str, [u8; 32])> {
+        self.keys
+            .iter()
+            .filter_map(|key| key.split_once(':'))
+            .filter_map(|(kid, secret)| Some((kid.trim(), decode_credential_key(secret)?)))
+            .collect()
+    }
+
+    fn validate(&self, environment: Environment, errors: &mut Vec<String>) {
+        if self.keys.is_empty() {
+            errors.push("credentials.keys must contain at least one key".to_string());
+        }
+        let mut kids = Vec::new();
+        for key in &self.keys {
+            match key.split_once(':') {
+                Some((kid, secret)) if !kid.trim().is_empty() => {
+                    if kids.contains(&kid.trim()) {
+                        errors.push(format!("credentials.keys contains duplicate kid '{}'", kid.trim()));
+                    }
+                    kids.push(kid.trim());
+                    if decode_credential_key(secret).is_none() {
+                        errors.push(format!(
+                            "credentials.keys '{}' must be a base64 encoded 32 byte key",
+                            kid.trim()
+                        ));
+                    }
+                    if environment == Environment::Production && secret.trim() == DEFAULT_CREDENTIAL_KEY {
+                        errors.push(format!(
+                            "credentials.keys '{}' uses the built-in default key, which is not allowed in production",
+                            kid.trim()
+                        ));
+                    }
+                }
+                _ => errors.push("credentials.keys entries must look like 'kid:base64key'".to_string()),
+            }
+        }
+        if !kids.contains(&self.active_kid.as_str()) {
+            errors.push(format!("credentials.active_kid '{}' is not one of the configured keys", self.active_kid));
+        }
+    }
+}
+
+/// 解析 CIDR，单个IP视为只包含该地址的网段
+fn parse_ip_net(value: &str) -> Option<IpNet> {
+    let value = value.trim();
+    value
+        .parse::<IpNet>()
+        .ok()
+        .or_else(|| value.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+fn decode_credential_key(secret: &str) -> Option<[u8; 32]> {
+    use base64::{engine::general_purpose, Engine as _};
+    general_purpose::STANDARD
+        .decode(secret.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+}
+
+/// 上游教务系统地址
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpstreamConfig {
+    pub sso_base_url: String,    // 统一身份认证
+    pub portal_base_url: String, // 融合门户
+    pub zero_base_url: String,   // 零信任网关
+    pub zero_external_id: String, // 零信任网关中教务应用的ID
+    pub jwglxt_base_url: String, // 教务管理系统
+}
+
+impl Default for UpstreamConfig {
+    fn default() -> Self {
+        Self {
+            sso_base_url: "https://sso1.tyust.edu.cn".to_string(),
+            portal_base_url: "https://ronghemenhu.tyust.edu.cn".to_string(),
+            zero_base_url: "https://zero.tyust.edu.cn".to_string(),
+            zero_external_id: "r3IveGXj".to_string(),
+            jwglxt_base_url: "https://newjwc.tyust.edu.cn".to_string(),
+        }
+    }
+}
+
+impl UpstreamConfig {
+    pub fn sso(&self, path: &str) -> String {
+        format!("{}{}", self.sso_base_url, path)
+    }
+
+    pub fn portal(&self, path: &str) -> String {
+        format!("{}{}", self.portal_base_url, path)
+    }
+
+    pub fn zero(&self, path: &str) -> String {
+        format!("{}{}", self.zero_base_url, path)
+    }
+
+    pub fn jwglxt(&self, path: &str) -> String {
+        format!("{}{}", self.jwglxt_base_url, path)
+    }
+}
+
+impl AppConfig {
+    /// 从配置文件和环境变量加载配置并校验
+    pub fn load() -> Result<(Self, Option<PathBuf>)> {
+        let path = std::env::var("TYUST_CONFIG")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(DEFAULT_CONFIG_FILE));
+
+        let mut value = serde_json::to_value(AppConfig::default())?;
+        let loaded_from = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read config file {}", path.display()))?;
+            let file_value: Value = serde_json::from_str(&content)
+                .with_context(|| format!("invalid JSON in config file {}", path.display()))?;
+            merge(&mut value, file_value);
+            Some(path)
+        } else if std::env::var("TYUST_CONFIG").is_ok() {
+            return Err(anyhow!("config file {} does not exist", path.display()));
+        } else {
+            None
+        };
+
+        apply_env_overrides(&mut value, std::env::vars())?;
+        apply_legacy_env(&mut value);
+
+        let mut config: AppConfig =
+            serde_json::from_value(value).context("invalid configuration")?;
+        config.normalize();
+        config.validate()?;
+        Ok((config, loaded_from))
+    }
+
+    /// 去掉上游地址末尾的斜杠，便于拼接路径
+    fn normalize(&mut self) {
+        for url in [
+            &mut self.upstream.sso_base_url,
+            &mut self.upstream.portal_base_url,
+            &mut self.upstream.zero_base_url,
+            &mut self.upstream.jwglxt_base_url,
+            &mut self.wechat.api_base_url,
+        ] {
+            let trimmed = url.trim_end_matches('/').len();
+            url.truncate(trimmed);
+        }
+    }
+
+    /// 校验配置，一次性列出所有错误
+    fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+
+        if self.server.bind_addr.parse::<SocketAddr>().is_err() {
+            errors.push(format!("server.bind_addr '{}' is not a valid socket address", self.server.bind_addr));
+        }
+        if self.server.static_dir.trim().is_empty() {
+            errors.push("server.static_dir must not be empty".to_string());
+        }
+        for proxy in &self.server.trusted_proxies {
+            if parse_ip_net(proxy).is_none() {
+                errors.push(format!("server.trusted_proxies entry '{}' is not a valid IP or CIDR", proxy));
+            }
+        }
+        for origin in &self.server.cors_allowed_origins {
+            if origin != "*" && (url::Url::parse(origin).is_err() || HeaderValue::from_str(origin).is_err()) {
+                errors.push(format!("server.cors_allowed_origins entry '{}' is not a valid origin", origin));
+            }
+        }
+
+        if self.cache.backend == CacheBackend::Redis && url::Url::parse(&self.cache.redis_url).is_err() {
+            errors.push(format!("cache.redis_url '{}' is not a valid URL", self.cache.redis_url));
+        }
+        if self.cache.memory_capacity == 0 {
+            errors.push("cache.memory_capacity must be greater than 0".to_string());
+        }
+
+        if self.avatar.max_upload_bytes == 0 {
+            errors.push("avatar.max_upload_bytes must be greater than 0".to_string());
+        }
+        if self.avatar.thumbnail_size == 0
+            || self.avatar.thumbnail_size > self.avatar.size
+            || self.avatar.size > self.avatar.max_dimension
+        {
+            errors.push(
+                "avatar sizes must satisfy 0 < thumbnail_size <= size <= max_dimension".to_string(),
+            );
+        }
+
+        if self.storage.backend == StorageBackend::S3 {
+            let s3 = &self.storage.s3;
+            if s3.bucket.trim().is_empty() {
+                errors.push("storage.s3.bucket must not be empty".to_string());
+            }
+            for (name, value) in [("storage.s3.endpoint", &s3.endpoint), ("storage.s3.public_base_url", &s3.public_base_url)] {
+                if !value.is_empty() && url::Url::parse(value).is_err() {
+                    errors.push(format!("{} '{}' is not a valid URL", name, value));
+                }
+            }
+            if self.storage.url_mode == StorageUrlMode::Public && s3.public_base_url.is_empty() && s3.endpoint.is_empty() {
+                errors.push("storage.s3.public_base_url is required for public URLs without a custom endpoint".to_string());
+            }
+        }
+        if self.storage.url_mode == StorageUrlMode::Signed && self.storage.signed_url_ttl_secs == 0 {
+            errors.push("storage.signed_url_ttl_secs must be greater than 0".to_string());
+        }
+
+        if !matches!(self.log.format.as_str(), "text" | "json") {
+            errors.push(format!("log.format must be 'text' or 'json', got '{}'", self.log.format));
+        }
+        if tracing_subscriber::EnvFilter::try_new(&self.log.level).is_err() {
+            errors.push(format!("log.level '{}' is not a valid filter", self.log.level));
+        }
+
+        if self.http.timeout_secs == 0 {
+            errors.push("http.timeout_secs must be greater than 0".to_string());
+        }
+        if self.http.connect_timeout_secs == 0 || self.http.connect_timeout_secs > self.http.timeout_secs {
+            errors.push("http.connect_timeout_secs must be between 1 and http.timeout_secs".to_string());
+        }
+        if self.health.probe_timeout_secs == 0 {
+            errors.push("health.probe_timeout_secs must be greater than 0".to_string());
+        }
+
+        // 时区名称由数据库解析，这里只排除明显无效的值
+        let timezone = &self.analytics.timezone;
+        if timezone.is_empty()
+            || !timezone.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '+'))
+        {
+            errors.push(format!("analytics.timezone '{}' is not a valid time zone name", timezone));
+        }
+
+        if self.events.heartbeat_secs == 0 {
+            errors.push("events.heartbeat_secs must be greater than 0".to_string());
+        }
+        if self.events.retention_hours <= 0 {
+            errors.push("events.retention_hours must be greater than 0".to_string());
+        }
+        if self.events.replay_limit <= 0 {
+            errors.push("events.replay_limit must be greater than 0".to_string());
+        }
+
+        self.validate_wechat(&mut errors);
+
+        let scheduler = &self.scheduler;
+        if scheduler.poll_interval_secs == 0 {
+            errors.push("scheduler.poll_interval_secs must be greater than 0".to_string());
+        }
+        if scheduler.lease_secs < scheduler.poll_interval_secs * 2 {
+            errors.push("scheduler.lease_secs must be at least twice scheduler.poll_interval_secs".to_string());
+        }
+        if scheduler.score_polling_concurrency == 0 {
+            errors.push("scheduler.score_polling_concurrency must be greater than 0".to_string());
+        }
+        for (name, expression) in [
+            ("scheduler.auth_cache_cleanup", &scheduler.auth_cache_cleanup),
+            ("scheduler.avatar_gc", &scheduler.avatar_gc),
+            ("scheduler.score_polling", &scheduler.score_polling),
+            ("scheduler.term_rollover", &scheduler.term_rollover),
+            ("scheduler.event_cleanup", &scheduler.event_cleanup),
+        ] {
+            if !expression.trim().is_empty() && expression.parse::<cron::Schedule>().is_err() {
+                errors.push(format!("{} '{}' is not a valid cron expression", name, expression));
+            }
+        }
+
+        for (name, value) in [
+            ("auth.student_access_token_ttl_secs", self.auth.student_access_token_ttl_secs),
+            ("auth.student_refresh_token_ttl_days", self.auth.student_refresh_token_ttl_days),
+            ("auth.admin_access_token_ttl_secs", self.auth.admin_access_token_ttl_secs),
+            ("auth.admin_refresh_token_ttl_days", self.auth.admin_refresh_token_ttl_days),
+            ("auth.auth_cache_ttl_hours", self.auth.auth_cache_ttl_hours),
+        ] {
+            if value <= 0 {
+                errors.push(format!("{} must be greater than 0", name));
+            }
+        }
+
+        for (name, value) in [
+            ("upstream.sso_base_url", &self.upstream.sso_base_url),
+            ("upstream.portal_base_url", &self.upstream.portal_base_url),
+            ("upstream.zero_base_url", &self.upstream.zero_base_url),
+            ("upstream.jwglxt_base_url", &self.upstream.jwglxt_base_url),
+        ] {
+            match url::Url::parse(value) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+                _ => errors.push(format!("{} '{}' is not a valid http(s) URL", name, value)),
+            }
+        }
+        for (name, limit) in [
+            ("rate_limit.student_login", &self.rate_limit.student_login),
+            ("rate_limit.admin_login", &self.rate_limit.admin_login),
+        ] {
+            if limit.window_secs == 0 {
+                errors.push(format!("{}.window_secs must be greater than 0", name));
+            }
+        }
+        let lockout = &self.rate_limit.admin_lockout;
+        if lockout.max_failures == 0 {
+            errors.push("rate_limit.admin_lockout.max_failures must be greater than 0".to_string());
+        }
+        if lockout.base_lockout_secs == 0 || lockout.base_lockout_secs > lockout.max_lockout_secs {
+            errors.push(
+                "rate_limit.admin_lockout.base_lockout_secs must be between 1 and max_lockout_secs".to_string(),
+            );
+        }
+
+        let environment = self.server.environment;
+        self.jwt.student.validate("student", environment, &mut errors);
+        self.jwt.admin.validate("admin", environment, &mut errors);
+        self.credentials.validate(environment, &mut errors);
+
+        if self.upstream.zero_external_id.trim().is_empty() {
+            errors.push("upstream.zero_external_id must not be empty".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("invalid configuration:\n  - {}", errors.join("\n  - ")))
+        }
+    }
+
+    /// 启用微信订阅消息时校验接口地址、凭证和模板字段中的占位符
+    fn validate_wechat(&self, errors: &mut Vec<String>) {
+        let wechat = &self.wechat;
+        if !wechat.enabled {
+            return;
+        }
+        match url::Url::parse(&wechat.api_base_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => errors.push(format!("wechat.api_base_url '{}' is not a valid http(s) URL", wechat.api_base_url)),
+        }
+        if wechat.app_id.trim().is_empty() || wechat.app_secret.trim().is_empty() {
+            errors.push("wechat.app_id and wechat.app_secret are required when wechat is enabled".to_string());
+        }
+        if !matches!(wechat.miniprogram_state.as_str(), "formal" | "trial" | "developer") {
+            errors.push(format!(
+                "wechat.miniprogram_state must be 'formal', 'trial' or 'developer', got '{}'",
+                wechat.miniprogram_state
+            ));
+        }
+        if wechat.send_concurrency == 0 {
+            errors.push("wechat.send_concurrency must be greater than 0".to_string());
+        }
+
+        for (name, template, placeholders) in wechat.templates.entries() {
+            if !template.is_configured() {
+                continue;
+            }
+            if template.fields.is_empty() {
+                errors.push(format!("wechat.templates.{}.fields must not be empty", name));
+            }
+            for (field, value) in &template.fields {
+                for placeholder in placeholder_names(value) {
+                    if !placeholders.contains(&placeholder) {
+                        errors.push(format!(
+                            "wechat.templates.{}.fields.{} uses unknown placeholder '{{{}}}' (available: {})",
+                            name,
+                            field,
+                            placeholder,
+                            placeholders.join(", ")
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 模板内容中 `{name}` 形式的占位符名
+pub fn placeholder_names(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+}
+
+/// 将 overlay 中的字段递归合并到 base
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// 应用 `TYUST_<SECTION>__<FIELD>` 形式的环境变量
+///
+/// 值按原字段类型解析，列表类型使用逗号分隔。
+fn apply_env_overrides(value: &mut Value, vars: impl Iterator<Item = (String, String)>) -> Result<()> {
+    for (key, raw) in vars {
+        let Some(path) = key.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        if path == "CONFIG" {
+            continue;
+        }
+
+        let segments: Vec<String> = path.split("__").map(|s| s.to_ascii_lowercase()).collect();
+        let target = segments
+            .iter()
+            .try_fold(&mut *value, |node, segment| node.get_mut(segment))
+            .ok_or_else(|| anyhow!("unknown configuration key in environment variable {}", key))?;
+
+        *target = match target {
+            Value::String(_) => Value::String(raw),
+            Value::Array(_) => Value::Array(
+                raw.split(',')
+                    .map(|item| item.trim())
+                    .filter(|item| !item.is_empty())
+                    .map(|item| Value::String(item.to_string()))
+                    .collect(),
+            ),
+            Value::Object(_) => {
+                return Err(anyhow!("environment variable {} must name a field, not a section", key));
+            }
+            _ => serde_json::from_str(&raw)
+                .with_context(|| format!("invalid value '{}' for environment variable {}", raw, key))?,
+        };
+    }
+    Ok(())
+}
+
+/// 兼容旧版本的 ADMIN_JWT_SECRET 环境变量
+///
+/// 仅当管理员密钥仍为内置默认值时生效，显式配置的 `jwt.admin` 优先。
+fn apply_legacy_env(value: &mut Value) {
+    let Ok(secret) = std::env::var("ADMIN_JWT_SECRET") else {
+        return;
+    };
+    let default_keys = serde_json::to_value(JwtKeySet::with_secret(DEFAULT_ADMIN_JWT_SECRET)).ok();
+    if let Some(admin) = value.pointer_mut("/jwt/admin")
+        && Some(&*admin) == default_keys.as_ref()
+        && !secret.is_empty()
+    {
+        *admin = serde_json::to_value(JwtKeySet::with_secret(&secret)).unwrap_or(Value::Null);
+    }
+}
+
+/// 加载并保存全局配置，返回加载到的配置文件路径
+pub fn init() -> Result<Option<PathBuf>> {
+    let (config, loaded_from) = AppConfig::load()?;
+    CONFIG
+        .set(config)
+        .map_err(|_| anyhow!("configuration already initialized"))?;
+    Ok(loaded_from)
+}
+
+/// 获取全局配置
+pub fn get() -> &'static AppConfig {
+    CONFIG.get().expect("Configuration not initialized")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn overridden(vars: &[(&str, &str)]) -> Result<AppConfig> {
+        let mut value = serde_json::to_value(AppConfig::default())?;
+        apply_env_overrides(&mut value, env(vars))?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    fn production() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.server.environment = Environment::Production;
+        config
+    }
+
+    fn validation_errors(config: &AppConfig) -> String {
+        config.validate().err().map(|e| e.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn env_overrides_parse_by_field_type() {
+        let config = overridden(&[
+            ("TYUST_SERVER__BIND_ADDR", "127.0.0.1:8080"),
+            ("TYUST_SERVER__SHUTDOWN_GRACE_SECS", "5"),
+            ("TYUST_SERVER__TRUSTED_PROXIES", " 10.0.0.0/8, ,127.0.0.1 "),
+            ("TYUST_WECHAT__ENABLED", "true"),
+        ])
+        .unwrap();
+        assert_eq!(config.server.bind_addr, "127.0.0.1:8080");
+        assert_eq!(config.server.shutdown_grace_secs, 5);
+        assert_eq!(config.server.trusted_proxies, vec!["10.0.0.0/8", "127.0.0.1"]);
+        assert!(config.wechat.enabled);
+    }
+
+    #[test]
+    fn env_overrides_ignore_unrelated_variables() {
+        let config = overridden(&[("PATH", "/usr/bin"), ("TYUST_CONFIG", "other.json")]).unwrap();
+        assert_eq!(config.server.bind_addr, ServerConfig::default().bind_addr);
+    }
+
+    #[test]
+    fn env_overrides_reject_unknown_keys_sections_and_bad_values() {
+        let unknown = overridden(&[("TYUST_SERVER__NO_SUCH_FIELD", "1")]).unwrap_err();
+        assert!(unknown.to_string().contains("unknown configuration key"));
+
+        let section = overridden(&[("TYUST_SERVER", "x")]).unwrap_err();
+        assert!(section.to_string().contains("must name a field"));
+
+        let bad_value = overridden(&[("TYUST_SERVER__SHUTDOWN_GRACE_SECS", "soon")]).unwrap_err();
+        assert!(bad_value.to_string().contains("invalid value 'soon'"));
+    }
+
+    #[test]
+    fn default_config_is_valid_in_development() {
+        AppConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn production_rejects_default_secrets() {
+        let errors = validation_errors(&production());
+        assert!(errors.contains("jwt.student.keys 'legacy' uses the built-in default secret"));
+        assert!(errors.contains("jwt.admin.keys 'legacy' uses the built-in default secret"));
+        assert!(errors.contains("credentials.keys 'legacy' uses the built-in default key"));
+    }
+
+    #[test]
+    fn production_accepts_custom_secrets() {
+        let mut config = production();
+        config.jwt.student = JwtKeySet::with_secret("student-secret");
+        config.jwt.admin = JwtKeySet::with_secret("admin-secret");
+        config.credentials.keys = vec![format!("{}:{}", LEGACY_KID, "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=")];
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn validation_lists_every_error() {
+        let mut config = AppConfig::default();
+        config.server.bind_addr = "not an address".to_string();
+        config.server.trusted_proxies = vec!["10.0.0.0/33".to_string()];
+        config.jwt.student.signing_kid = "missing".to_string();
+        let errors = validation_errors(&config);
+        assert!(errors.contains("server.bind_addr 'not an address'"));
+        assert!(errors.contains("server.trusted_proxies entry '10.0.0.0/33'"));
+        assert!(errors.contains("jwt.student.signing_kid 'missing'"));
+    }
+
+    #[test]
+    fn trusted_proxies_match_cidrs_and_single_addresses() {
+        let server = ServerConfig {
+            trusted_proxies: vec!["10.0.0.0/8".to_string(), "::1".to_string()],
+            ..ServerConfig::default()
+        };
+        assert!(server.is_trusted_proxy("10.1.2.3".parse().unwrap()));
+        assert!(server.is_trusted_proxy("::1".parse().unwrap()));
+        assert!(!server.is_trusted_proxy("11.0.0.1".parse().unwrap()));
+        assert!(!ServerConfig::default().is_trusted_proxy("127.0.0.1".parse().unwrap()));
+    }
+}
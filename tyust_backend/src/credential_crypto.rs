@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes256Gcm, Key, KeyInit, Nonce,
+};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use once_cell::sync::Lazy;
+use rand::RngCore;
+
+use crate::config::{self, CredentialKeyConfig};
+
+/// AES-GCM 随机数长度
+const NONCE_LEN: usize = 12;
+
+/// 上游凭证的主密钥
+static MASTER_KEYS: Lazy<MasterKeys> = Lazy::new(|| MasterKeys::new(&config::get().credentials));
+
+/// 包装后的数据密钥，与密文一起保存在数据库中
+#[derive(Debug, Clone)]
+pub struct SealedKey {
+    pub key_id: String,      // 包装所用主密钥的 kid
+    pub wrapped_key: String, // base64(nonce || 密文)
+}
+
+/// 每条缓存记录独立的数据密钥
+///
+/// 字段使用数据密钥加密，数据密钥再用主密钥包装（信封加密）。
+/// 轮换主密钥时只需重新包装数据密钥，不必重新加密字段。
+pub struct DataKey {
+    key: [u8; 32],
+}
+
+impl DataKey {
+    /// 生成新的数据密钥，并用当前主密钥包装
+    pub fn generate(student_id: &str) -> Result<(Self, SealedKey)> {
+        let mut key = [0u8; 32];
+        rand::rng().fill_bytes(&mut key);
+        let data_key = Self { key };
+        let sealed = MASTER_KEYS.wrap(student_id, &data_key)?;
+        Ok((data_key, sealed))
+    }
+
+    /// 解开数据库中保存的数据密钥
+    pub fn open(student_id: &str, sealed: &SealedKey) -> Result<Self> {
+        MASTER_KEYS.unwrap(student_id, sealed)
+    }
+
+    /// 加密一个字段，字段名和学号作为附加数据，防止密文在记录或字段间被调换
+    pub fn encrypt(&self, student_id: &str, field: &str, plaintext: &str) -> Result<String> {
+        seal(&self.key, &aad(student_id, field), plaintext.as_bytes())
+    }
+
+    /// 解密一个字段
+    pub fn decrypt(&self, student_id: &str, field: &str, ciphertext: &str) -> Result<String> {
+        let plaintext = open(&self.key, &aad(student_id, field), ciphertext)?;
+        String::from_utf8(plaintext).map_err(|_| anyhow!("decrypted {} is not valid UTF-8", field))
+    }
+}
+
+/// 用当前主密钥重新包装数据密钥
+pub fn rewrap(student_id: &str, sealed: &SealedKey) -> Result<SealedKey> {
+    MASTER_KEYS.rewrap(student_id, sealed)
+}
+
+/// 直接用当前主密钥加密，结果形如 `kid:base64(nonce || 密文)`
+///
+/// 用于缓存中的短期凭证副本，避免凭证以明文形式出现在 Redis 中。
+pub fn seal_with_master_key(context: &str, plaintext: &str) -> Result<String> {
+    let master = MASTER_KEYS
+        .keys
+        .get(&MASTER_KEYS.active_kid)
+        .expect("active credential key validated at startup");
+    let sealed = seal(master, context.as_bytes(), plaintext.as_bytes())?;
+    Ok(format!("{}:{}", MASTER_KEYS.active_kid, sealed))
+}
+
+/// 解密 `seal_with_master_key` 的结果
+pub fn open_with_master_key(context: &str, sealed: &str) -> Result<String> {
+    let (kid, encoded) = sealed
+        .split_once(':')
+        .ok_or_else(|| anyhow!("sealed value is missing key id"))?;
+    let master = MASTER_KEYS
+        .keys
+        .get(kid)
+        .ok_or_else(|| anyhow!("unknown credential key id '{}'", kid))?;
+    let plaintext = open(master, context.as_bytes(), encoded)?;
+    String::from_utf8(plaintext).map_err(|_| anyhow!("decrypted value is not valid UTF-8"))
+}
+
+/// 一组可轮换的主密钥
+struct MasterKeys {
+    active_kid: String,
+    keys: HashMap<String, [u8; 32]>,
+}
+
+impl MasterKeys {
+    fn new(config: &CredentialKeyConfig) -> Self {
+        Self {
+            active_kid: config.active_kid.clone(),
+            keys: config
+                .parsed_keys()
+                .into_iter()
+                .map(|(kid, key)| (kid.to_string(), key))
+                .collect(),
+        }
+    }
+
+    fn wrap(&self, student_id: &str, data_key: &DataKey) -> Result<SealedKey> {
+        let master = self
+            .keys
+            .get(&self.active_kid)
+            .expect("active credential key validated at startup");
+        Ok(SealedKey {
+            key_id: self.active_kid.clone(),
+            wrapped_key: seal(master, &aad(student_id, "data_key"), &data_key.key)?,
+        })
+    }
+
+    fn unwrap(&self, student_id: &str, sealed: &SealedKey) -> Result<DataKey> {
+        let master = self
+            .keys
+            .get(&sealed.key_id)
+            .ok_or_else(|| anyhow!("unknown credential key id '{}'", sealed.key_id))?;
+        let key = open(master, &aad(student_id, "data_key"), &sealed.wrapped_key)?
+            .try_into()
+            .map_err(|_| anyhow!("unwrapped data key has invalid length"))?;
+        Ok(DataKey { key })
+    }
+
+    fn rewrap(&self, student_id: &str, sealed: &SealedKey) -> Result<SealedKey> {
+        let data_key = self.unwrap(student_id, sealed)?;
+        self.wrap(student_id, &data_key)
+    }
+}
+
+fn aad(student_id: &str, field: &str) -> Vec<u8> {
+    format!("{}:{}", student_id, field).into_bytes()
+}
+
+fn seal(key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> Result<String> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rng().fill_bytes(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+        .map_err(|_| anyhow!("encryption failed"))?;
+
+    let mut output = nonce.to_vec();
+    output.extend_from_slice(&ciphertext);
+    Ok(general_purpose::STANDARD.encode(output))
+}
+
+fn open(key: &[u8; 32], aad: &[u8], encoded: &str) -> Result<Vec<u8>> {
+    let bytes = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| anyhow!("invalid ciphertext encoding: {}", e))?;
+    if bytes.len() < NONCE_LEN {
+        return Err(anyhow!("ciphertext too short"));
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|_| anyhow!("decryption failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD_KEY: &str = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+    const NEW_KEY: &str = "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+
+    fn master_keys(active_kid: &str, keys: &[(&str, &str)]) -> MasterKeys {
+        MasterKeys::new(&CredentialKeyConfig {
+            active_kid: active_kid.to_string(),
+            keys: keys.iter().map(|(kid, key)| format!("{}:{}", kid, key)).collect(),
+        })
+    }
+
+    fn data_key(byte: u8) -> DataKey {
+        DataKey { key: [byte; 32] }
+    }
+
+    #[test]
+    fn data_key_round_trip() {
+        let key = data_key(7);
+        let ciphertext = key.encrypt("2021001", "password", "secret").unwrap();
+        assert_ne!(ciphertext, "secret");
+        assert_eq!(key.decrypt("2021001", "password", &ciphertext).unwrap(), "secret");
+        // 每次加密使用新的随机数
+        assert_ne!(key.encrypt("2021001", "password", "secret").unwrap(), ciphertext);
+    }
+
+    #[test]
+    fn data_key_rejects_wrong_key_or_tampering() {
+        let ciphertext = data_key(7).encrypt("2021001", "password", "secret").unwrap();
+        assert!(data_key(8).decrypt("2021001", "password", &ciphertext).is_err());
+
+        let mut bytes = general_purpose::STANDARD.decode(&ciphertext).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        let tampered = general_purpose::STANDARD.encode(bytes);
+        assert!(data_key(7).decrypt("2021001", "password", &tampered).is_err());
+        assert!(data_key(7).decrypt("2021001", "password", "AAAA").is_err());
+    }
+
+    #[test]
+    fn data_key_rejects_swapped_student_or_field() {
+        let key = data_key(7);
+        let ciphertext = key.encrypt("2021001", "password", "secret").unwrap();
+        assert!(key.decrypt("2021002", "password", &ciphertext).is_err());
+        assert!(key.decrypt("2021001", "cookie", &ciphertext).is_err());
+    }
+
+    #[test]
+    fn wrapped_data_key_is_bound_to_student() {
+        let keys = master_keys("old", &[("old", OLD_KEY)]);
+        let sealed = keys.wrap("2021001", &data_key(7)).unwrap();
+        assert_eq!(sealed.key_id, "old");
+        assert_eq!(keys.unwrap("2021001", &sealed).unwrap().key, [7; 32]);
+        assert!(keys.unwrap("2021002", &sealed).is_err());
+    }
+
+    #[test]
+    fn rewrap_moves_data_key_to_active_kid() {
+        let before = master_keys("old", &[("old", OLD_KEY)]);
+        let sealed = before.wrap("2021001", &data_key(7)).unwrap();
+        let ciphertext = data_key(7).encrypt("2021001", "password", "secret").unwrap();
+
+        let rotating = master_keys("new", &[("old", OLD_KEY), ("new", NEW_KEY)]);
+        let rewrapped = rotating.rewrap("2021001", &sealed).unwrap();
+        assert_eq!(rewrapped.key_id, "new");
+
+        // 移除旧密钥后仍能解开数据密钥并解密原有字段
+        let after = master_keys("new", &[("new", NEW_KEY)]);
+        assert!(after.unwrap("2021001", &sealed).is_err());
+        let key = after.unwrap("2021001", &rewrapped).unwrap();
+        assert_eq!(key.decrypt("2021001", "password", &ciphertext).unwrap(), "secret");
+    }
+
+    #[test]
+    fn unwrap_rejects_unknown_kid() {
+        let keys = master_keys("old", &[("old", OLD_KEY)]);
+        let mut sealed = keys.wrap("2021001", &data_key(7)).unwrap();
+        sealed.key_id = "missing".to_string();
+        let error = keys.unwrap("2021001", &sealed).err().unwrap();
+        assert!(error.to_string().contains("unknown credential key id 'missing'"));
+    }
+}
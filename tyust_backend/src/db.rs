@@ -1,9 +1,14 @@
-use sqlx::{PgPool, Row};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use sqlx::migrate::{MigrateError, Migrator};
 use once_cell::sync::OnceCell;
 use std::env;
 use bcrypt::{hash, DEFAULT_COST};
-use crate::entity::UserLoginInfo;
+use crate::entity::{
+    AdminAccount, AuditLogEntry, HourlyLogins, LoginAttempt, LoginPeriodStats, NewAuditLog, ScheduledJobState,
+    SessionDevice, StudentEventRecord, StudentSessionRecord, StudentSummary, UserLoginInfo, WechatSubscription,
+};
 use crate::api_types::SemesterConfig;
+use crate::credential_crypto::{self, DataKey, SealedKey};
 
 static DB_POOL: OnceCell<PgPool> = OnceCell::new();
 
@@ -20,84 +25,59 @@ pub async fn get_db_pool() -> &'static PgPool {
     DB_POOL.get().expect("Database pool not initialized")
 }
 
-/// 初始化数据库表
-pub async fn init_tables(pool: &PgPool) -> Result<(), sqlx::Error> {
-    // 创建用户表（添加avatar_url字段）
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS users (
-            student_id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            class TEXT,
-            token TEXT,
-            avatar_url TEXT,
-            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-        )"
-    )
-    .execute(pool)
-    .await?;
-    
-    // 如果表已存在但没有avatar_url字段，则添加该字段
-    let _ = sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar_url TEXT")
-        .execute(pool)
-        .await;
-    
-    // 创建用户认证缓存表
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS user_auth_cache (
-            student_id TEXT PRIMARY KEY,
-            sourceid_tgc TEXT,
-            rg_objectid TEXT,
-            access_token TEXT,
-            route TEXT,
-            jwglxt_jsession TEXT,
-            ronghemenhu_jsession TEXT,
-            code TEXT,
-            cached_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-        )"
-    )
-    .execute(pool)
-    .await?;
-    
-    // 创建登录统计表
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS login_stats (
-            id SERIAL PRIMARY KEY,
-            student_id TEXT NOT NULL,
-            login_time TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-        )"
-    )
-    .execute(pool)
-    .await?;
-    
-    // 创建学期配置表
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS semester_config (
-            id SERIAL PRIMARY KEY,
-            semester_name TEXT NOT NULL,
-            semester_start_date TEXT NOT NULL,
-            is_active BOOLEAN DEFAULT true,
-            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-        )"
-    )
-    .execute(pool)
-    .await?;
+/// 嵌入二进制的数据库迁移（见 migrations 目录），已应用的版本记录在 _sqlx_migrations 表中
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// 一个迁移的应用状态
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// 列出所有迁移及其是否已应用
+pub async fn migration_status(pool: &PgPool) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+    let (tracked,): (bool,) = sqlx::query_as("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+    let applied: Vec<(i64, chrono::DateTime<chrono::Utc>)> = if tracked {
+        sqlx::query_as("SELECT version, installed_on FROM _sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await?
+    } else {
+        Vec::new()
+    };
     
-    // 创建管理员表
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS admins (
-            id SERIAL PRIMARY KEY,
-            username TEXT UNIQUE NOT NULL,
-            password_hash TEXT NOT NULL,
-            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-        )"
-    )
-    .execute(pool)
-    .await?;
+    Ok(MIGRATOR
+        .iter()
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            description: migration.description.to_string(),
+            applied_at: applied
+                .iter()
+                .find(|(version, _)| *version == migration.version)
+                .map(|(_, applied_at)| *applied_at),
+        })
+        .collect())
+}
+
+/// 应用所有尚未应用的迁移，返回本次应用的迁移
+///
+/// 每个迁移在独立事务中执行，失败时回滚该迁移并返回错误。
+pub async fn run_migrations(pool: &PgPool) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let pending: Vec<MigrationStatus> = migration_status(pool)
+        .await?
+        .into_iter()
+        .filter(|migration| migration.applied_at.is_none())
+        .collect();
+    MIGRATOR.run(pool).await?;
     
-    // 创建默认管理员账户（如果不存在）
+    Ok(pending)
+}
+
+/// 创建默认管理员账户（如果不存在）
+pub async fn ensure_default_admin(pool: &PgPool) -> Result<(), sqlx::Error> {
     // 使用环境变量中的默认密码，如果未设置则使用默认值
     let default_password = std::env::var("DEFAULT_ADMIN_PASSWORD")
         .unwrap_or_else(|_| "admin123".to_string());
@@ -106,7 +86,7 @@ pub async fn init_tables(pool: &PgPool) -> Result<(), sqlx::Error> {
     let hashed_password = bcrypt::hash(&default_password, bcrypt::DEFAULT_COST)
         .map_err(|e| sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
     
-    let _ = sqlx::query(
+    sqlx::query(
         "INSERT INTO admins (username, password_hash) 
          VALUES ($1, $2) 
          ON CONFLICT (username) DO NOTHING"
@@ -114,7 +94,7 @@ pub async fn init_tables(pool: &PgPool) -> Result<(), sqlx::Error> {
     .bind("admin")
     .bind(&hashed_password)
     .execute(pool)
-    .await;
+    .await?;
     
     Ok(())
 }
@@ -122,15 +102,14 @@ pub async fn init_tables(pool: &PgPool) -> Result<(), sqlx::Error> {
 /// 保存用户信息（包括头像URL）
 pub async fn save_user(pool: &PgPool, user: &UserLoginInfo) -> Result<(), sqlx::Error> {
     sqlx::query(
-        "INSERT INTO users (student_id, name, class, token, avatar_url, updated_at) 
-         VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP)
+        "INSERT INTO users (student_id, name, class, avatar_url, updated_at) 
+         VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
          ON CONFLICT (student_id) 
-         DO UPDATE SET name = EXCLUDED.name, class = EXCLUDED.class, token = EXCLUDED.token, avatar_url = EXCLUDED.avatar_url, updated_at = CURRENT_TIMESTAMP"
+         DO UPDATE SET name = EXCLUDED.name, class = EXCLUDED.class, avatar_url = EXCLUDED.avatar_url, updated_at = CURRENT_TIMESTAMP"
     )
     .bind(&user.student_id)
     .bind(&user.name)
     .bind(&user.class)
-    .bind(&user.token)
     .bind(&user.avatar_url)
     .execute(pool)
     .await?;
@@ -141,7 +120,7 @@ pub async fn save_user(pool: &PgPool, user: &UserLoginInfo) -> Result<(), sqlx::
 /// 获取用户信息
 pub async fn get_user(pool: &PgPool, student_id: &str) -> Result<Option<UserLoginInfo>, sqlx::Error> {
     let row = sqlx::query(
-        "SELECT student_id, name, class, token, avatar_url FROM users WHERE student_id = $1"
+        "SELECT student_id, name, class, avatar_url FROM users WHERE student_id = $1"
     )
     .bind(student_id)
     .fetch_optional(pool)
@@ -152,17 +131,143 @@ pub async fn get_user(pool: &PgPool, student_id: &str) -> Result<Option<UserLogi
             student_id: row.get(0),
             name: row.get(1),
             class: row.get(2),
-            token: row.get(3),
-            avatar_url: row.get(4),
+            avatar_url: row.get(3),
         }))
     } else {
         Ok(None)
     }
 }
 
-/// 删除用户信息
-pub async fn delete_user(pool: &PgPool, student_id: &str) -> Result<(), sqlx::Error> {
-    sqlx::query("DELETE FROM users WHERE student_id = $1")
+/// 统计使用某个头像URL的学生数
+pub async fn count_users_with_avatar(pool: &PgPool, avatar_url: &str) -> Result<i64, sqlx::Error> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE avatar_url = $1")
+        .bind(avatar_url)
+        .fetch_one(pool)
+        .await?;
+    
+    Ok(count)
+}
+
+/// 列出在 updated_before 之前更新过资料且设置了头像的学生及其头像字段
+///
+/// 最近更新的学生可能正在上传头像（文件已写入、数据库尚未更新），清理时跳过。
+pub async fn list_avatar_owners(pool: &PgPool, updated_before: chrono::NaiveDateTime) -> Result<Vec<(String, String)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT student_id, avatar_url FROM users WHERE avatar_url IS NOT NULL AND updated_at < $1"
+    )
+    .bind(updated_before)
+    .fetch_all(pool)
+    .await
+}
+
+/// 学生列表排序字段
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StudentSortField {
+    #[default]
+    LastActive,
+    StudentId,
+    Name,
+    Class,
+    CreatedAt,
+}
+
+impl StudentSortField {
+    fn column(&self) -> &'static str {
+        match self {
+            StudentSortField::LastActive => "s.last_active_at",
+            StudentSortField::StudentId => "u.student_id",
+            StudentSortField::Name => "u.name",
+            StudentSortField::Class => "u.class",
+            StudentSortField::CreatedAt => "u.created_at",
+        }
+    }
+}
+
+/// 学生列表查询条件
+#[derive(Debug, Default)]
+pub struct StudentQuery {
+    pub search: Option<String>,
+    pub sort_by: StudentSortField,
+    pub descending: bool,
+}
+
+fn push_student_filter(builder: &mut QueryBuilder<'_, Postgres>, query: &StudentQuery) {
+    if let Some(search) = &query.search {
+        let pattern = format!("%{}%", search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        builder
+            .push(" WHERE (u.student_id ILIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR u.name ILIKE ")
+            .push_bind(pattern)
+            .push(")");
+    }
+}
+
+/// 分页查询学生列表，返回当前页数据和总条数
+pub async fn list_students(
+    pool: &PgPool,
+    query: &StudentQuery,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<StudentSummary>, i64), sqlx::Error> {
+    let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM users u");
+    push_student_filter(&mut count_query, query);
+    let (total,): (i64,) = count_query.build_query_as().fetch_one(pool).await?;
+    
+    let mut list_query = QueryBuilder::new(
+        "SELECT u.student_id, u.name, u.class, u.avatar_url, u.created_at, s.last_active_at, d.student_id IS NOT NULL
+         FROM users u
+         LEFT JOIN disabled_students d ON d.student_id = u.student_id
+         LEFT JOIN (
+             SELECT student_id, MAX(last_seen_at) AS last_active_at FROM student_sessions GROUP BY student_id
+         ) s ON s.student_id = u.student_id"
+    );
+    push_student_filter(&mut list_query, query);
+    list_query
+        .push(" ORDER BY ")
+        .push(query.sort_by.column())
+        .push(if query.descending { " DESC NULLS LAST" } else { " ASC NULLS LAST" })
+        .push(", u.student_id ASC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+    
+    let rows = list_query.build().fetch_all(pool).await?;
+    let students = rows
+        .into_iter()
+        .map(|row| StudentSummary {
+            student_id: row.get(0),
+            name: row.get(1),
+            class: row.get(2),
+            avatar_url: row.get(3),
+            created_at: row.get(4),
+            last_active_at: row.get(5),
+            disabled: row.get(6),
+        })
+        .collect();
+    
+    Ok((students, total))
+}
+
+/// 禁用学生访问
+pub async fn disable_student(pool: &PgPool, student_id: &str, reason: Option<&str>, disabled_by: Option<i32>) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO disabled_students (student_id, reason, disabled_by) VALUES ($1, $2, $3)
+         ON CONFLICT (student_id) DO UPDATE SET reason = EXCLUDED.reason, disabled_by = EXCLUDED.disabled_by, disabled_at = CURRENT_TIMESTAMP"
+    )
+    .bind(student_id)
+    .bind(reason)
+    .bind(disabled_by)
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// 恢复学生访问
+pub async fn enable_student(pool: &PgPool, student_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM disabled_students WHERE student_id = $1")
         .bind(student_id)
         .execute(pool)
         .await?;
@@ -170,13 +275,41 @@ pub async fn delete_user(pool: &PgPool, student_id: &str) -> Result<(), sqlx::Er
     Ok(())
 }
 
-/// 保存用户认证缓存
+/// 学生是否已被禁用
+pub async fn is_student_disabled(pool: &PgPool, student_id: &str) -> Result<bool, sqlx::Error> {
+    let (disabled,): (bool,) = sqlx::query_as("SELECT EXISTS (SELECT 1 FROM disabled_students WHERE student_id = $1)")
+        .bind(student_id)
+        .fetch_one(pool)
+        .await?;
+    
+    Ok(disabled)
+}
+
+/// 加密保存的认证缓存字段，这些凭证可直接冒充学生访问学校系统
+const ENCRYPTED_CACHE_FIELDS: [&str; 4] = ["sourceid_tgc", "access_token", "jwglxt_jsession", "ronghemenhu_jsession"];
+
+/// 保存用户认证缓存（敏感字段加密后写入）
 pub async fn save_auth_cache(pool: &PgPool, student_id: &str, cache: &crate::auth::UserAuthCache) -> Result<(), sqlx::Error> {
+    let (data_key, sealed) = DataKey::generate(student_id).map_err(|e| sqlx::Error::Encode(e.into()))?;
+    let mut encrypted = Vec::with_capacity(ENCRYPTED_CACHE_FIELDS.len());
+    for (field, value) in ENCRYPTED_CACHE_FIELDS.iter().zip([
+        &cache.sourceid_tgc,
+        &cache.access_token,
+        &cache.jwglxt_jsession,
+        &cache.ronghemenhu_jsession,
+    ]) {
+        encrypted.push(
+            data_key
+                .encrypt(student_id, field, value)
+                .map_err(|e| sqlx::Error::Encode(e.into()))?,
+        );
+    }
+    
     sqlx::query(
         "INSERT INTO user_auth_cache (
             student_id, sourceid_tgc, rg_objectid, access_token, 
-            route, jwglxt_jsession, ronghemenhu_jsession, code, cached_at
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            route, jwglxt_jsession, ronghemenhu_jsession, code, cached_at, key_id, wrapped_key
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
          ON CONFLICT (student_id) 
          DO UPDATE SET 
             sourceid_tgc = EXCLUDED.sourceid_tgc,
@@ -186,27 +319,32 @@ pub async fn save_auth_cache(pool: &PgPool, student_id: &str, cache: &crate::aut
             jwglxt_jsession = EXCLUDED.jwglxt_jsession,
             ronghemenhu_jsession = EXCLUDED.ronghemenhu_jsession,
             code = EXCLUDED.code,
-            cached_at = EXCLUDED.cached_at"
+            cached_at = EXCLUDED.cached_at,
+            key_id = EXCLUDED.key_id,
+            wrapped_key = EXCLUDED.wrapped_key"
     )
     .bind(student_id)
-    .bind(&cache.sourceid_tgc)
+    .bind(&encrypted[0])
     .bind(&cache.rg_objectid)
-    .bind(&cache.access_token)
+    .bind(&encrypted[1])
     .bind(&cache.route)
-    .bind(&cache.jwglxt_jsession)
-    .bind(&cache.ronghemenhu_jsession)
+    .bind(&encrypted[2])
+    .bind(&encrypted[3])
     .bind(&cache.code)
     .bind(cache.cached_at.naive_utc())
+    .bind(&sealed.key_id)
+    .bind(&sealed.wrapped_key)
     .execute(pool)
     .await?;
     
     Ok(())
 }
 
-/// 获取用户认证缓存
+/// 获取用户认证缓存（解密敏感字段，密钥缺失或密文损坏时返回错误）
 pub async fn get_auth_cache(pool: &PgPool, student_id: &str) -> Result<Option<crate::auth::UserAuthCache>, sqlx::Error> {
     let row = sqlx::query(
-        "SELECT sourceid_tgc, rg_objectid, access_token, route, jwglxt_jsession, ronghemenhu_jsession, code, cached_at 
+        "SELECT sourceid_tgc, rg_objectid, access_token, route, jwglxt_jsession, ronghemenhu_jsession, code, cached_at,
+                key_id, wrapped_key
          FROM user_auth_cache WHERE student_id = $1"
     )
     .bind(student_id)
@@ -214,13 +352,28 @@ pub async fn get_auth_cache(pool: &PgPool, student_id: &str) -> Result<Option<cr
     .await?;
 
     if let Some(row) = row {
+        let mut values: [String; 4] = [row.get(0), row.get(2), row.get(4), row.get(5)];
+        let key_id: Option<String> = row.get(8);
+        let wrapped_key: Option<String> = row.get(9);
+        // 尚未迁移的明文记录直接返回
+        if let (Some(key_id), Some(wrapped_key)) = (key_id, wrapped_key) {
+            let data_key = DataKey::open(student_id, &SealedKey { key_id, wrapped_key })
+                .map_err(|e| sqlx::Error::Decode(e.into()))?;
+            for (field, value) in ENCRYPTED_CACHE_FIELDS.iter().zip(values.iter_mut()) {
+                *value = data_key
+                    .decrypt(student_id, field, value)
+                    .map_err(|e| sqlx::Error::Decode(e.into()))?;
+            }
+        }
+        let [sourceid_tgc, access_token, jwglxt_jsession, ronghemenhu_jsession] = values;
+        
         Ok(Some(crate::auth::UserAuthCache {
-            sourceid_tgc: row.get(0),
+            sourceid_tgc,
             rg_objectid: row.get(1),
-            access_token: row.get(2),
+            access_token,
             route: row.get(3),
-            jwglxt_jsession: row.get(4),
-            ronghemenhu_jsession: row.get(5),
+            jwglxt_jsession,
+            ronghemenhu_jsession,
             code: row.get(6),
             cached_at: chrono::DateTime::from_naive_utc_and_offset(row.get(7), chrono::Utc),
         }))
@@ -229,6 +382,95 @@ pub async fn get_auth_cache(pool: &PgPool, student_id: &str) -> Result<Option<cr
     }
 }
 
+/// 认证缓存加密迁移结果
+#[derive(Debug, Default)]
+pub struct CredentialMigration {
+    pub encrypted: u64, // 明文记录已加密
+    pub rewrapped: u64, // 数据密钥已用当前主密钥重新包装
+    pub dropped: u64,   // 主密钥已不存在、无法解密而删除的记录
+}
+
+/// 加密旧的明文认证缓存，并把旧主密钥包装的数据密钥迁移到当前主密钥
+///
+/// 启动时调用，可重复执行。无法解密的记录会被删除，对应学生需要重新登录。
+pub async fn encrypt_auth_cache(pool: &PgPool) -> Result<CredentialMigration, sqlx::Error> {
+    let active_kid = &crate::config::get().credentials.active_kid;
+    let rows = sqlx::query(
+        "SELECT student_id, sourceid_tgc, access_token, jwglxt_jsession, ronghemenhu_jsession, key_id, wrapped_key
+         FROM user_auth_cache WHERE key_id IS NULL OR key_id <> $1"
+    )
+    .bind(active_kid)
+    .fetch_all(pool)
+    .await?;
+    
+    let mut migration = CredentialMigration::default();
+    for row in rows {
+        let student_id: String = row.get(0);
+        let key_id: Option<String> = row.get(5);
+        let wrapped_key: Option<String> = row.get(6);
+        
+        match (key_id, wrapped_key) {
+            (Some(key_id), Some(wrapped_key)) => {
+                let sealed = SealedKey { key_id, wrapped_key };
+                match credential_crypto::rewrap(&student_id, &sealed) {
+                    Ok(rewrapped) => {
+                        sqlx::query(
+                            "UPDATE user_auth_cache SET key_id = $2, wrapped_key = $3
+                             WHERE student_id = $1 AND key_id = $4"
+                        )
+                        .bind(&student_id)
+                        .bind(&rewrapped.key_id)
+                        .bind(&rewrapped.wrapped_key)
+                        .bind(&sealed.key_id)
+                        .execute(pool)
+                        .await?;
+                        migration.rewrapped += 1;
+                    }
+                    Err(_) => {
+                        delete_auth_cache(pool, &student_id).await?;
+                        migration.dropped += 1;
+                    }
+                }
+            }
+            (Some(_), None) => {
+                delete_auth_cache(pool, &student_id).await?;
+                migration.dropped += 1;
+            }
+            (None, _) => {
+                let (data_key, sealed) = DataKey::generate(&student_id).map_err(|e| sqlx::Error::Encode(e.into()))?;
+                let mut encrypted = Vec::with_capacity(ENCRYPTED_CACHE_FIELDS.len());
+                for (index, field) in ENCRYPTED_CACHE_FIELDS.iter().enumerate() {
+                    let value: Option<String> = row.get(index + 1);
+                    encrypted.push(
+                        value
+                            .map(|value| data_key.encrypt(&student_id, field, &value))
+                            .transpose()
+                            .map_err(|e| sqlx::Error::Encode(e.into()))?,
+                    );
+                }
+                sqlx::query(
+                    "UPDATE user_auth_cache
+                     SET sourceid_tgc = $2, access_token = $3, jwglxt_jsession = $4, ronghemenhu_jsession = $5,
+                         key_id = $6, wrapped_key = $7
+                     WHERE student_id = $1 AND key_id IS NULL"
+                )
+                .bind(&student_id)
+                .bind(&encrypted[0])
+                .bind(&encrypted[1])
+                .bind(&encrypted[2])
+                .bind(&encrypted[3])
+                .bind(&sealed.key_id)
+                .bind(&sealed.wrapped_key)
+                .execute(pool)
+                .await?;
+                migration.encrypted += 1;
+            }
+        }
+    }
+    
+    Ok(migration)
+}
+
 /// 删除用户认证缓存
 pub async fn delete_auth_cache(pool: &PgPool, student_id: &str) -> Result<(), sqlx::Error> {
     sqlx::query("DELETE FROM user_auth_cache WHERE student_id = $1")
@@ -239,9 +481,9 @@ pub async fn delete_auth_cache(pool: &PgPool, student_id: &str) -> Result<(), sq
     Ok(())
 }
 
-/// 清理过期的认证缓存（超过24小时）
+/// 清理过期的认证缓存（有效期见 `auth.auth_cache_ttl_hours` 配置）
 pub async fn cleanup_expired_auth_cache(pool: &PgPool) -> Result<u64, sqlx::Error> {
-    let expired_time = chrono::Utc::now() - chrono::Duration::hours(24);
+    let expired_time = chrono::Utc::now() - chrono::Duration::hours(crate::config::get().auth.auth_cache_ttl_hours);
     
     let result = sqlx::query("DELETE FROM user_auth_cache WHERE cached_at < $1")
         .bind(expired_time.naive_utc())
@@ -251,50 +493,655 @@ pub async fn cleanup_expired_auth_cache(pool: &PgPool) -> Result<u64, sqlx::Erro
     Ok(result.rows_affected())
 }
 
-/// 记录登录统计
-pub async fn record_login(pool: &PgPool, student_id: &str) -> Result<(), sqlx::Error> {
-    sqlx::query("INSERT INTO login_stats (student_id, login_time) VALUES ($1, CURRENT_TIMESTAMP)")
+/// 列出认证缓存未过期且未被禁用的学生
+pub async fn list_students_with_live_auth_cache(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    let expired_time = chrono::Utc::now() - chrono::Duration::hours(crate::config::get().auth.auth_cache_ttl_hours);
+    
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT c.student_id FROM user_auth_cache c
+         LEFT JOIN disabled_students d ON d.student_id = c.student_id
+         WHERE c.cached_at >= $1 AND d.student_id IS NULL
+         ORDER BY c.student_id"
+    )
+    .bind(expired_time.naive_utc())
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(|(student_id,)| student_id).collect())
+}
+
+/// 获取成绩轮询保存的上一次成绩
+pub async fn get_score_snapshot(pool: &PgPool, student_id: &str) -> Result<Option<serde_json::Value>, sqlx::Error> {
+    let row: Option<(serde_json::Value,)> = sqlx::query_as("SELECT scores FROM score_snapshots WHERE student_id = $1")
         .bind(student_id)
-        .execute(pool)
+        .fetch_optional(pool)
         .await?;
     
+    Ok(row.map(|(scores,)| scores))
+}
+
+/// 保存成绩轮询得到的成绩
+pub async fn save_score_snapshot(pool: &PgPool, student_id: &str, scores: &serde_json::Value) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO score_snapshots (student_id, scores, updated_at) VALUES ($1, $2, CURRENT_TIMESTAMP)
+         ON CONFLICT (student_id) DO UPDATE SET scores = EXCLUDED.scores, updated_at = CURRENT_TIMESTAMP"
+    )
+    .bind(student_id)
+    .bind(scores)
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// 统计未过期的认证缓存数量
+pub async fn count_live_auth_cache(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let expired_time = chrono::Utc::now() - chrono::Duration::hours(crate::config::get().auth.auth_cache_ttl_hours);
+    
+    let result: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user_auth_cache WHERE cached_at >= $1")
+        .bind(expired_time.naive_utc())
+        .fetch_one(pool)
+        .await?;
+    
+    Ok(result.0)
+}
+
+/// 记录一次登录尝试（成功或失败）
+pub async fn record_login(pool: &PgPool, attempt: &LoginAttempt) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO login_stats (student_id, login_time, success, duration_ms, client, user_agent, ip, failure_reason)
+         VALUES ($1, $8, $2, $3, $4, $5, $6, $7)"
+    )
+    .bind(&attempt.student_id)
+    .bind(attempt.success)
+    .bind(attempt.duration_ms)
+    .bind(&attempt.client)
+    .bind(&attempt.user_agent)
+    .bind(&attempt.ip)
+    .bind(&attempt.failure_reason)
+    .bind(chrono::Utc::now().naive_utc())
+    .execute(pool)
+    .await?;
+    
     Ok(())
 }
 
-/// 获取总登录次数
+/// 获取总登录次数（仅统计成功的登录）
 pub async fn get_total_logins(pool: &PgPool) -> Result<i64, sqlx::Error> {
-    let result: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM login_stats")
+    let result: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM login_stats WHERE success")
         .fetch_one(pool)
         .await?;
     
     Ok(result.0)
 }
 
+/// 获取指定时间之后成功登录过的学生数
+pub async fn count_active_students_since(pool: &PgPool, since: chrono::NaiveDateTime) -> Result<i64, sqlx::Error> {
+    let result: (i64,) = sqlx::query_as(
+        "SELECT COUNT(DISTINCT student_id) FROM login_stats WHERE success AND login_time > $1"
+    )
+    .bind(since)
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(result.0)
+}
+
+/// 统计时间粒度
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl Granularity {
+    fn trunc_unit(&self) -> &'static str {
+        match self {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+        }
+    }
+
+    /// 未指定起始时间时默认统计的时间范围
+    pub fn default_span(&self) -> chrono::Duration {
+        match self {
+            Granularity::Day => chrono::Duration::days(30),
+            Granularity::Week => chrono::Duration::weeks(12),
+            Granularity::Month => chrono::Duration::days(365),
+        }
+    }
+}
+
+/// 按时间粒度统计活跃用户数和登录成功率
+///
+/// login_time 为 UTC，from/to 也按 UTC 比较；分组按 timezone 时区的日/周/月进行，period 为该时区的时间。
+pub async fn login_time_series(
+    pool: &PgPool,
+    granularity: Granularity,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+    timezone: &str,
+) -> Result<Vec<LoginPeriodStats>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT date_trunc($1, (login_time AT TIME ZONE 'UTC') AT TIME ZONE $4) AS period,
+                COUNT(DISTINCT student_id) FILTER (WHERE success) AS active_users,
+                COUNT(*) AS attempts,
+                COUNT(*) FILTER (WHERE success) AS successes,
+                AVG(duration_ms) FILTER (WHERE success)::DOUBLE PRECISION AS avg_duration_ms
+         FROM login_stats
+         WHERE login_time >= $2 AND login_time < $3
+         GROUP BY period
+         ORDER BY period"
+    )
+    .bind(granularity.trunc_unit())
+    .bind(from)
+    .bind(to)
+    .bind(timezone)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let attempts: i64 = row.get(2);
+            let successes: i64 = row.get(3);
+            LoginPeriodStats {
+                period: row.get(0),
+                active_users: row.get(1),
+                attempts,
+                successes,
+                success_rate: if attempts > 0 { successes as f64 / attempts as f64 } else { 0.0 },
+                avg_duration_ms: row.get(4),
+            }
+        })
+        .collect())
+}
+
+/// 按 timezone 时区的小时统计成功登录次数（0-23点，没有登录的小时补0）
+pub async fn login_peak_hours(
+    pool: &PgPool,
+    since: chrono::NaiveDateTime,
+    timezone: &str,
+) -> Result<Vec<HourlyLogins>, sqlx::Error> {
+    let rows: Vec<(i32, i64)> = sqlx::query_as(
+        "SELECT h.hour, COUNT(l.id)
+         FROM generate_series(0, 23) AS h(hour)
+         LEFT JOIN login_stats l
+           ON EXTRACT(HOUR FROM (l.login_time AT TIME ZONE 'UTC') AT TIME ZONE $2)::INTEGER = h.hour
+          AND l.success AND l.login_time >= $1
+         GROUP BY h.hour
+         ORDER BY h.hour"
+    )
+    .bind(since)
+    .bind(timezone)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(rows
+        .into_iter()
+        .map(|(hour, logins)| HourlyLogins { hour, logins })
+        .collect())
+}
+
+/// 管理员查询字段
+const ADMIN_COLUMNS: &str = "id, username, password_hash, role, is_active, token_version, created_at, updated_at";
+
+fn admin_from_row(row: &sqlx::postgres::PgRow) -> AdminAccount {
+    AdminAccount {
+        id: row.get(0),
+        username: row.get(1),
+        password_hash: row.get(2),
+        role: row.get(3),
+        is_active: row.get(4),
+        token_version: row.get(5),
+        created_at: row.get(6),
+        updated_at: row.get(7),
+    }
+}
+
 /// 获取管理员信息
-pub async fn get_admin(pool: &PgPool, username: &str) -> Result<Option<(i32, String, String)>, sqlx::Error> {
-    let admin = sqlx::query_as::<_, (i32, String, String)>(
-        "SELECT id, username, password_hash FROM admins WHERE username = $1"
+pub async fn get_admin(pool: &PgPool, username: &str) -> Result<Option<AdminAccount>, sqlx::Error> {
+    let row = sqlx::query(&format!("SELECT {} FROM admins WHERE username = $1", ADMIN_COLUMNS))
+        .bind(username)
+        .fetch_optional(pool)
+        .await?;
+    
+    Ok(row.as_ref().map(admin_from_row))
+}
+
+/// 根据ID获取管理员信息
+pub async fn get_admin_by_id(pool: &PgPool, admin_id: i32) -> Result<Option<AdminAccount>, sqlx::Error> {
+    let row = sqlx::query(&format!("SELECT {} FROM admins WHERE id = $1", ADMIN_COLUMNS))
+        .bind(admin_id)
+        .fetch_optional(pool)
+        .await?;
+    
+    Ok(row.as_ref().map(admin_from_row))
+}
+
+/// 获取所有管理员
+pub async fn list_admins(pool: &PgPool) -> Result<Vec<AdminAccount>, sqlx::Error> {
+    let rows = sqlx::query(&format!("SELECT {} FROM admins ORDER BY id", ADMIN_COLUMNS))
+        .fetch_all(pool)
+        .await?;
+    
+    Ok(rows.iter().map(admin_from_row).collect())
+}
+
+/// 创建管理员，返回新管理员ID
+pub async fn create_admin(pool: &PgPool, username: &str, password_hash: &str, role: &str) -> Result<i32, sqlx::Error> {
+    let (admin_id,): (i32,) = sqlx::query_as(
+        "INSERT INTO admins (username, password_hash, role) VALUES ($1, $2, $3) RETURNING id"
     )
     .bind(username)
+    .bind(password_hash)
+    .bind(role)
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(admin_id)
+}
+
+/// 更新管理员角色
+pub async fn update_admin_role(pool: &PgPool, admin_id: i32, role: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE admins SET role = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2"
+    )
+    .bind(role)
+    .bind(admin_id)
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// 启用或禁用管理员
+pub async fn set_admin_active(pool: &PgPool, admin_id: i32, is_active: bool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE admins SET is_active = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2"
+    )
+    .bind(is_active)
+    .bind(admin_id)
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// 统计启用状态的超级管理员数量
+pub async fn count_active_super_admins(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let result: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM admins WHERE role = 'super_admin' AND is_active = true"
+    )
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(result.0)
+}
+
+/// 保存管理员 refresh token 摘要
+pub async fn save_admin_refresh_token(
+    pool: &PgPool,
+    admin_id: i32,
+    token_hash: &str,
+    token_version: i32,
+    expires_at: chrono::NaiveDateTime,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO admin_refresh_tokens (admin_id, token_hash, token_version, expires_at) VALUES ($1, $2, $3, $4)"
+    )
+    .bind(admin_id)
+    .bind(token_hash)
+    .bind(token_version)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// 消费一个有效的管理员 refresh token，返回 (管理员ID, 签发时的 token 版本)
+///
+/// token 被标记为已吊销，因此每个 refresh token 只能使用一次。
+pub async fn consume_admin_refresh_token(pool: &PgPool, token_hash: &str) -> Result<Option<(i32, i32)>, sqlx::Error> {
+    sqlx::query_as(
+        "UPDATE admin_refresh_tokens SET revoked_at = $2
+         WHERE token_hash = $1 AND revoked_at IS NULL AND expires_at > $2
+         RETURNING admin_id, token_version"
+    )
+    .bind(token_hash)
+    .bind(chrono::Utc::now().naive_utc())
     .fetch_optional(pool)
+    .await
+}
+
+/// 吊销属于该管理员的一个 refresh token，返回是否吊销成功
+pub async fn revoke_admin_refresh_token(pool: &PgPool, admin_id: i32, token_hash: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE admin_refresh_tokens SET revoked_at = $3
+         WHERE token_hash = $1 AND admin_id = $2 AND revoked_at IS NULL AND expires_at > $3"
+    )
+    .bind(token_hash)
+    .bind(admin_id)
+    .bind(chrono::Utc::now().naive_utc())
+    .execute(pool)
     .await?;
     
-    Ok(admin)
+    Ok(result.rows_affected() > 0)
 }
 
-/// 创建管理员
-pub async fn create_admin(pool: &PgPool, username: &str, password_hash: &str) -> Result<(), sqlx::Error> {
+/// 保存学生 refresh token 摘要
+pub async fn save_student_refresh_token(
+    pool: &PgPool,
+    student_id: &str,
+    family_id: &str,
+    token_hash: &str,
+    expires_at: chrono::NaiveDateTime,
+) -> Result<(), sqlx::Error> {
     sqlx::query(
-        "INSERT INTO admins (username, password_hash) VALUES ($1, $2)"
+        "INSERT INTO student_refresh_tokens (student_id, family_id, token_hash, expires_at) VALUES ($1, $2, $3, $4)"
     )
-    .bind(username)
-    .bind(password_hash)
+    .bind(student_id)
+    .bind(family_id)
+    .bind(token_hash)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// 学生 refresh token 轮换结果
+#[derive(Debug)]
+pub enum RefreshRotation {
+    /// 旧 token 已作废，新 token 已写入同一 family
+    Rotated { student_id: String, family_id: String },
+    /// 旧 token 已被使用过，整个 family 已被吊销
+    Reused { student_id: String, family_id: String },
+    /// token 不存在、已过期或已吊销
+    Invalid,
+}
+
+/// 轮换学生 refresh token：旧 token 标记为已使用并签发同一 family 的新 token
+///
+/// 已使用过的 token 再次出现说明可能被盗用，此时吊销整个 family。
+pub async fn rotate_student_refresh_token(
+    pool: &PgPool,
+    token_hash: &str,
+    new_token_hash: &str,
+    new_expires_at: chrono::NaiveDateTime,
+) -> Result<RefreshRotation, sqlx::Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+    
+    let row = sqlx::query(
+        "SELECT id, student_id, family_id, used_at IS NOT NULL, revoked_at IS NOT NULL OR expires_at <= $2
+         FROM student_refresh_tokens WHERE token_hash = $1 FOR UPDATE"
+    )
+    .bind(token_hash)
+    .bind(now)
+    .fetch_optional(&mut *tx)
+    .await?;
+    
+    let Some(row) = row else {
+        return Ok(RefreshRotation::Invalid);
+    };
+    let id: i64 = row.get(0);
+    let student_id: String = row.get(1);
+    let family_id: String = row.get(2);
+    let used: bool = row.get(3);
+    let inactive: bool = row.get(4);
+    
+    if used {
+        sqlx::query(
+            "UPDATE student_refresh_tokens SET revoked_at = $2 WHERE family_id = $1 AND revoked_at IS NULL"
+        )
+        .bind(&family_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE student_sessions SET revoked_at = $2 WHERE id = $1 AND revoked_at IS NULL")
+            .bind(&family_id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        return Ok(RefreshRotation::Reused { student_id, family_id });
+    }
+    if inactive {
+        return Ok(RefreshRotation::Invalid);
+    }
+    
+    sqlx::query("UPDATE student_refresh_tokens SET used_at = $2 WHERE id = $1")
+        .bind(id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    // 刷新后会话有效期随新的 refresh token 顺延
+    sqlx::query("UPDATE student_sessions SET expires_at = $2, last_seen_at = $3 WHERE id = $1")
+        .bind(&family_id)
+        .bind(new_expires_at)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO student_refresh_tokens (student_id, family_id, token_hash, expires_at) VALUES ($1, $2, $3, $4)"
+    )
+    .bind(&student_id)
+    .bind(&family_id)
+    .bind(new_token_hash)
+    .bind(new_expires_at)
+    .execute(&mut *tx)
+    .await?;
+    
+    tx.commit().await?;
+    Ok(RefreshRotation::Rotated { student_id, family_id })
+}
+
+/// 创建学生会话
+pub async fn create_student_session(
+    pool: &PgPool,
+    session_id: &str,
+    student_id: &str,
+    device: &SessionDevice,
+    expires_at: chrono::NaiveDateTime,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO student_sessions (id, student_id, device_name, client, ip, expires_at, created_at, last_seen_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $7)"
+    )
+    .bind(session_id)
+    .bind(student_id)
+    .bind(&device.device_name)
+    .bind(&device.client)
+    .bind(&device.ip)
+    .bind(expires_at)
+    .bind(chrono::Utc::now().naive_utc())
     .execute(pool)
     .await?;
     
     Ok(())
 }
 
+/// 检查会话并更新最近活跃时间
+///
+/// 会话不属于该学生、已吊销或已过期时返回 None，否则返回学生是否已被禁用。
+/// 调用方按会话缓存结果，因此只在缓存未命中时更新活跃时间。
+pub async fn check_student_session(pool: &PgPool, session_id: &str, student_id: &str) -> Result<Option<bool>, sqlx::Error> {
+    let disabled: Option<(bool,)> = sqlx::query_as(
+        "UPDATE student_sessions s SET last_seen_at = $3
+         WHERE s.id = $1 AND s.student_id = $2 AND s.revoked_at IS NULL AND s.expires_at > $3
+         RETURNING EXISTS (SELECT 1 FROM disabled_students d WHERE d.student_id = s.student_id)"
+    )
+    .bind(session_id)
+    .bind(student_id)
+    .bind(chrono::Utc::now().naive_utc())
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(disabled.map(|(disabled,)| disabled))
+}
+
+/// 列出学生仍然有效的会话，最近活跃的在前
+pub async fn list_student_sessions(pool: &PgPool, student_id: &str) -> Result<Vec<StudentSessionRecord>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT id, device_name, client, ip, created_at, last_seen_at FROM student_sessions
+         WHERE student_id = $1 AND revoked_at IS NULL AND expires_at > $2
+         ORDER BY last_seen_at DESC NULLS LAST, created_at DESC"
+    )
+    .bind(student_id)
+    .bind(chrono::Utc::now().naive_utc())
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(rows
+        .into_iter()
+        .map(|row| StudentSessionRecord {
+            id: row.get(0),
+            device_name: row.get(1),
+            client: row.get(2),
+            ip: row.get(3),
+            created_at: row.get(4),
+            last_seen_at: row.get(5),
+        })
+        .collect())
+}
+
+/// 吊销学生的某个会话及其 refresh token，会话不存在或不属于该学生时返回 false
+pub async fn revoke_student_session(pool: &PgPool, student_id: &str, session_id: &str) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+    
+    let result = sqlx::query(
+        "UPDATE student_sessions SET revoked_at = $3
+         WHERE id = $1 AND student_id = $2 AND revoked_at IS NULL"
+    )
+    .bind(session_id)
+    .bind(student_id)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    sqlx::query(
+        "UPDATE student_refresh_tokens SET revoked_at = $2 WHERE family_id = $1 AND revoked_at IS NULL"
+    )
+    .bind(session_id)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+    
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// 吊销学生的所有会话及 refresh token（强制登出、禁用时调用）
+pub async fn revoke_student_sessions(pool: &PgPool, student_id: &str) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+    
+    sqlx::query("UPDATE student_sessions SET revoked_at = $2 WHERE student_id = $1 AND revoked_at IS NULL")
+        .bind(student_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "UPDATE student_refresh_tokens SET revoked_at = $2 WHERE student_id = $1 AND revoked_at IS NULL"
+    )
+    .bind(student_id)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+    
+    tx.commit().await
+}
+
+/// 统计学生仍然有效的会话数
+pub async fn count_active_student_sessions(pool: &PgPool, student_id: &str) -> Result<i64, sqlx::Error> {
+    let result: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM student_sessions
+         WHERE student_id = $1 AND revoked_at IS NULL AND expires_at > $2"
+    )
+    .bind(student_id)
+    .bind(chrono::Utc::now().naive_utc())
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(result.0)
+}
+
+
+/// 将微信 openid 绑定到学号，该 openid 已绑定其他学号时先解除原绑定
+pub async fn bind_wechat_openid(pool: &PgPool, student_id: &str, openid: &str) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    
+    sqlx::query(
+        "UPDATE users SET wechat_openid = NULL, wechat_bound_at = NULL
+         WHERE wechat_openid = $1 AND student_id <> $2"
+    )
+    .bind(openid)
+    .bind(student_id)
+    .execute(&mut *tx)
+    .await?;
+    
+    let result = sqlx::query("UPDATE users SET wechat_openid = $1, wechat_bound_at = $2 WHERE student_id = $3")
+        .bind(openid)
+        .bind(chrono::Utc::now().naive_utc())
+        .bind(student_id)
+        .execute(&mut *tx)
+        .await?;
+    
+    tx.commit().await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// 解除学号的微信绑定，返回之前是否已绑定
+pub async fn unbind_wechat_openid(pool: &PgPool, student_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE users SET wechat_openid = NULL, wechat_bound_at = NULL
+         WHERE student_id = $1 AND wechat_openid IS NOT NULL"
+    )
+    .bind(student_id)
+    .execute(pool)
+    .await?;
+    
+    Ok(result.rows_affected() == 1)
+}
+
+/// 按微信 openid 查找绑定的学号
+pub async fn find_student_by_wechat_openid(pool: &PgPool, openid: &str) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT student_id FROM users WHERE wechat_openid = $1")
+        .bind(openid)
+        .fetch_optional(pool)
+        .await?;
+    
+    Ok(row.map(|(student_id,)| student_id))
+}
+
+/// 使管理员的所有会话失效：递增 token 版本并吊销所有 refresh token
+pub async fn revoke_admin_sessions(pool: &PgPool, admin_id: i32) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    
+    sqlx::query("UPDATE admins SET token_version = token_version + 1 WHERE id = $1")
+        .bind(admin_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "UPDATE admin_refresh_tokens SET revoked_at = $2 WHERE admin_id = $1 AND revoked_at IS NULL"
+    )
+    .bind(admin_id)
+    .bind(chrono::Utc::now().naive_utc())
+    .execute(&mut *tx)
+    .await?;
+    
+    tx.commit().await
+}
+
 /// 更新管理员密码
 pub async fn update_admin_password(pool: &PgPool, admin_id: i32, new_password_hash: &str) -> Result<(), sqlx::Error> {
     sqlx::query(
@@ -358,4 +1205,482 @@ pub async fn save_semester_config(pool: &PgPool, config: &SemesterConfig) -> Res
     .await?;
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// 保存下一学期配置，到开学日期由学期切换任务激活；已有的待切换学期被替换
+pub async fn schedule_semester_config(pool: &PgPool, config: &SemesterConfig) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    
+    sqlx::query("DELETE FROM semester_config WHERE scheduled")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO semester_config (semester_name, semester_start_date, is_active, scheduled) 
+         VALUES ($1, $2, false, true)"
+    )
+    .bind(&config.semester_name)
+    .bind(&config.semester_start_date)
+    .execute(&mut *tx)
+    .await?;
+    
+    tx.commit().await
+}
+
+/// 激活开学日期（YYYY-MM-DD）不晚于 today 的待切换学期，返回新的当前学期
+pub async fn activate_due_semester(pool: &PgPool, today: &str) -> Result<Option<SemesterConfig>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    
+    let due = sqlx::query(
+        "SELECT id, semester_name, semester_start_date 
+         FROM semester_config 
+         WHERE scheduled AND semester_start_date <= $1 
+         ORDER BY semester_start_date DESC, id DESC 
+         LIMIT 1 
+         FOR UPDATE"
+    )
+    .bind(today)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(row) = due else {
+        return Ok(None);
+    };
+    let id: i32 = row.get(0);
+    
+    sqlx::query("UPDATE semester_config SET is_active = false WHERE is_active")
+        .execute(&mut *tx)
+        .await?;
+    // 同时到期的更早的待切换学期不再激活
+    sqlx::query("UPDATE semester_config SET scheduled = false WHERE scheduled AND semester_start_date <= $1")
+        .bind(today)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE semester_config SET is_active = true, updated_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    
+    Ok(Some(SemesterConfig {
+        semester_name: row.get(1),
+        semester_start_date: row.get(2),
+    }))
+}
+
+/// 写入一条管理员审计日志
+pub async fn insert_audit_log(pool: &PgPool, entry: &NewAuditLog) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO admin_audit_log (admin_id, admin_username, action, target, before_data, after_data, ip)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)"
+    )
+    .bind(entry.admin_id)
+    .bind(&entry.admin_username)
+    .bind(&entry.action)
+    .bind(&entry.target)
+    .bind(&entry.before)
+    .bind(&entry.after)
+    .bind(&entry.ip)
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// 审计日志查询条件
+#[derive(Debug, Default)]
+pub struct AuditLogFilter {
+    pub admin_id: Option<i32>,
+    pub action: Option<String>,
+    pub target: Option<String>,
+    pub from: Option<chrono::NaiveDateTime>,
+    pub to: Option<chrono::NaiveDateTime>,
+}
+
+fn push_audit_filter(builder: &mut QueryBuilder<'_, Postgres>, filter: &AuditLogFilter) {
+    builder.push(" WHERE 1 = 1");
+    if let Some(admin_id) = filter.admin_id {
+        builder.push(" AND admin_id = ").push_bind(admin_id);
+    }
+    if let Some(action) = &filter.action {
+        // 支持按前缀过滤，如 "admin." 匹配所有管理员账户操作
+        builder.push(" AND action LIKE ").push_bind(format!("{}%", action));
+    }
+    if let Some(target) = &filter.target {
+        builder.push(" AND target = ").push_bind(target.clone());
+    }
+    if let Some(from) = filter.from {
+        builder.push(" AND created_at >= ").push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        builder.push(" AND created_at < ").push_bind(to);
+    }
+}
+
+/// 分页查询审计日志，返回当前页数据和总条数
+pub async fn query_audit_logs(
+    pool: &PgPool,
+    filter: &AuditLogFilter,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<AuditLogEntry>, i64), sqlx::Error> {
+    let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM admin_audit_log");
+    push_audit_filter(&mut count_query, filter);
+    let (total,): (i64,) = count_query.build_query_as().fetch_one(pool).await?;
+    
+    let mut query = QueryBuilder::new(
+        "SELECT id, admin_id, admin_username, action, target, before_data, after_data, ip, created_at FROM admin_audit_log"
+    );
+    push_audit_filter(&mut query, filter);
+    query
+        .push(" ORDER BY id DESC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+    
+    let rows = query.build().fetch_all(pool).await?;
+    let entries = rows
+        .into_iter()
+        .map(|row| AuditLogEntry {
+            id: row.get(0),
+            admin_id: row.get(1),
+            admin_username: row.get(2),
+            action: row.get(3),
+            target: row.get(4),
+            before: row.get(5),
+            after: row.get(6),
+            ip: row.get(7),
+            created_at: row.get(8),
+        })
+        .collect();
+    
+    Ok((entries, total))
+}
+const SCHEDULED_JOB_COLUMNS: &str = "name, next_run_at, locked_by, locked_until, run_requested_at, \
+    last_started_at, last_finished_at, last_status, last_message, last_duration_ms";
+
+fn scheduled_job_from_row(row: &sqlx::postgres::PgRow) -> ScheduledJobState {
+    ScheduledJobState {
+        name: row.get(0),
+        next_run_at: row.get(1),
+        locked_by: row.get(2),
+        locked_until: row.get(3),
+        run_requested_at: row.get(4),
+        last_started_at: row.get(5),
+        last_finished_at: row.get(6),
+        last_status: row.get(7),
+        last_message: row.get(8),
+        last_duration_ms: row.get(9),
+    }
+}
+
+/// 登记定时任务；已存在时只在计划变得更早时更新下次执行时间，next_run_at 为空表示只能手动触发
+pub async fn register_scheduled_job(pool: &PgPool, name: &str, next_run_at: Option<chrono::NaiveDateTime>) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO scheduled_jobs (name, next_run_at) VALUES ($1, $2)
+         ON CONFLICT (name) DO UPDATE SET next_run_at = CASE
+             WHEN EXCLUDED.next_run_at IS NULL THEN NULL
+             ELSE LEAST(COALESCE(scheduled_jobs.next_run_at, EXCLUDED.next_run_at), EXCLUDED.next_run_at)
+         END"
+    )
+    .bind(name)
+    .bind(next_run_at)
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// 列出所有定时任务的状态
+pub async fn list_scheduled_jobs(pool: &PgPool) -> Result<Vec<ScheduledJobState>, sqlx::Error> {
+    let rows = sqlx::query(&format!("SELECT {} FROM scheduled_jobs ORDER BY name", SCHEDULED_JOB_COLUMNS))
+        .fetch_all(pool)
+        .await?;
+    
+    Ok(rows.iter().map(scheduled_job_from_row).collect())
+}
+
+/// 尝试获取到期（或被手动触发）任务的租约，成功时返回 true
+///
+/// 判断和加锁在同一条 UPDATE 中完成，多个实例同时尝试时只有一个会成功。
+pub async fn claim_scheduled_job(pool: &PgPool, name: &str, instance: &str, lease_until: chrono::NaiveDateTime) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().naive_utc();
+    let result = sqlx::query(
+        "UPDATE scheduled_jobs
+         SET locked_by = $2, locked_until = $3, run_requested_at = NULL,
+             last_started_at = $4, last_status = 'running', last_message = NULL
+         WHERE name = $1
+           AND (locked_until IS NULL OR locked_until < $4)
+           AND (next_run_at <= $4 OR run_requested_at IS NOT NULL)"
+    )
+    .bind(name)
+    .bind(instance)
+    .bind(lease_until)
+    .bind(now)
+    .execute(pool)
+    .await?;
+    
+    Ok(result.rows_affected() == 1)
+}
+
+/// 延长任务租约，租约已被其他实例接管时返回 false
+pub async fn renew_scheduled_job(pool: &PgPool, name: &str, instance: &str, lease_until: chrono::NaiveDateTime) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE scheduled_jobs SET locked_until = $3 WHERE name = $1 AND locked_by = $2")
+        .bind(name)
+        .bind(instance)
+        .bind(lease_until)
+        .execute(pool)
+        .await?;
+    
+    Ok(result.rows_affected() == 1)
+}
+
+/// 记录任务执行结果并释放租约
+pub async fn finish_scheduled_job(
+    pool: &PgPool,
+    name: &str,
+    instance: &str,
+    status: &str,
+    message: &str,
+    duration_ms: i64,
+    next_run_at: Option<chrono::NaiveDateTime>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE scheduled_jobs
+         SET locked_by = NULL, locked_until = NULL, last_finished_at = $7,
+             last_status = $3, last_message = $4, last_duration_ms = $5, next_run_at = $6
+         WHERE name = $1 AND locked_by = $2"
+    )
+    .bind(name)
+    .bind(instance)
+    .bind(status)
+    .bind(message)
+    .bind(duration_ms)
+    .bind(next_run_at)
+    .bind(chrono::Utc::now().naive_utc())
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// 请求尽快执行一次任务，任务不存在时返回 false
+pub async fn request_scheduled_job_run(pool: &PgPool, name: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE scheduled_jobs SET run_requested_at = $2 WHERE name = $1")
+        .bind(name)
+        .bind(chrono::Utc::now().naive_utc())
+        .execute(pool)
+        .await?;
+    
+    Ok(result.rows_affected() == 1)
+}
+
+/// 推送事件使用的 LISTEN/NOTIFY 通道，通知内容为事件ID
+pub const STUDENT_EVENTS_CHANNEL: &str = "student_events";
+
+const STUDENT_EVENT_COLUMNS: &str = "id, student_id, event_type, payload, created_at";
+
+fn student_event_from_row(row: &sqlx::postgres::PgRow) -> StudentEventRecord {
+    StudentEventRecord {
+        id: row.get(0),
+        student_id: row.get(1),
+        event_type: row.get(2),
+        payload: row.get(3),
+        created_at: row.get(4),
+    }
+}
+
+/// 保存推送事件并通知所有实例，student_id 为空表示发送给所有学生，返回事件ID
+pub async fn insert_student_event(
+    pool: &PgPool,
+    student_id: Option<&str>,
+    event_type: &str,
+    payload: &serde_json::Value,
+) -> Result<i64, sqlx::Error> {
+    let (id,): (i64,) = sqlx::query_as(
+        "WITH inserted AS (
+             INSERT INTO student_events (student_id, event_type, payload, created_at)
+             VALUES ($1, $2, $3, $4)
+             RETURNING id
+         )
+         SELECT id FROM inserted, pg_notify($5, id::text)"
+    )
+    .bind(student_id)
+    .bind(event_type)
+    .bind(payload)
+    .bind(chrono::Utc::now().naive_utc())
+    .bind(STUDENT_EVENTS_CHANNEL)
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(id)
+}
+
+/// 按ID获取推送事件
+pub async fn get_student_event(pool: &PgPool, id: i64) -> Result<Option<StudentEventRecord>, sqlx::Error> {
+    let row = sqlx::query(&format!("SELECT {} FROM student_events WHERE id = $1", STUDENT_EVENT_COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    
+    Ok(row.as_ref().map(student_event_from_row))
+}
+
+/// 获取发给该学生（含全体学生）的、ID 大于 after_id 且创建于 since 之后的事件
+///
+/// 超过 limit 条时只返回最近的 limit 条，结果按ID升序。
+pub async fn list_student_events_after(
+    pool: &PgPool,
+    student_id: &str,
+    after_id: i64,
+    since: chrono::NaiveDateTime,
+    limit: i64,
+) -> Result<Vec<StudentEventRecord>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT * FROM (
+             SELECT {} FROM student_events
+             WHERE id > $1 AND (student_id = $2 OR student_id IS NULL) AND created_at >= $3
+             ORDER BY id DESC
+             LIMIT $4
+         ) recent
+         ORDER BY id",
+        STUDENT_EVENT_COLUMNS
+    ))
+    .bind(after_id)
+    .bind(student_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(rows.iter().map(student_event_from_row).collect())
+}
+
+/// 当前最新的事件ID，没有事件时为 0
+pub async fn latest_student_event_id(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let (id,): (Option<i64>,) = sqlx::query_as("SELECT MAX(id) FROM student_events")
+        .fetch_one(pool)
+        .await?;
+    
+    Ok(id.unwrap_or(0))
+}
+
+/// 删除创建于 before 之前的推送事件
+pub async fn delete_student_events_before(pool: &PgPool, before: chrono::NaiveDateTime) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM student_events WHERE created_at < $1")
+        .bind(before)
+        .execute(pool)
+        .await?;
+    
+    Ok(result.rows_affected())
+}
+
+const WECHAT_SUBSCRIPTION_COLUMNS: &str = "template, remaining, updated_at, last_sent_at";
+
+fn wechat_subscription_from_row(row: &sqlx::postgres::PgRow) -> WechatSubscription {
+    WechatSubscription {
+        template: row.get(0),
+        remaining: row.get(1),
+        updated_at: row.get(2),
+        last_sent_at: row.get(3),
+    }
+}
+
+/// 记录学生对订阅消息的授权，accepted 为 true 时增加一次可发送次数，否则清零
+///
+/// openid 总是更新为最近一次授权的用户。
+pub async fn record_wechat_subscription(
+    pool: &PgPool,
+    student_id: &str,
+    template: &str,
+    openid: &str,
+    accepted: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO wechat_subscriptions (student_id, template, openid, remaining, updated_at)
+         VALUES ($1, $2, $3, CASE WHEN $4 THEN 1 ELSE 0 END, $5)
+         ON CONFLICT (student_id, template) DO UPDATE SET
+             openid = EXCLUDED.openid,
+             remaining = CASE WHEN $4 THEN wechat_subscriptions.remaining + 1 ELSE 0 END,
+             updated_at = EXCLUDED.updated_at"
+    )
+    .bind(student_id)
+    .bind(template)
+    .bind(openid)
+    .bind(accepted)
+    .bind(chrono::Utc::now().naive_utc())
+    .execute(pool)
+    .await?;
+    
+    Ok(())
+}
+
+/// 获取学生的订阅消息授权
+pub async fn list_wechat_subscriptions(pool: &PgPool, student_id: &str) -> Result<Vec<WechatSubscription>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM wechat_subscriptions WHERE student_id = $1 ORDER BY template",
+        WECHAT_SUBSCRIPTION_COLUMNS
+    ))
+    .bind(student_id)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(rows.iter().map(wechat_subscription_from_row).collect())
+}
+
+/// 获取还有可发送次数且未被禁用的学生，student_id 为空时返回该模板的所有授权
+pub async fn list_wechat_subscribers(
+    pool: &PgPool,
+    template: &str,
+    student_id: Option<&str>,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT student_id FROM wechat_subscriptions
+         WHERE template = $1 AND remaining > 0 AND ($2::TEXT IS NULL OR student_id = $2)
+           AND student_id NOT IN (SELECT student_id FROM disabled_students)
+         ORDER BY student_id"
+    )
+    .bind(template)
+    .bind(student_id)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(rows.into_iter().map(|(student_id,)| student_id).collect())
+}
+
+/// 扣减一次可发送次数，返回发送目标 openid；没有剩余次数时返回 None
+pub async fn take_wechat_subscription(pool: &PgPool, student_id: &str, template: &str) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "UPDATE wechat_subscriptions SET remaining = remaining - 1, last_sent_at = $3
+         WHERE student_id = $1 AND template = $2 AND remaining > 0
+         RETURNING openid"
+    )
+    .bind(student_id)
+    .bind(template)
+    .bind(chrono::Utc::now().naive_utc())
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(row.map(|(openid,)| openid))
+}
+
+/// 发送失败（消息未送达）时退回扣减的次数
+pub async fn refund_wechat_subscription(pool: &PgPool, student_id: &str, template: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE wechat_subscriptions SET remaining = remaining + 1 WHERE student_id = $1 AND template = $2")
+        .bind(student_id)
+        .bind(template)
+        .execute(pool)
+        .await?;
+    
+    Ok(())
+}
+
+/// 用户已拒绝或取消订阅时清零可发送次数
+pub async fn clear_wechat_subscription(pool: &PgPool, student_id: &str, template: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE wechat_subscriptions SET remaining = 0, updated_at = $3 WHERE student_id = $1 AND template = $2")
+        .bind(student_id)
+        .bind(template)
+        .bind(chrono::Utc::now().naive_utc())
+        .execute(pool)
+        .await?;
+    
+    Ok(())
+}
@@ -1,25 +1,24 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-/// 完整的用户信息（包含token等额外字段）
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// 学生资料（access token 只在登录响应中返回，不保存）
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
 pub struct UserLoginInfo {
     #[serde(rename = "studentId")]
     pub student_id: String,
     pub name: String,
     pub class: String,
-    pub token: String,
     #[serde(rename = "avatarUrl")]
     pub avatar_url: Option<String>, // 头像URL
 }
 
 impl UserLoginInfo {
     /// 创建新的用户登录信息
-    pub fn new(student_id: String, name: String, class: String, token: String) -> Self {
+    pub fn new(student_id: String, name: String, class: String) -> Self {
         Self {
             student_id,
             name,
             class,
-            token,
             avatar_url: None,
         }
     }
@@ -30,6 +29,156 @@ impl UserLoginInfo {
     }
 }
 
+/// 管理后台中展示的学生信息（不包含 token）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StudentSummary {
+    pub student_id: String,
+    pub name: String,
+    pub class: Option<String>,
+    pub avatar_url: Option<String>,
+    pub created_at: Option<chrono::NaiveDateTime>,
+    pub last_active_at: Option<chrono::NaiveDateTime>, // 所有会话中最近一次使用的时间
+    pub disabled: bool,
+}
+
+/// 一次学生登录尝试
+#[derive(Debug, Clone)]
+pub struct LoginAttempt {
+    pub student_id: String,
+    pub success: bool,
+    pub duration_ms: i32,
+    pub client: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+/// 登录设备信息，创建会话时记录
+#[derive(Debug, Clone)]
+pub struct SessionDevice {
+    pub device_name: Option<String>,
+    pub client: String,
+    pub ip: Option<String>,
+}
+
+/// 学生的一个登录会话（一台设备）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StudentSessionRecord {
+    pub id: String,
+    pub device_name: Option<String>,
+    pub client: String,
+    pub ip: Option<String>,
+    pub created_at: Option<chrono::NaiveDateTime>,
+    pub last_seen_at: Option<chrono::NaiveDateTime>,
+}
+
+/// 登录趋势，period 为 timezone 时区的时间
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LoginTrend {
+    pub timezone: String,
+    pub periods: Vec<LoginPeriodStats>,
+}
+
+/// 某一时间段内的登录统计
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LoginPeriodStats {
+    pub period: chrono::NaiveDateTime, // 时间段起点（统计时区的本地时间）
+    pub active_users: i64,
+    pub attempts: i64,
+    pub successes: i64,
+    pub success_rate: f64,
+    pub avg_duration_ms: Option<f64>,
+}
+
+/// 各小时的登录次数，hour 为 timezone 时区的小时
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PeakHours {
+    pub timezone: String,
+    pub hours: Vec<HourlyLogins>,
+}
+
+/// 某一小时的登录次数
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HourlyLogins {
+    pub hour: i32, // 0-23，统计时区的小时
+    pub logins: i64,
+}
+
+/// 管理员账户信息
+#[derive(Debug, Clone)]
+pub struct AdminAccount {
+    pub id: i32,
+    pub username: String,
+    pub password_hash: String,
+    pub role: String,
+    pub is_active: bool,
+    pub token_version: i32,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+/// 待写入的管理员审计日志
+#[derive(Debug, Clone)]
+pub struct NewAuditLog {
+    pub admin_id: Option<i32>,
+    pub admin_username: Option<String>,
+    pub action: String,
+    pub target: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub ip: Option<String>,
+}
+
+/// 管理员审计日志
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub admin_id: Option<i32>,
+    pub admin_username: Option<String>,
+    pub action: String,
+    pub target: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub ip: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+/// 定时任务的持久化状态（scheduled_jobs 表）
+#[derive(Debug, Clone)]
+pub struct ScheduledJobState {
+    pub name: String,
+    pub next_run_at: Option<chrono::NaiveDateTime>,
+    pub locked_by: Option<String>,
+    pub locked_until: Option<chrono::NaiveDateTime>,
+    pub run_requested_at: Option<chrono::NaiveDateTime>,
+    pub last_started_at: Option<chrono::NaiveDateTime>,
+    pub last_finished_at: Option<chrono::NaiveDateTime>,
+    pub last_status: Option<String>,
+    pub last_message: Option<String>,
+    pub last_duration_ms: Option<i64>,
+}
+
+/// 推送事件记录（student_events 表）
+#[derive(Debug, Clone)]
+pub struct StudentEventRecord {
+    pub id: i64,
+    pub student_id: Option<String>,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+/// 微信订阅消息授权（wechat_subscriptions 表）
+#[derive(Debug, Clone)]
+pub struct WechatSubscription {
+    pub template: String,
+    pub remaining: i32,
+    pub updated_at: chrono::NaiveDateTime,
+    pub last_sent_at: Option<chrono::NaiveDateTime>,
+}
+
 /// 太原科技大学课表响应结构
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TyustCourseResponse {
@@ -0,0 +1,345 @@
+use axum::{
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        Request,
+    },
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::api_types::ApiResponse;
+
+tokio::task_local! {
+    /// 当前请求的语言，由 `locale_middleware` 根据 Accept-Language 设置
+    static LOCALE: Locale;
+}
+
+/// 错误信息的语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    ZhCn,
+    En,
+}
+
+impl Locale {
+    /// 按 Accept-Language 的权重选择支持的语言，都不支持时使用中文
+    pub fn from_accept_language(value: &str) -> Self {
+        value
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let tag = parts.next()?.trim().to_ascii_lowercase();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                let locale = if tag == "zh" || tag.starts_with("zh-") {
+                    Locale::ZhCn
+                } else if tag == "en" || tag.starts_with("en-") {
+                    Locale::En
+                } else {
+                    return None;
+                };
+                (quality > 0.0).then_some((locale, quality))
+            })
+            // 权重相同时保留先出现的语言
+            .fold(None, |best: Option<(Locale, f32)>, (locale, quality)| match best {
+                Some((_, best_quality)) if best_quality >= quality => best,
+                _ => Some((locale, quality)),
+            })
+            .map(|(locale, _)| locale)
+            .unwrap_or_default()
+    }
+
+    /// 当前请求的语言，不在请求上下文中时使用默认语言
+    pub fn current() -> Self {
+        LOCALE.try_with(|locale| *locale).unwrap_or_default()
+    }
+}
+
+/// 在请求处理期间记录客户端语言，供错误响应选择翻译
+pub async fn locale_middleware(request: Request, next: Next) -> Response {
+    let locale = request
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(Locale::from_accept_language)
+        .unwrap_or_default();
+    LOCALE.scope(locale, next.run(request)).await
+}
+
+/// 接口错误目录
+///
+/// 每个错误有稳定的错误码（响应中的 `error` 字段）、HTTP 状态码和中英文说明，
+/// 客户端应根据错误码而不是说明文字判断错误类型。具体原因（上游或数据库错误）只写入日志。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiError {
+    InvalidRequest,
+    RequestTooLarge,
+    MissingToken,
+    InvalidToken,
+    TokenMismatch,
+    SessionRevoked,
+    LoginRequired,
+    InvalidRefreshToken,
+    LoginFailed,
+    InvalidCredentials,
+    WrongPassword,
+    AccountDisabled,
+    AdminDisabled,
+    PermissionDenied,
+    InvalidAdminRole,
+    SessionNotFound,
+    AdminNotFound,
+    JobNotFound,
+    SemesterNotConfigured,
+    UsernameRequired,
+    PasswordTooShort,
+    CannotModifySelf,
+    InvalidTimeRange,
+    UsernameTaken,
+    LastSuperAdmin,
+    AvatarMissing,
+    AvatarInvalid,
+    AvatarTooLarge,
+    AvatarUnsupportedFormat,
+    TooManyRequests,
+    UpstreamUnavailable,
+    WechatCodeInvalid,
+    WechatUnavailable,
+    WechatDisabled,
+    WechatNotBound,
+    TokenIssueFailed,
+    StorageError,
+    DatabaseError,
+    InternalError,
+}
+
+impl ApiError {
+    /// 稳定的错误码
+    pub fn code(self) -> &'static str {
+        match self {
+            ApiError::InvalidRequest => "invalid_request",
+            ApiError::RequestTooLarge => "request_too_large",
+            ApiError::MissingToken => "missing_token",
+            ApiError::InvalidToken => "invalid_token",
+            ApiError::TokenMismatch => "token_mismatch",
+            ApiError::SessionRevoked => "session_revoked",
+            ApiError::LoginRequired => "login_required",
+            ApiError::InvalidRefreshToken => "invalid_refresh_token",
+            ApiError::LoginFailed => "login_failed",
+            ApiError::InvalidCredentials => "invalid_credentials",
+            ApiError::WrongPassword => "wrong_password",
+            ApiError::AccountDisabled => "account_disabled",
+            ApiError::AdminDisabled => "admin_disabled",
+            ApiError::PermissionDenied => "permission_denied",
+            ApiError::InvalidAdminRole => "invalid_admin_role",
+            ApiError::SessionNotFound => "session_not_found",
+            ApiError::AdminNotFound => "admin_not_found",
+            ApiError::JobNotFound => "job_not_found",
+            ApiError::SemesterNotConfigured => "semester_not_configured",
+            ApiError::UsernameRequired => "username_required",
+            ApiError::PasswordTooShort => "password_too_short",
+            ApiError::CannotModifySelf => "cannot_modify_self",
+            ApiError::InvalidTimeRange => "invalid_time_range",
+            ApiError::UsernameTaken => "username_taken",
+            ApiError::LastSuperAdmin => "last_super_admin",
+            ApiError::AvatarMissing => "avatar_missing",
+            ApiError::AvatarInvalid => "avatar_invalid",
+            ApiError::AvatarTooLarge => "avatar_too_large",
+            ApiError::AvatarUnsupportedFormat => "avatar_unsupported_format",
+            ApiError::TooManyRequests => "too_many_requests",
+            ApiError::UpstreamUnavailable => "upstream_unavailable",
+            ApiError::WechatCodeInvalid => "wechat_code_invalid",
+            ApiError::WechatUnavailable => "wechat_unavailable",
+            ApiError::WechatDisabled => "wechat_disabled",
+            ApiError::WechatNotBound => "wechat_not_bound",
+            ApiError::TokenIssueFailed => "token_issue_failed",
+            ApiError::StorageError => "storage_error",
+            ApiError::DatabaseError => "database_error",
+            ApiError::InternalError => "internal_error",
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            ApiError::InvalidRequest
+            | ApiError::UsernameRequired
+            | ApiError::PasswordTooShort
+            | ApiError::CannotModifySelf
+            | ApiError::InvalidTimeRange
+            | ApiError::AvatarMissing
+            | ApiError::AvatarInvalid
+            | ApiError::WechatCodeInvalid => StatusCode::BAD_REQUEST,
+            ApiError::MissingToken
+            | ApiError::InvalidToken
+            | ApiError::SessionRevoked
+            | ApiError::LoginRequired
+            | ApiError::InvalidRefreshToken
+            | ApiError::LoginFailed
+            | ApiError::InvalidCredentials
+            | ApiError::WrongPassword => StatusCode::UNAUTHORIZED,
+            ApiError::TokenMismatch
+            | ApiError::AccountDisabled
+            | ApiError::AdminDisabled
+            | ApiError::PermissionDenied
+            | ApiError::InvalidAdminRole => StatusCode::FORBIDDEN,
+            ApiError::SessionNotFound
+            | ApiError::AdminNotFound
+            | ApiError::JobNotFound
+            | ApiError::SemesterNotConfigured
+            | ApiError::WechatNotBound => StatusCode::NOT_FOUND,
+            ApiError::UsernameTaken | ApiError::LastSuperAdmin => StatusCode::CONFLICT,
+            ApiError::RequestTooLarge | ApiError::AvatarTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::AvatarUnsupportedFormat => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::WechatDisabled => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::UpstreamUnavailable
+            | ApiError::WechatUnavailable
+            | ApiError::TokenIssueFailed
+            | ApiError::StorageError
+            | ApiError::DatabaseError
+            | ApiError::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// 指定语言的错误说明
+    pub fn message(self, locale: Locale) -> &'static str {
+        let (zh_cn, en) = match self {
+            ApiError::InvalidRequest => ("请求参数错误", "Invalid request"),
+            ApiError::RequestTooLarge => ("请求内容过大", "Request body is too large"),
+            ApiError::MissingToken => ("缺少认证凭证", "Missing authorization token"),
+            ApiError::InvalidToken => ("认证凭证无效或已过期", "Invalid or expired token"),
+            ApiError::TokenMismatch => ("认证凭证与学号不匹配", "Token does not match the student ID"),
+            ApiError::SessionRevoked => ("该设备已退出登录", "Session has been revoked"),
+            ApiError::LoginRequired => ("登录已失效，请重新登录", "Your session has expired, please log in again"),
+            ApiError::InvalidRefreshToken => ("登录已失效，请重新登录", "Invalid refresh token, please log in again"),
+            ApiError::LoginFailed => ("登录失败，请检查学号和密码", "Login failed, please check your student ID and password"),
+            ApiError::InvalidCredentials => ("用户名或密码错误", "Incorrect username or password"),
+            ApiError::WrongPassword => ("密码错误", "Incorrect password"),
+            ApiError::AccountDisabled => ("账号已被禁用", "Account has been disabled"),
+            ApiError::AdminDisabled => ("管理员账户已被禁用", "Admin account has been disabled"),
+            ApiError::PermissionDenied => ("权限不足", "Permission denied"),
+            ApiError::InvalidAdminRole => ("管理员角色无效", "Invalid admin role"),
+            ApiError::SessionNotFound => ("登录设备不存在", "Session not found"),
+            ApiError::AdminNotFound => ("管理员不存在", "Admin not found"),
+            ApiError::JobNotFound => ("定时任务不存在", "Scheduled job not found"),
+            ApiError::SemesterNotConfigured => ("学期配置未设置", "Semester configuration has not been set"),
+            ApiError::UsernameRequired => ("用户名不能为空", "Username must not be empty"),
+            ApiError::PasswordTooShort => ("密码长度至少为6位", "Password must be at least 6 characters"),
+            ApiError::CannotModifySelf => ("不能修改自己的角色或状态", "You cannot change your own role or status"),
+            ApiError::InvalidTimeRange => ("起始时间必须早于结束时间", "Start time must be earlier than end time"),
+            ApiError::UsernameTaken => ("用户名已存在", "Username already exists"),
+            ApiError::LastSuperAdmin => ("至少需要保留一个启用的超级管理员", "At least one active super admin is required"),
+            ApiError::AvatarMissing => ("缺少头像文件", "Missing avatar file"),
+            ApiError::AvatarInvalid => ("头像图片无效", "Invalid avatar image"),
+            ApiError::AvatarTooLarge => ("头像图片过大", "Avatar image is too large"),
+            ApiError::AvatarUnsupportedFormat => (
+                "头像只支持 JPEG、PNG、WebP 或 GIF 格式",
+                "Avatar must be a JPEG, PNG, WebP or GIF image",
+            ),
+            ApiError::TooManyRequests => ("请求过于频繁，请稍后再试", "Too many requests, please try again later"),
+            ApiError::UpstreamUnavailable => (
+                "教务系统暂时无法访问，请稍后再试",
+                "The academic system is temporarily unavailable, please try again later",
+            ),
+            ApiError::WechatCodeInvalid => ("微信登录凭证无效或已过期", "Invalid or expired WeChat login code"),
+            ApiError::WechatUnavailable => (
+                "微信服务暂时无法访问，请稍后再试",
+                "WeChat service is temporarily unavailable, please try again later",
+            ),
+            ApiError::WechatDisabled => ("未启用微信功能", "WeChat integration is not enabled"),
+            ApiError::WechatNotBound => (
+                "该微信尚未绑定学号，请使用学号和密码登录",
+                "This WeChat account is not linked to a student, please log in with your student ID and password",
+            ),
+            ApiError::TokenIssueFailed => ("生成登录凭证失败", "Failed to generate token"),
+            ApiError::StorageError => ("文件保存失败", "Failed to save file"),
+            ApiError::DatabaseError => ("数据库操作失败", "Database operation failed"),
+            ApiError::InternalError => ("服务器内部错误", "Internal server error"),
+        };
+        match locale {
+            Locale::ZhCn => zh_cn,
+            Locale::En => en,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ApiResponse::<()> {
+            code: self.status().as_u16() as i32,
+            message: self.message(Locale::current()).to_string(),
+            data: None,
+            error: Some(self.code()),
+        };
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        tracing::debug!(error = %rejection.body_text(), "Rejected JSON body");
+        if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            ApiError::RequestTooLarge
+        } else {
+            ApiError::InvalidRequest
+        }
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        tracing::debug!(error = %rejection.body_text(), "Rejected query string");
+        ApiError::InvalidRequest
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        tracing::debug!(error = %rejection.body_text(), "Rejected path parameters");
+        ApiError::InvalidRequest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(value: &str) -> Locale {
+        Locale::from_accept_language(value)
+    }
+
+    #[test]
+    fn picks_supported_language_with_highest_weight() {
+        assert_eq!(locale("en"), Locale::En);
+        assert_eq!(locale("EN-gb"), Locale::En);
+        assert_eq!(locale("en-US,zh;q=0.9"), Locale::En);
+        assert_eq!(locale("zh-CN;q=0.5, en;q=0.8"), Locale::En);
+        assert_eq!(locale("en;q=0.3,zh-TW"), Locale::ZhCn);
+    }
+
+    #[test]
+    fn keeps_first_language_on_equal_weight() {
+        assert_eq!(locale("en;q=0.5,zh;q=0.5"), Locale::En);
+        assert_eq!(locale("zh,en"), Locale::ZhCn);
+    }
+
+    #[test]
+    fn ignores_rejected_unknown_and_malformed_entries() {
+        assert_eq!(locale("en;q=0"), Locale::ZhCn);
+        assert_eq!(locale("en;q=0,zh;q=0.1"), Locale::ZhCn);
+        assert_eq!(locale("fr,de;q=0.9,en;q=0.1"), Locale::En);
+        assert_eq!(locale("en;q=abc,zh;q=0.2"), Locale::ZhCn);
+        assert_eq!(locale("english"), Locale::ZhCn);
+    }
+
+    #[test]
+    fn falls_back_to_chinese() {
+        assert_eq!(locale(""), Locale::ZhCn);
+        assert_eq!(locale("*"), Locale::ZhCn);
+        assert_eq!(locale("fr-FR"), Locale::ZhCn);
+    }
+}
@@ -0,0 +1,344 @@
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Extension,
+    },
+    http::HeaderMap,
+    response::{
+        sse::{Event, Sse},
+        Response,
+    },
+};
+use futures::{channel::mpsc, SinkExt, Stream, StreamExt};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    api_types::{EmptyResponse, Score},
+    auth::{self, SessionState, StudentSession},
+    config, db,
+    entity::StudentEventRecord,
+    extract::ApiQuery,
+    health, notifications,
+};
+
+/// 本实例内广播通道的容量，连接落后超过该数量时改为从数据库补发
+const BUS_CAPACITY: usize = 1024;
+
+/// 每个连接待发送消息的缓冲数
+const FEED_BUFFER: usize = 32;
+
+/// 监听连接出错后重连的等待时间
+const LISTENER_RETRY: Duration = Duration::from_secs(5);
+
+/// 本实例内的事件广播，由 LISTEN 任务写入，每个推送连接订阅后按学号过滤
+static BUS: Lazy<broadcast::Sender<Arc<StoredEvent>>> = Lazy::new(|| broadcast::channel(BUS_CAPACITY).0);
+
+/// 推送给学生的事件
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum StudentEvent {
+    /// 有新出或变化的成绩
+    NewScores { scores: Vec<Score> },
+    /// 管理员发布的公告
+    Announcement { title: String, content: String },
+    /// 切换了学期，课表需要重新获取
+    ScheduleChanged {
+        semester_name: String,
+        semester_start_date: String,
+    },
+}
+
+impl StudentEvent {
+    /// 事件类型，即 SSE 的 event 字段
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            StudentEvent::NewScores { .. } => "new_scores",
+            StudentEvent::Announcement { .. } => "announcement",
+            StudentEvent::ScheduleChanged { .. } => "schedule_changed",
+        }
+    }
+}
+
+/// 已保存的事件
+///
+/// SSE 中 id、event、data 分别对应事件ID、type 和 data；WebSocket 中整个结构作为一条 JSON 文本消息发送。
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StoredEvent {
+    pub id: i64,
+    #[serde(skip)]
+    student_id: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    #[serde(flatten)]
+    pub event: StudentEvent,
+}
+
+impl StoredEvent {
+    fn from_record(record: StudentEventRecord) -> Option<Self> {
+        let value = serde_json::json!({ "type": record.event_type, "data": record.payload });
+        match serde_json::from_value(value) {
+            Ok(event) => Some(Self {
+                id: record.id,
+                student_id: record.student_id,
+                created_at: record.created_at,
+                event,
+            }),
+            Err(e) => {
+                warn!(id = record.id, event_type = %record.event_type, error = %e, "Skipping undecodable event");
+                None
+            }
+        }
+    }
+
+    fn is_for(&self, student_id: &str) -> bool {
+        self.student_id.as_deref().is_none_or(|target| target == student_id)
+    }
+
+    /// 事件数据的 JSON 文本
+    fn data_json(&self) -> String {
+        serde_json::to_value(&self.event)
+            .ok()
+            .and_then(|mut value| value.get_mut("data").map(serde_json::Value::take))
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+/// 向一个学生发布事件
+pub async fn publish_to(student_id: &str, event: &StudentEvent) -> Result<i64> {
+    publish(Some(student_id), event).await
+}
+
+/// 向所有学生发布事件
+pub async fn broadcast(event: &StudentEvent) -> Result<i64> {
+    publish(None, event).await
+}
+
+/// 保存事件并通过 NOTIFY 通知所有实例（包括本实例），由各实例推送给在线的学生
+async fn publish(student_id: Option<&str>, event: &StudentEvent) -> Result<i64> {
+    let mut value = serde_json::to_value(event)?;
+    let payload = value.get_mut("data").map(serde_json::Value::take).unwrap_or_default();
+    let db_pool = db::get_db_pool().await;
+    let id = db::insert_student_event(db_pool, student_id, event.event_type(), &payload).await?;
+    debug!(id, event_type = event.event_type(), student_id = ?student_id, "Published student event");
+    notifications::notify(student_id, event);
+    Ok(id)
+}
+
+/// 启动 LISTEN 任务，把其他实例（和本实例）发布的事件转发给本实例的推送连接
+pub fn spawn_listener() {
+    tokio::spawn(async {
+        loop {
+            if let Err(e) = listen().await {
+                warn!(error = %e, "Student event listener failed, reconnecting");
+            }
+            tokio::time::sleep(LISTENER_RETRY).await;
+        }
+    });
+}
+
+async fn listen() -> Result<()> {
+    let db_pool = db::get_db_pool().await;
+    let mut listener = PgListener::connect_with(db_pool).await?;
+    listener.listen(db::STUDENT_EVENTS_CHANNEL).await?;
+    info!("Listening for student events");
+
+    loop {
+        let notification = listener.recv().await?;
+        let Ok(id) = notification.payload().parse::<i64>() else {
+            continue;
+        };
+        // 本实例没有推送连接时不必读取事件
+        if BUS.receiver_count() == 0 {
+            continue;
+        }
+        if let Some(event) = db::get_student_event(db_pool, id).await?.and_then(StoredEvent::from_record) {
+            let _ = BUS.send(Arc::new(event));
+        }
+    }
+}
+
+/// 推送连接要发送的内容
+enum FeedItem {
+    Event(Arc<StoredEvent>),
+    Heartbeat,
+}
+
+/// 为一个连接启动推送任务，连接断开（接收端被丢弃）后任务随之结束
+fn open_feed(session: StudentSession, last_event_id: Option<i64>) -> mpsc::Receiver<FeedItem> {
+    let (mut sender, receiver) = mpsc::channel(FEED_BUFFER);
+    tokio::spawn(async move {
+        if let Err(e) = feed(&session, last_event_id, &mut sender).await {
+            debug!(student_id = %session.student_id, error = %e, "Event feed closed");
+        }
+    });
+    receiver
+}
+
+/// 补发断线期间的事件后持续推送新事件，连接断开、会话失效或服务停机时返回
+async fn feed(session: &StudentSession, last_event_id: Option<i64>, sender: &mut mpsc::Sender<FeedItem>) -> Result<()> {
+    let db_pool = db::get_db_pool().await;
+    // 先订阅再读取数据库，避免遗漏补发期间发布的事件
+    let mut bus = BUS.subscribe();
+    let mut delivered = match last_event_id {
+        Some(last_event_id) => replay(session, last_event_id, sender).await?,
+        None => db::latest_student_event_id(db_pool).await?,
+    };
+
+    let mut heartbeat = tokio::time::interval(config::get().events.heartbeat());
+    heartbeat.tick().await;
+    let shutdown = health::shutting_down();
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            received = bus.recv() => match received {
+                Ok(event) => {
+                    if event.is_for(&session.student_id) && event.id > delivered {
+                        delivered = event.id;
+                        sender.send(FeedItem::Event(event)).await?;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(student_id = %session.student_id, skipped, "Event feed lagged, replaying from database");
+                    delivered = replay(session, delivered, sender).await?;
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            _ = heartbeat.tick() => {
+                // 会话被注销或学生被禁用后断开推送
+                if !is_session_active(session).await? {
+                    return Ok(());
+                }
+                sender.send(FeedItem::Heartbeat).await?;
+            }
+            _ = &mut shutdown => return Ok(()),
+        }
+    }
+}
+
+/// 补发 ID 大于 after_id 的事件，返回最后补发的事件ID
+async fn replay(session: &StudentSession, after_id: i64, sender: &mut mpsc::Sender<FeedItem>) -> Result<i64> {
+    let config = &config::get().events;
+    let since = (chrono::Utc::now() - chrono::Duration::hours(config.retention_hours)).naive_utc();
+    let db_pool = db::get_db_pool().await;
+    let records = db::list_student_events_after(db_pool, &session.student_id, after_id, since, config.replay_limit).await?;
+
+    let mut delivered = after_id;
+    for record in records {
+        delivered = delivered.max(record.id);
+        if let Some(event) = StoredEvent::from_record(record) {
+            sender.send(FeedItem::Event(Arc::new(event))).await?;
+        }
+    }
+    Ok(delivered)
+}
+
+async fn is_session_active(session: &StudentSession) -> Result<bool, sqlx::Error> {
+    Ok(auth::load_session_state(&session.student_id, &session.session_id).await? == SessionState::Active)
+}
+
+/// 事件流查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventStreamQuery {
+    /// 上次收到的事件ID，断线重连时补发之后的事件；SSE 客户端也可以使用 Last-Event-ID 请求头
+    pub last_event_id: Option<i64>,
+}
+
+fn resume_from(headers: &HeaderMap, query: &EventStreamQuery) -> Option<i64> {
+    headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .or(query.last_event_id)
+}
+
+/// 学生事件流（Server-Sent Events）
+///
+/// 推送新成绩、公告和学期切换等事件，定期发送注释行作为心跳；会话被注销或学生被禁用后断开。
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    tag = "student",
+    params(
+        EventStreamQuery,
+        ("Last-Event-ID" = Option<i64>, Header, description = "上次收到的事件ID（EventSource 重连时自动携带）"),
+    ),
+    responses(
+        (status = 200, description = "SSE 事件流，每条事件的 data 为对应类型的事件数据", content_type = "text/event-stream", body = StoredEvent),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
+pub async fn stream_events(
+    Extension(session): Extension<StudentSession>,
+    headers: HeaderMap,
+    ApiQuery(query): ApiQuery<EventStreamQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let feed = open_feed(session, resume_from(&headers, &query));
+    Sse::new(feed.map(|item| {
+        Ok(match item {
+            FeedItem::Event(event) => Event::default()
+                .id(event.id.to_string())
+                .event(event.event.event_type())
+                .data(event.data_json()),
+            FeedItem::Heartbeat => Event::default().comment("heartbeat"),
+        })
+    }))
+}
+
+/// 学生事件流（WebSocket）
+///
+/// 与 SSE 推送相同的事件，每条事件为一条 JSON 文本消息，心跳使用 Ping 帧；客户端发送的消息会被忽略。
+#[utoipa::path(
+    get,
+    path = "/api/events/ws",
+    tag = "student",
+    params(EventStreamQuery),
+    responses(
+        (status = 101, description = "升级为 WebSocket，之后每条消息为一个事件", body = StoredEvent),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
+pub async fn events_websocket(
+    Extension(session): Extension<StudentSession>,
+    headers: HeaderMap,
+    ApiQuery(query): ApiQuery<EventStreamQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let last_event_id = resume_from(&headers, &query);
+    upgrade.on_upgrade(move |socket| forward_to_websocket(socket, open_feed(session, last_event_id)))
+}
+
+async fn forward_to_websocket(mut socket: WebSocket, mut feed: mpsc::Receiver<FeedItem>) {
+    loop {
+        tokio::select! {
+            item = feed.next() => {
+                let message = match item {
+                    Some(FeedItem::Event(event)) => match serde_json::to_string(&*event) {
+                        Ok(text) => Message::Text(text.into()),
+                        Err(_) => continue,
+                    },
+                    Some(FeedItem::Heartbeat) => Message::Ping(Default::default()),
+                    None => break,
+                };
+                if socket.send(message).await.is_err() {
+                    return;
+                }
+            }
+            received = socket.recv() => match received {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
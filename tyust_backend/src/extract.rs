@@ -0,0 +1,20 @@
+use axum::extract::{FromRequest, FromRequestParts};
+
+use crate::error::ApiError;
+
+/// JSON 请求体，解析失败时返回统一的 `ApiError` 响应
+///
+/// 请求体格式错误返回 `invalid_request`，超过大小限制返回 `request_too_large`。
+#[derive(Debug, FromRequest)]
+#[from_request(via(axum::Json), rejection(ApiError))]
+pub struct ApiJson<T>(pub T);
+
+/// 查询参数，解析失败时返回 `invalid_request`
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(ApiError))]
+pub struct ApiQuery<T>(pub T);
+
+/// 路径参数，解析失败时返回 `invalid_request`
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(ApiError))]
+pub struct ApiPath<T>(pub T);
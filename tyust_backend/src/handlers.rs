@@ -1,22 +1,31 @@
 use axum::{
     Json,
-    extract::{Extension, Query},
-    http::StatusCode,
+    extract::Extension,
+    http::{HeaderMap, header},
 };
 use chrono::NaiveDate;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::{error, info_span, instrument, warn, Instrument};
 
 use crate::tyust_api::tyust_get_user_info;
 use crate::{
     api_types::{
-        ApiResponse, Course, LoginParams, ScheduleParams, Score, SemesterConfig,
-        SetSemesterStartRequest, UserInfo,
+        ApiResponse, Course, EmptyResponse, LoginParams, LoginResponse, RefreshTokenParams, ScheduleParams, Score,
+        SemesterConfig, SetSemesterStartRequest, StudentSessionInfo, TokenResponse, UserInfo, WechatLoginParams,
     },
-    auth::{UserAuthCache, generate_token},
+    audit::ClientIp,
+    avatar::{self, AvatarUpload},
+    cache::{self, CacheKey, CacheKind},
+    auth::{StudentSession, UserAuthCache, generate_token, issue_student_tokens, rotate_student_refresh_token},
+    db::RefreshRotation,
+    error::ApiError,
+    extract::{ApiJson, ApiPath, ApiQuery},
+    storage,
     de_crypto::get_crypto_and_password,
-    entity::UserLoginInfo,
+    entity::{LoginAttempt, SessionDevice, UserLoginInfo},
     tyust_api::{
         tyust_get_access_token, tyust_get_current_course, tyust_get_jwglxt_jsession,
         tyust_get_login_code, tyust_get_raw_scores, tyust_get_ronghemenhu_jsessionid,
@@ -30,25 +39,121 @@ lazy_static! {
 }
 
 /// 用户登录接口
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    tag = "auth",
+    request_body = LoginParams,
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<LoginResponse>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 403, description = "无权限或账号已被禁用", body = EmptyResponse),
+        (status = 413, description = "请求内容过大", body = EmptyResponse),
+        (status = 429, description = "请求过于频繁，响应头 Retry-After 为需等待的秒数", body = EmptyResponse),
+    ),
+)]
 pub async fn login(
-    Json(params): Json<LoginParams>,
-) -> Result<Json<ApiResponse<UserInfo>>, (StatusCode, Json<ApiResponse<()>>)> {
+    ip: ClientIp,
+    headers: HeaderMap,
+    ApiJson(params): ApiJson<LoginParams>,
+) -> Result<Json<ApiResponse<LoginResponse>>, ApiError> {
+    let started = Instant::now();
+    let device = SessionDevice {
+        device_name: login_device_name(&headers),
+        client: login_client(&headers),
+        ip: ip.0,
+    };
+    let result = login_inner(&params, &device).await;
+    crate::metrics::record_login("student", result.is_ok());
+    record_login_attempt(&params.student_id, result.as_ref().err(), started, &device, &headers).await;
+
+    result
+}
+
+/// 记录一次登录尝试（成功或失败），写入失败不影响登录结果
+async fn record_login_attempt(
+    student_id: &str,
+    error: Option<&ApiError>,
+    started: Instant,
+    device: &SessionDevice,
+    headers: &HeaderMap,
+) {
+    let attempt = LoginAttempt {
+        student_id: student_id.to_string(),
+        success: error.is_none(),
+        duration_ms: started.elapsed().as_millis().min(i32::MAX as u128) as i32,
+        client: device.client.clone(),
+        user_agent: headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string()),
+        ip: device.ip.clone(),
+        failure_reason: error.map(|e| e.code().to_string()),
+    };
+    let db_pool = crate::db::get_db_pool().await;
+    if let Err(e) = crate::db::record_login(db_pool, &attempt).await {
+        error!(error = %e, "Failed to record login attempt");
+    }
+}
+
+/// 根据请求头判断登录客户端类型
+///
+/// 优先使用客户端主动上报的 X-Client 头，否则根据 User-Agent 推断。
+fn login_client(headers: &HeaderMap) -> String {
+    if let Some(client) = headers
+        .get("x-client")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+    {
+        return client.chars().take(32).collect();
+    }
+
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    if user_agent.contains("miniProgram") || user_agent.contains("MicroMessenger") {
+        "wechat-miniprogram".to_string()
+    } else {
+        "other".to_string()
+    }
+}
+
+/// 客户端通过 X-Device-Name 头上报的设备名称（如手机型号）
+fn login_device_name(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-device-name")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.chars().take(64).collect())
+}
+
+async fn login_inner(
+    params: &LoginParams,
+    device: &SessionDevice,
+) -> Result<Json<ApiResponse<LoginResponse>>, ApiError> {
+    // 被管理员禁用的学生不允许登录（在请求上游之前检查）
+    let db_pool = crate::db::get_db_pool().await;
+    if crate::db::is_student_disabled(db_pool, &params.student_id).await.map_err(|e| {
+        error!(student_id = %params.student_id, error = %e, "Failed to check student status");
+        ApiError::DatabaseError
+    })? {
+        return Err(ApiError::AccountDisabled);
+    }
+
     // 验证登录信息
     let login_result = authenticate_user(&params.student_id, &params.password).await;
 
     match login_result {
         Ok(user_name) => {
-            // 生成JWT token
-            let token = match generate_token(&params.student_id) {
-                Ok(token) => token,
-                Err(_) => {
-                    return Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ApiResponse::error(
-                            500,
-                            "Failed to generate token".to_string(),
-                        )),
-                    ));
+            // 生成 access token 和 refresh token
+            let tokens = match issue_student_tokens(&params.student_id, device).await {
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    error!(error = %e, "Failed to issue student tokens");
+                    return Err(ApiError::TokenIssueFailed);
                 }
             };
 
@@ -64,97 +169,387 @@ pub async fn login(
                 None
             };
 
-            let user_info = UserLoginInfo {
+            let mut user_info = UserLoginInfo {
                 student_id: params.student_id.clone(),
                 name: user_name,
                 class: "未知班级".to_string(), // 可以从API获取更详细信息
-                token,
                 avatar_url, // 使用数据库中的头像URL（如果存在）
             };
 
             // 存储用户会话（数据库）
             let db_pool = crate::db::get_db_pool().await;
             if let Err(e) = crate::db::save_user(db_pool, &user_info).await {
-                eprintln!("Failed to save user to database: {}", e);
+                error!(error = %e, "Failed to save user to database");
             }
 
-            Ok(Json(ApiResponse::success(user_info)))
+            // 重新登录后上游凭证已更新，丢弃该学生的所有旧缓存
+            cache::invalidate_student(&params.student_id).await;
+
+            // 登录时带有 wx.login 的 code 则绑定微信，绑定失败不影响登录
+            let wechat_bound = match params.wechat_code.as_deref() {
+                Some(code) => bind_wechat(&params.student_id, code).await,
+                None => false,
+            };
+
+            let avatar_url = storage::resolve_url(user_info.avatar_url.as_deref()).await;
+            user_info.set_avatar_url(avatar_url);
+            Ok(Json(ApiResponse::success(LoginResponse {
+                user: user_info,
+                token: tokens.access_token,
+                refresh_token: tokens.refresh_token,
+                expires_in: tokens.expires_in,
+                wechat_bound,
+            })))
+        }
+        // 上游错误已由 authenticate_user 记录到日志，不返回给客户端
+        Err(_) => Err(ApiError::LoginFailed),
+    }
+}
+
+/// 将 wx.login 的 code 对应的微信绑定到学号，返回是否绑定成功
+async fn bind_wechat(student_id: &str, code: &str) -> bool {
+    let openid = match crate::wechat::openid_for_code(code).await {
+        Ok(openid) => openid,
+        Err(e) => {
+            warn!(student_id = %student_id, error = e.code(), "Skipping wechat binding");
+            return false;
+        }
+    };
+    let db_pool = crate::db::get_db_pool().await;
+    match crate::db::bind_wechat_openid(db_pool, student_id, &openid).await {
+        Ok(bound) => bound,
+        Err(e) => {
+            error!(student_id = %student_id, error = %e, "Failed to bind wechat");
+            false
+        }
+    }
+}
+
+/// 微信登录接口
+///
+/// 用 wx.login 得到的 code 换取 openid，已绑定学号（使用学号密码登录时提供过 wechatCode）时直接签发登录凭证。
+/// 课表和成绩仍依赖上游凭证，上游凭证过期后接口返回 login_required，需要重新使用学号密码登录。
+#[utoipa::path(
+    post,
+    path = "/api/auth/wechat-login",
+    tag = "auth",
+    request_body = WechatLoginParams,
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<LoginResponse>),
+        (status = 400, description = "code 无效或已过期", body = EmptyResponse),
+        (status = 403, description = "账号已被禁用", body = EmptyResponse),
+        (status = 404, description = "该微信尚未绑定学号", body = EmptyResponse),
+        (status = 413, description = "请求内容过大", body = EmptyResponse),
+        (status = 429, description = "请求过于频繁，响应头 Retry-After 为需等待的秒数", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误或微信接口不可用", body = EmptyResponse),
+        (status = 503, description = "未启用微信功能", body = EmptyResponse),
+    ),
+)]
+pub async fn wechat_login(
+    ip: ClientIp,
+    headers: HeaderMap,
+    ApiJson(params): ApiJson<WechatLoginParams>,
+) -> Result<Json<ApiResponse<LoginResponse>>, ApiError> {
+    let started = Instant::now();
+    let openid = crate::wechat::openid_for_code(&params.code).await?;
+
+    let db_pool = crate::db::get_db_pool().await;
+    let student_id = crate::db::find_student_by_wechat_openid(db_pool, &openid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to look up wechat binding");
+            ApiError::DatabaseError
+        })?
+        .ok_or(ApiError::WechatNotBound)?;
+
+    let device = SessionDevice {
+        device_name: login_device_name(&headers),
+        client: login_client(&headers),
+        ip: ip.0,
+    };
+    let result = wechat_login_inner(&student_id, &device).await;
+    crate::metrics::record_login("student_wechat", result.is_ok());
+    record_login_attempt(&student_id, result.as_ref().err(), started, &device, &headers).await;
+
+    result
+}
+
+async fn wechat_login_inner(
+    student_id: &str,
+    device: &SessionDevice,
+) -> Result<Json<ApiResponse<LoginResponse>>, ApiError> {
+    let db_pool = crate::db::get_db_pool().await;
+    if crate::db::is_student_disabled(db_pool, student_id).await.map_err(|e| {
+        error!(student_id = %student_id, error = %e, "Failed to check student status");
+        ApiError::DatabaseError
+    })? {
+        return Err(ApiError::AccountDisabled);
+    }
+
+    let mut user_info = crate::db::get_user(db_pool, student_id)
+        .await
+        .map_err(|e| {
+            error!(student_id = %student_id, error = %e, "Failed to load user");
+            ApiError::DatabaseError
+        })?
+        .ok_or(ApiError::WechatNotBound)?;
+
+    let tokens = issue_student_tokens(student_id, device).await.map_err(|e| {
+        error!(error = %e, "Failed to issue student tokens");
+        ApiError::TokenIssueFailed
+    })?;
+
+    let avatar_url = storage::resolve_url(user_info.avatar_url.as_deref()).await;
+    user_info.set_avatar_url(avatar_url);
+    Ok(Json(ApiResponse::success(LoginResponse {
+        user: user_info,
+        token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+        expires_in: tokens.expires_in,
+        wechat_bound: true,
+    })))
+}
+
+/// 解除当前学生的微信绑定，之后需要使用学号密码登录
+#[utoipa::path(
+    post,
+    path = "/api/auth/wechat/unbind",
+    tag = "auth",
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
+pub async fn unbind_wechat(
+    Extension(session): Extension<StudentSession>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let db_pool = crate::db::get_db_pool().await;
+    if let Err(e) = crate::db::unbind_wechat_openid(db_pool, &session.student_id).await {
+        error!(student_id = %session.student_id, error = %e, "Failed to unbind wechat");
+        return Err(ApiError::DatabaseError);
+    }
+
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// 刷新登录凭证接口
+///
+/// refresh token 只能使用一次，每次刷新都会签发新的 refresh token；
+/// 已使用过的 refresh token 再次出现时吊销同一登录产生的所有 refresh token。
+#[utoipa::path(
+    post,
+    path = "/api/auth/refresh",
+    tag = "auth",
+    request_body = RefreshTokenParams,
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<TokenResponse>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+)]
+pub async fn refresh_token(
+    ApiJson(params): ApiJson<RefreshTokenParams>,
+) -> Result<Json<ApiResponse<TokenResponse>>, ApiError> {
+    let (new_refresh_token, rotation) = rotate_student_refresh_token(&params.refresh_token)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to rotate refresh token");
+            ApiError::DatabaseError
+        })?;
+
+    let (student_id, session_id) = match rotation {
+        RefreshRotation::Rotated { student_id, family_id } => (student_id, family_id),
+        RefreshRotation::Reused { student_id, family_id } => {
+            warn!(student_id = %student_id, family_id = %family_id, "Refresh token reuse detected, token family revoked");
+            return Err(ApiError::InvalidRefreshToken);
         }
-        Err(err) => Err((
-            StatusCode::UNAUTHORIZED,
-            Json(ApiResponse::error(401, format!("Login failed: {}", err))),
-        )),
+        RefreshRotation::Invalid => return Err(ApiError::InvalidRefreshToken),
+    };
+
+    // 被禁用的学生不能继续刷新
+    let db_pool = crate::db::get_db_pool().await;
+    if crate::db::is_student_disabled(db_pool, &student_id).await.map_err(|e| {
+        error!(student_id = %student_id, error = %e, "Failed to check student status");
+        ApiError::DatabaseError
+    })? {
+        let _ = crate::db::revoke_student_sessions(db_pool, &student_id).await;
+        return Err(ApiError::AccountDisabled);
     }
+
+    let access_token = generate_token(&student_id, &session_id).map_err(|e| {
+        error!(error = %e, "Failed to generate token");
+        ApiError::TokenIssueFailed
+    })?;
+
+    Ok(Json(ApiResponse::success(TokenResponse {
+        token: access_token,
+        refresh_token: new_refresh_token,
+        expires_in: crate::config::get().auth.student_access_token_ttl_secs,
+    })))
 }
 
 /// 获取课表接口
+#[utoipa::path(
+    get,
+    path = "/api/schedule",
+    tag = "student",
+    params(ScheduleParams),
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<Vec<Course>>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
 pub async fn get_schedule(
     Extension(student_id): Extension<String>,
-    Query(_params): Query<ScheduleParams>,
-) -> Result<Json<ApiResponse<Vec<Course>>>, (StatusCode, Json<ApiResponse<()>>)> {
-    // 从数据库中获取用户信息
-    let db_pool = crate::db::get_db_pool().await;
-    let _user_info = match crate::db::get_user(db_pool, &student_id).await {
+    ApiQuery(_params): ApiQuery<ScheduleParams>,
+) -> Result<Json<ApiResponse<Vec<Course>>>, ApiError> {
+    // 获取用户信息
+    let _user_info = match crate::auth::load_user(&student_id).await {
         Ok(Some(info)) => info,
         _ => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(
-                    401,
-                    "User session not found".to_string(),
-                )),
-            ));
+            return Err(ApiError::LoginRequired);
         }
     };
 
     // 获取课表数据
-    match get_user_courses(&student_id).await {
-        Ok(courses) => {
-            // 前端会根据weeks数组自行过滤，后端直接返回所有课程
-            Ok(Json(ApiResponse::success(courses)))
-        }
-        Err(err) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(
-                500,
-                format!("Failed to get courses: {}", err),
-            )),
-        )),
-    }
+    // 前端会根据weeks数组自行过滤，后端直接返回所有课程
+    let courses = get_user_courses(&student_id).await?;
+    Ok(Json(ApiResponse::success(courses)))
 }
 
 /// 获取用户信息接口
+#[utoipa::path(
+    get,
+    path = "/api/user/info",
+    tag = "student",
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<UserInfo>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
 pub async fn get_user_info(
     Extension(student_id): Extension<String>,
-) -> Result<Json<ApiResponse<UserInfo>>, (StatusCode, Json<ApiResponse<()>>)> {
+) -> Result<Json<ApiResponse<UserInfo>>, ApiError> {
+    match crate::auth::load_user(&student_id).await {
+        Ok(Some(mut user_info)) => {
+            // 签名地址有有效期，缓存中只保存存储键，每次返回时重新生成
+            let avatar_url = storage::resolve_url(user_info.avatar_url.as_deref()).await;
+            user_info.set_avatar_url(avatar_url);
+            Ok(Json(ApiResponse::success(user_info)))
+        }
+        _ => Err(ApiError::LoginRequired),
+    }
+}
+
+/// 用户登出接口：只注销当前会话，保留用户资料和头像
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    tag = "auth",
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
+pub async fn logout(
+    Extension(session): Extension<StudentSession>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
     let db_pool = crate::db::get_db_pool().await;
-    match crate::db::get_user(db_pool, &student_id).await {
-        Ok(Some(user_info)) => Ok(Json(ApiResponse::success(user_info))),
-        _ => Err((
-            StatusCode::UNAUTHORIZED,
-            Json(ApiResponse::error(
-                401,
-                "User session not found".to_string(),
-            )),
-        )),
+    if let Err(e) = crate::db::revoke_student_session(db_pool, &session.student_id, &session.session_id).await {
+        error!(student_id = %session.student_id, error = %e, "Failed to revoke session");
+        return Err(ApiError::DatabaseError);
+    }
+
+    // 没有其他有效会话时再清除上游认证缓存
+    if let Ok(0) = crate::db::count_active_student_sessions(db_pool, &session.student_id).await {
+        let _ = crate::db::delete_auth_cache(db_pool, &session.student_id).await;
     }
+    cache::invalidate_student(&session.student_id).await;
+
+    Ok(Json(ApiResponse::success(())))
 }
 
-/// 用户登出接口
-pub async fn logout(Extension(student_id): Extension<String>) -> Json<ApiResponse<()>> {
-    // 从数据库中删除
+/// 获取当前学生已登录的设备列表
+#[utoipa::path(
+    get,
+    path = "/api/auth/sessions",
+    tag = "auth",
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<Vec<StudentSessionInfo>>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
+pub async fn list_sessions(
+    Extension(session): Extension<StudentSession>,
+) -> Result<Json<ApiResponse<Vec<StudentSessionInfo>>>, ApiError> {
     let db_pool = crate::db::get_db_pool().await;
-    let _ = crate::db::delete_user(db_pool, &student_id).await;
-    let _ = crate::db::delete_auth_cache(db_pool, &student_id).await;
+    match crate::db::list_student_sessions(db_pool, &session.student_id).await {
+        Ok(records) => Ok(Json(ApiResponse::success(
+            records
+                .into_iter()
+                .map(|record| StudentSessionInfo {
+                    current: record.id == session.session_id,
+                    session: record,
+                })
+                .collect(),
+        ))),
+        Err(e) => {
+            error!(student_id = %session.student_id, error = %e, "Failed to list sessions");
+            Err(ApiError::DatabaseError)
+        }
+    }
+}
 
-    Json(ApiResponse::success(()))
+/// 注销当前学生的某个登录设备
+#[utoipa::path(
+    post,
+    path = "/api/auth/sessions/{session_id}/revoke",
+    tag = "auth",
+    params(("session_id" = String, Path, description = "会话ID")),
+    responses(
+        (status = 200, description = "成功", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 404, description = "资源不存在", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
+pub async fn revoke_session(
+    Extension(session): Extension<StudentSession>,
+    ApiPath(session_id): ApiPath<String>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let db_pool = crate::db::get_db_pool().await;
+    match crate::db::revoke_student_session(db_pool, &session.student_id, &session_id).await {
+        Ok(true) => {
+            cache::invalidate(CacheKind::Session, &session.student_id).await;
+            if let Ok(0) = crate::db::count_active_student_sessions(db_pool, &session.student_id).await {
+                let _ = crate::db::delete_auth_cache(db_pool, &session.student_id).await;
+                cache::invalidate_student(&session.student_id).await;
+            }
+            Ok(Json(ApiResponse::success(())))
+        }
+        Ok(false) => Err(ApiError::SessionNotFound),
+        Err(e) => {
+            error!(student_id = %session.student_id, error = %e, "Failed to revoke session");
+            Err(ApiError::DatabaseError)
+        }
+    }
 }
 
 #[allow(unused)]
 /// 设置开学时间接口
 pub async fn set_semester_start(
-    Json(params): Json<SetSemesterStartRequest>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+    ApiJson(params): ApiJson<SetSemesterStartRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
     let config = SemesterConfig {
         semester_start_date: params.start_date,
         semester_name: params.semester_name,
@@ -163,13 +558,8 @@ pub async fn set_semester_start(
     // 保存到数据库
     let db_pool = crate::db::get_db_pool().await;
     if let Err(e) = crate::db::save_semester_config(db_pool, &config).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(
-                500,
-                format!("Failed to save semester config to database: {}", e),
-            )),
-        ));
+        error!(error = %e, "Failed to save semester config");
+        return Err(ApiError::DatabaseError);
     }
 
     // 保存到内存
@@ -178,38 +568,20 @@ pub async fn set_semester_start(
             *semester_config = Some(config);
             Ok(Json(ApiResponse::success(())))
         }
-        Err(_) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(
-                500,
-                "Failed to set semester config".to_string(),
-            )),
-        )),
+        Err(_) => Err(ApiError::InternalError),
     }
 }
 
 #[allow(unused)]
 /// 获取开学时间接口
 pub async fn get_semester_start()
--> Result<Json<ApiResponse<SemesterConfig>>, (StatusCode, Json<ApiResponse<()>>)> {
+-> Result<Json<ApiResponse<SemesterConfig>>, ApiError> {
     match SEMESTER_CONFIG.lock() {
         Ok(semester_config) => match semester_config.as_ref() {
             Some(config) => Ok(Json(ApiResponse::success(config.clone()))),
-            None => Err((
-                StatusCode::NOT_FOUND,
-                Json(ApiResponse::error(
-                    404,
-                    "Semester start date not set".to_string(),
-                )),
-            )),
+            None => Err(ApiError::SemesterNotConfigured),
         },
-        Err(_) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(
-                500,
-                "Failed to get semester config".to_string(),
-            )),
-        )),
+        Err(_) => Err(ApiError::InternalError),
     }
 }
 
@@ -266,35 +638,37 @@ fn calculate_current_week() -> Option<i32> {
 }
 
 /// 获取学期配置
+#[utoipa::path(
+    get,
+    path = "/api/semester-config",
+    tag = "student",
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<SemesterConfig>),
+        (status = 404, description = "资源不存在", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+)]
 pub async fn get_semester_config()
--> Result<Json<ApiResponse<crate::api_types::SemesterConfig>>, (StatusCode, Json<ApiResponse<()>>)>
+-> Result<Json<ApiResponse<crate::api_types::SemesterConfig>>, ApiError>
 {
     match SEMESTER_CONFIG.lock() {
         Ok(semester_config) => match semester_config.as_ref() {
             Some(config) => Ok(Json(ApiResponse::success(config.clone()))),
-            None => Err((
-                StatusCode::NOT_FOUND,
-                Json(ApiResponse::error(
-                    404,
-                    "Semester configuration not found".to_string(),
-                )),
-            )),
+            None => Err(ApiError::SemesterNotConfigured),
         },
-        Err(_) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(
-                500,
-                "Failed to get semester config".to_string(),
-            )),
-        )),
+        Err(_) => Err(ApiError::InternalError),
     }
 }
 
 /// 认证用户（使用现有的登录逻辑）
+///
+/// 每个上游步骤都有对应的 span（见 tyust_api），失败时可以从日志中定位是哪一步出错。
+#[instrument(skip(password), err)]
 async fn authenticate_user(student_id: &str, password: &str) -> Result<String, String> {
     // 使用现有的登录流程进行认证
-    let (crypto, password_str) =
-        get_crypto_and_password(password).map_err(|e| format!("Crypto error: {}", e))?;
+    let (crypto, password_str) = info_span!("encrypt_password")
+        .in_scope(|| get_crypto_and_password(password))
+        .map_err(|e| format!("Crypto error: {}", e))?;
 
     let (session, execution_code) = tyust_get_session()
         .await
@@ -345,8 +719,11 @@ async fn authenticate_user(student_id: &str, password: &str) -> Result<String, S
 
     // 只执行一次数据库保存操作
     let db_pool = crate::db::get_db_pool().await;
-    if let Err(e) = crate::db::save_auth_cache(db_pool, student_id, &auth_cache).await {
-        eprintln!("Failed to save auth cache to database: {}", e);
+    if let Err(e) = crate::db::save_auth_cache(db_pool, student_id, &auth_cache)
+        .instrument(info_span!("save_auth_cache"))
+        .await
+    {
+        error!(error = %e, "Failed to save auth cache to database");
     }
 
     let user_info_response = tyust_get_user_info(&ronghemenhu_jsession)
@@ -355,60 +732,71 @@ async fn authenticate_user(student_id: &str, password: &str) -> Result<String, S
     Ok(user_info_response.data.name)
 }
 
-/// 获取用户课程（使用缓存的认证信息）
-async fn get_user_courses(student_id: &str) -> Result<Vec<Course>, String> {
-    // 从数据库中获取认证信息
-    let db_pool = crate::db::get_db_pool().await;
-    let auth_cache = crate::db::get_auth_cache(db_pool, student_id)
-        .await
-        .ok()
-        .flatten()
-        .ok_or_else(|| "User auth cache not found. Please login again.".to_string())?;
-
-    // 检查缓存是否有效（24小时内）
-    use crate::auth::is_auth_cache_valid;
-    if !is_auth_cache_valid(&auth_cache) {
-        return Err("Auth cache expired. Please login again.".to_string());
+/// 读取学生的上游认证信息，不存在或已过期时需要重新登录
+async fn load_valid_auth_cache(student_id: &str) -> Result<UserAuthCache, ApiError> {
+    match crate::auth::load_auth_cache(student_id).await {
+        Ok(Some(auth_cache)) if crate::auth::is_auth_cache_valid(&auth_cache) => Ok(auth_cache),
+        _ => Err(ApiError::LoginRequired),
     }
+}
 
-    // 使用缓存的认证信息获取课程数据
-    let kb_list = tyust_get_current_course(
-        &auth_cache.jwglxt_jsession,
-        &auth_cache.access_token,
-        &auth_cache.route,
-    )
-    .await
-    .map_err(|e| format!("Course error: {}", e))?;
+/// 上游请求失败时记录原因，返回给客户端的只有错误码
+fn upstream_error(operation: &'static str) -> impl FnOnce(anyhow::Error) -> ApiError {
+    move |e| {
+        error!(operation, error = %e, "Upstream request failed");
+        ApiError::UpstreamUnavailable
+    }
+}
 
-    // 转换为Course格式
-    let courses: Vec<Course> = kb_list.into_iter().map(Course::from).collect();
+/// 获取用户课程（使用缓存的认证信息）
+async fn get_user_courses(student_id: &str) -> Result<Vec<Course>, ApiError> {
+    let auth_cache = load_valid_auth_cache(student_id).await?;
+
+    // 使用缓存的认证信息获取课程数据，结果按学生缓存
+    cache::cached(&CacheKey::new(CacheKind::Courses, student_id), || async {
+        let kb_list = tyust_get_current_course(
+            &auth_cache.jwglxt_jsession,
+            &auth_cache.access_token,
+            &auth_cache.route,
+        )
+        .await
+        .map_err(upstream_error("courses"))?;
 
-    Ok(courses)
+        // 转换为Course格式
+        Ok(kb_list.into_iter().map(Course::from).collect())
+    })
+    .await
 }
 
 /// 获取课程列表接口（不过滤周次）
+#[utoipa::path(
+    get,
+    path = "/api/courses",
+    tag = "student",
+    params(
+        ("token" = String, Header, description = "学生 token"),
+        ("studentId" = String, Header, description = "学号"),
+    ),
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<Vec<Course>>),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+)]
 pub async fn get_courses(
     headers: axum::http::HeaderMap,
-) -> Result<Json<ApiResponse<Vec<Course>>>, (StatusCode, Json<ApiResponse<()>>)> {
+) -> Result<Json<ApiResponse<Vec<Course>>>, ApiError> {
     // 从 header 中获取 token
     let token = match headers.get("token") {
         Some(header_value) => match header_value.to_str() {
             Ok(t) => t,
             Err(_) => {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(ApiResponse::error(
-                        400,
-                        "Invalid token header value".to_string(),
-                    )),
-                ));
+                return Err(ApiError::InvalidRequest);
             }
         },
         None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse::error(400, "Missing token header".to_string())),
-            ));
+            return Err(ApiError::InvalidRequest);
         }
     };
 
@@ -417,23 +805,11 @@ pub async fn get_courses(
         Some(header_value) => match header_value.to_str() {
             Ok(id) => id,
             Err(_) => {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(ApiResponse::error(
-                        400,
-                        "Invalid studentId header value".to_string(),
-                    )),
-                ));
+                return Err(ApiError::InvalidRequest);
             }
         },
         None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse::error(
-                    400,
-                    "Missing studentId header".to_string(),
-                )),
-            ));
+            return Err(ApiError::InvalidRequest);
         }
     };
 
@@ -442,197 +818,129 @@ pub async fn get_courses(
     let claims = match verify_token(token) {
         Ok(claims) => claims,
         Err(_) => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(401, "Invalid token".to_string())),
-            ));
+            return Err(ApiError::InvalidToken);
         }
     };
 
     // 确保token中的student_id与请求的student_id匹配
     if claims.sub != student_id {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(ApiResponse::error(
-                403,
-                "Token does not match student_id".to_string(),
-            )),
-        ));
+        return Err(ApiError::TokenMismatch);
     }
 
-    // 从数据库中获取用户信息
-    let db_pool = crate::db::get_db_pool().await;
-    let _user_info = match crate::db::get_user(db_pool, student_id).await {
+    // 会话已注销或学生被禁用时拒绝访问
+    crate::auth::check_student_session(&claims).await?;
+
+    // 获取用户信息
+    let _user_info = match crate::auth::load_user(student_id).await {
         Ok(Some(info)) => info,
         _ => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(
-                    401,
-                    "User session not found".to_string(),
-                )),
-            ));
+            return Err(ApiError::LoginRequired);
         }
     };
 
     // 获取课程数据
-    match get_user_courses(student_id).await {
-        Ok(courses) => Ok(Json(ApiResponse::success(courses))),
-        Err(err) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(
-                500,
-                format!("Failed to get courses: {}", err),
-            )),
-        )),
-    }
+    let courses = get_user_courses(student_id).await?;
+    Ok(Json(ApiResponse::success(courses)))
 }
 
 /// 获取有效成绩接口
+#[utoipa::path(
+    get,
+    path = "/api/scores",
+    tag = "student",
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<Vec<Score>>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
 pub async fn get_scores(
     Extension(student_id): Extension<String>,
-) -> Result<Json<ApiResponse<Vec<Score>>>, (StatusCode, Json<ApiResponse<()>>)> {
-    // 从数据库中获取用户信息
-    let db_pool = crate::db::get_db_pool().await;
-    let _user_info = match crate::db::get_user(db_pool, &student_id).await {
-        Ok(Some(info)) => info,
-        _ => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(
-                    401,
-                    "User session not found".to_string(),
-                )),
-            ));
-        }
-    };
-
-    // 从数据库中获取认证信息
-    let auth_cache = match crate::db::get_auth_cache(db_pool, &student_id).await {
-        Ok(Some(cache)) => cache,
-        _ => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(
-                    401,
-                    "User auth cache not found. Please login again.".to_string(),
-                )),
-            ));
-        }
-    };
-
-    // 检查缓存是否有效
-    use crate::auth::is_auth_cache_valid;
-    if !is_auth_cache_valid(&auth_cache) {
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            Json(ApiResponse::error(
-                401,
-                "Auth cache expired. Please login again.".to_string(),
-            )),
-        ));
+) -> Result<Json<ApiResponse<Vec<Score>>>, ApiError> {
+    // 获取用户信息
+    if !matches!(crate::auth::load_user(&student_id).await, Ok(Some(_))) {
+        return Err(ApiError::LoginRequired);
     }
 
+    // 获取认证信息
+    let auth_cache = load_valid_auth_cache(&student_id).await?;
+
     // 使用缓存的认证信息获取成绩数据
-    match tyust_get_scores(
-        &auth_cache.jwglxt_jsession,
-        &auth_cache.access_token,
-        &auth_cache.route,
-    )
-    .await
-    {
-        Ok(score_items) => {
-            let scores: Vec<Score> = score_items.into_iter().map(Score::from).collect();
-            Ok(Json(ApiResponse::success(scores)))
-        }
-        Err(err) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(
-                500,
-                format!("Failed to get scores: {}", err),
-            )),
-        )),
-    }
+    let scores = cache::cached(&CacheKey::new(CacheKind::Scores, &student_id), || async {
+        tyust_get_scores(
+            &auth_cache.jwglxt_jsession,
+            &auth_cache.access_token,
+            &auth_cache.route,
+        )
+        .await
+        .map(|score_items| score_items.into_iter().map(Score::from).collect::<Vec<Score>>())
+        .map_err(upstream_error("scores"))
+    })
+    .await?;
+    Ok(Json(ApiResponse::success(scores)))
 }
 
 /// 获取原始成绩接口
+#[utoipa::path(
+    get,
+    path = "/api/raw-scores",
+    tag = "student",
+    params(crate::api_types::RawScoresParams),
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<Vec<Score>>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
 pub async fn get_raw_scores(
     Extension(student_id): Extension<String>,
-    Query(params): Query<crate::api_types::RawScoresParams>,
-) -> Result<Json<ApiResponse<Vec<Score>>>, (StatusCode, Json<ApiResponse<()>>)> {
-    // 从数据库中获取用户信息
-    let db_pool = crate::db::get_db_pool().await;
-    let _user_info = match crate::db::get_user(db_pool, &student_id).await {
-        Ok(Some(info)) => info,
-        _ => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(
-                    401,
-                    "User session not found".to_string(),
-                )),
-            ));
-        }
-    };
-
-    // 从数据库中获取认证信息
-    let auth_cache = match crate::db::get_auth_cache(db_pool, &student_id).await {
-        Ok(Some(cache)) => cache,
-        _ => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(
-                    401,
-                    "User auth cache not found. Please login again.".to_string(),
-                )),
-            ));
-        }
-    };
-
-    // 检查缓存是否有效
-    use crate::auth::is_auth_cache_valid;
-    if !is_auth_cache_valid(&auth_cache) {
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            Json(ApiResponse::error(
-                401,
-                "Auth cache expired. Please login again.".to_string(),
-            )),
-        ));
+    ApiQuery(params): ApiQuery<crate::api_types::RawScoresParams>,
+) -> Result<Json<ApiResponse<Vec<Score>>>, ApiError> {
+    // 获取用户信息
+    if !matches!(crate::auth::load_user(&student_id).await, Ok(Some(_))) {
+        return Err(ApiError::LoginRequired);
     }
 
+    // 获取认证信息
+    let auth_cache = load_valid_auth_cache(&student_id).await?;
+
     // 使用缓存的认证信息获取原始成绩数据
     // 使用查询参数或默认为学号和空字符串
     let xh_id = params.xh_id.as_deref().unwrap_or(&student_id);
     let xnm = params.xnm.as_deref().unwrap_or("");
     let xqm = params.xqm.as_deref().unwrap_or("");
 
-    match tyust_get_raw_scores(
-        &auth_cache.jwglxt_jsession,
-        &auth_cache.route,
-        xh_id,
-        xnm,
-        xqm,
-    )
-    .await
-    {
-        Ok(score_items) => {
-            let scores: Vec<Score> = score_items.into_iter().map(Score::from).collect();
-            Ok(Json(ApiResponse::success(scores)))
-        }
-        Err(err) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::error(
-                500,
-                format!("Failed to get raw scores: {}", err),
-            )),
-        )),
-    }
+    let cache_key = CacheKey::with_suffix(CacheKind::RawScores, &student_id, &[xh_id, xnm, xqm]);
+    let scores = cache::cached(&cache_key, || async {
+        tyust_get_raw_scores(
+            &auth_cache.jwglxt_jsession,
+            &auth_cache.route,
+            xh_id,
+            xnm,
+            xqm,
+        )
+        .await
+        .map(|score_items| score_items.into_iter().map(Score::from).collect::<Vec<Score>>())
+        .map_err(upstream_error("raw_scores"))
+    })
+    .await?;
+    Ok(Json(ApiResponse::success(scores)))
 }
 
 /// 初始化登录(获取验证码相关信息)
+#[utoipa::path(
+    get,
+    path = "/api/login-init",
+    tag = "auth",
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<crate::api_types::LoginInitData>),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+)]
 pub async fn init_login()
--> Result<Json<ApiResponse<crate::api_types::LoginInitData>>, (StatusCode, Json<ApiResponse<()>>)> {
+-> Result<Json<ApiResponse<crate::api_types::LoginInitData>>, ApiError> {
     use crate::api_types::LoginInitData;
 
     // 简化实现: 生成一个模拟的cookie和formData
@@ -649,8 +957,17 @@ pub async fn init_login()
 }
 
 /// 获取验证码图片
+#[utoipa::path(
+    get,
+    path = "/api/login-code",
+    tag = "auth",
+    params(("cookie" = Option<String>, Query, description = "login-init 返回的 cookie")),
+    responses(
+        (status = 200, description = "验证码图片", content_type = "image/png", body = Vec<u8>),
+    ),
+)]
 pub async fn get_login_code(
-    Query(params): Query<std::collections::HashMap<String, String>>,
+    ApiQuery(params): ApiQuery<std::collections::HashMap<String, String>>,
 ) -> Vec<u8> {
     // 简化实现: 返回一个简单的1x1像素PNG图片
     // 前端会显示这个"验证码",但实际不需要用户输入
@@ -675,104 +992,69 @@ pub async fn get_login_code(
     ]
 }
 
-/// 更新头像请求参数
-#[derive(serde::Deserialize)]
-pub struct UpdateAvatarParams {
-    #[serde(rename = "avatarData")]
-    pub avatar_data: String,
-}
-
 /// 更新用户头像
+///
+/// 支持 multipart 和 base64 JSON 两种上传方式，图片经过格式校验、统一尺寸重新编码后
+/// 按内容摘要保存到文件存储中该学生的目录，并清理被替换的旧头像。
+#[utoipa::path(
+    post,
+    path = "/api/update-avatar",
+    tag = "student",
+    request_body(
+        content(
+            (avatar::UpdateAvatarParams = "application/json"),
+            (avatar::AvatarForm = "multipart/form-data"),
+        ),
+        description = "JPEG、PNG、WebP 或 GIF 图片",
+    ),
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "请求参数错误", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 413, description = "上传文件过大", body = EmptyResponse),
+        (status = 415, description = "文件格式不支持", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
 pub async fn update_avatar(
     Extension(student_id): Extension<String>,
-    Json(params): Json<UpdateAvatarParams>,
-) -> Result<Json<ApiResponse<serde_json::Value>>, (StatusCode, Json<ApiResponse<()>>)> {
-    use base64::{Engine as _, engine::general_purpose};
-    use std::fs::File;
-    use std::io::Write;
-
+    AvatarUpload(data): AvatarUpload,
+) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
     let db_pool = crate::db::get_db_pool().await;
 
     // 获取当前用户信息
     let mut user_info = match crate::db::get_user(db_pool, &student_id).await {
         Ok(Some(user)) => user,
         _ => {
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::error(401, "User not found".to_string())),
-            ));
+            return Err(ApiError::LoginRequired);
         }
     };
 
-    // 解码base64数据
-    let base64_data = params.avatar_data.replace("data:image/jpeg;base64,", "");
-    let base64_data = base64_data.replace("data:image/png;base64,", "");
-
-    match general_purpose::STANDARD.decode(&base64_data) {
-        Ok(image_data) => {
-            // 生成唯一的文件名
-            let filename = format!("avatar_{}.jpg", chrono::Utc::now().timestamp());
-            let file_path = format!("static/avatars/{}", filename);
-
-            // 确保目录存在
-            std::fs::create_dir_all("static/avatars").ok();
-
-            // 保存文件
-            match File::create(&file_path) {
-                Ok(mut file) => {
-                    if let Err(e) = file.write_all(&image_data) {
-                        eprintln!("Failed to write avatar file: {}", e);
-                        return Err((
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ApiResponse::error(
-                                500,
-                                "Failed to save avatar file".to_string(),
-                            )),
-                        ));
-                    }
-
-                    // 构建可访问的URL
-                    let avatar_url = format!("/static/avatars/{}", filename);
-
-                    // 更新头像URL
-                    user_info.set_avatar_url(Some(avatar_url.clone()));
-
-                    // 保存到数据库
-                    if let Err(e) = crate::db::save_user(db_pool, &user_info).await {
-                        return Err((
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ApiResponse::error(
-                                500,
-                                format!("Failed to update avatar: {}", e),
-                            )),
-                        ));
-                    }
-
-                    // 返回新的头像URL
-                    let response_data = serde_json::json!({
-                        "avatarUrl": avatar_url
-                    });
-
-                    Ok(Json(ApiResponse::success(response_data)))
-                }
-                Err(e) => {
-                    eprintln!("Failed to create avatar file: {}", e);
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ApiResponse::error(
-                            500,
-                            "Failed to create avatar file".to_string(),
-                        )),
-                    ))
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Failed to decode base64 avatar data: {}", e);
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse::error(400, "Invalid avatar data".to_string())),
-            ))
-        }
+    // 校验并重新编码（CPU 密集，放到阻塞线程）
+    let avatar_config = crate::config::get().avatar.clone();
+    let processed = tokio::task::spawn_blocking(move || avatar::process(&data, &avatar_config))
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Avatar processing task failed");
+            ApiError::InternalError
+        })??;
+    let stored = avatar::store(&student_id, processed).await.map_err(|e| {
+        error!(error = %e, "Failed to store avatar file");
+        ApiError::StorageError
+    })?;
+
+    // 数据库中保存存储键，访问地址在返回时生成
+    // 被替换的旧头像由 avatar_gc 定时任务清理，这里不删除，避免同一学生并发上传时删掉对方刚写入的文件
+    user_info.set_avatar_url(Some(stored.avatar_key.clone()));
+    if let Err(e) = crate::db::save_user(db_pool, &user_info).await {
+        error!(student_id = %student_id, error = %e, "Failed to update avatar");
+        return Err(ApiError::DatabaseError);
     }
+    cache::invalidate(CacheKind::User, &student_id).await;
+
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "avatarUrl": storage::resolve_url(Some(&stored.avatar_key)).await,
+        "thumbnailUrl": storage::resolve_url(Some(&stored.thumbnail_key)).await,
+    }))))
 }
@@ -0,0 +1,160 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    time::Instant,
+};
+
+use axum::{http::StatusCode, routing::get, Json, Router};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::watch;
+use tracing::{error, info};
+
+use crate::{config, db, tyust_api};
+
+/// 存活检查地址：进程能处理请求即返回 200
+pub const LIVENESS_PATH: &str = "/healthz";
+
+/// 就绪检查地址：依赖可用时返回 200，否则返回 503
+pub const READINESS_PATH: &str = "/readyz";
+
+/// 收到停止信号后置为 true，就绪检查随即返回 503，使负载均衡不再转发新请求；
+/// 长连接（事件推送）订阅该状态以便主动断开
+static SHUTTING_DOWN: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+#[derive(Serialize)]
+pub struct LivenessResponse {
+    pub status: &'static str,
+}
+
+/// 单项检查结果
+#[derive(Serialize)]
+pub struct CheckResult {
+    pub ok: bool,
+    pub elapsed_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 就绪检查结果，status 为 ready、not_ready 或 shutting_down
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    pub status: &'static str,
+    pub checks: BTreeMap<&'static str, CheckResult>,
+}
+
+/// 健康检查路由，不经过请求日志，避免探针请求刷屏
+pub fn routes() -> Router {
+    Router::new()
+        .route(LIVENESS_PATH, get(liveness))
+        .route(READINESS_PATH, get(readiness))
+}
+
+async fn liveness() -> Json<LivenessResponse> {
+    Json(LivenessResponse { status: "ok" })
+}
+
+async fn readiness() -> (StatusCode, Json<ReadinessResponse>) {
+    if *SHUTTING_DOWN.borrow() {
+        let response = ReadinessResponse {
+            status: "shutting_down",
+            checks: BTreeMap::new(),
+        };
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(response));
+    }
+
+    let (database, migrations, upstream) = tokio::join!(
+        run_check(check_database()),
+        run_check(check_migrations()),
+        async {
+            if config::get().health.upstream_probe {
+                Some(run_check(tyust_api::tyust_probe_sso()).await)
+            } else {
+                None
+            }
+        },
+    );
+
+    let mut checks = BTreeMap::from([("database", database), ("migrations", migrations)]);
+    if let Some(upstream) = upstream {
+        checks.insert("upstream", upstream);
+    }
+    let ready = checks.values().all(|check| check.ok);
+    let (status, code) = if ready {
+        ("ready", StatusCode::OK)
+    } else {
+        ("not_ready", StatusCode::SERVICE_UNAVAILABLE)
+    };
+    (code, Json(ReadinessResponse { status, checks }))
+}
+
+/// 执行一项检查并记录耗时，超过 health.probe_timeout_secs 视为失败
+async fn run_check(check: impl Future<Output = anyhow::Result<()>>) -> CheckResult {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(config::get().health.probe_timeout(), check).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!("timed out")),
+    };
+    CheckResult {
+        ok: result.is_ok(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        error: result.err().map(|e| format!("{:#}", e)),
+    }
+}
+
+async fn check_database() -> anyhow::Result<()> {
+    let db_pool = db::get_db_pool().await;
+    sqlx::query("SELECT 1").execute(db_pool).await?;
+    Ok(())
+}
+
+/// 嵌入的迁移都已应用（关闭自动迁移时，新版本可能在执行 `tyust migrate` 之前启动）
+async fn check_migrations() -> anyhow::Result<()> {
+    let db_pool = db::get_db_pool().await;
+    let pending = db::migration_status(db_pool)
+        .await?
+        .into_iter()
+        .filter(|migration| migration.applied_at.is_none())
+        .count();
+    if pending > 0 {
+        anyhow::bail!("{} migration(s) pending", pending);
+    }
+    Ok(())
+}
+
+/// 等待 SIGTERM 或 Ctrl+C，收到后将服务标记为停机中
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!(error = %e, "Failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl+C"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+    SHUTTING_DOWN.send_replace(true);
+}
+
+/// 等待服务开始停机
+pub async fn shutting_down() {
+    let mut receiver = SHUTTING_DOWN.subscribe();
+    let _ = receiver.wait_for(|shutting_down| *shutting_down).await;
+}
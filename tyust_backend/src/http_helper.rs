@@ -1,30 +1,67 @@
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, time::Instant};
 
 use anyhow::Result;
-use reqwest::{header::{HeaderMap, HeaderValue}, Client};
+use reqwest::{header::{HeaderMap, HeaderValue}, Client, RequestBuilder, Response};
 use url::Url;
 
+use tracing::{debug, warn};
+
+use crate::{config, metrics};
+
+/// 按配置创建HTTP客户端构造器（超时和连接池大小见 `http` 配置段）
+fn client_builder() -> reqwest::ClientBuilder {
+    let http = &config::get().http;
+    Client::builder()
+        .timeout(http.timeout())
+        .connect_timeout(http.connect_timeout())
+        .pool_max_idle_per_host(http.pool_max_idle_per_host)
+}
+
 /// 创建不跟随重定向的HTTP客户端
 pub fn new_client_no_redirect() -> Result<Client> {
-    Client::builder()
-        .timeout(Duration::from_secs(8))  // 减少超时时间
-        .connect_timeout(Duration::from_secs(5))  // 添加连接超时
+    client_builder()
         .redirect(reqwest::redirect::Policy::none())
-        .pool_max_idle_per_host(4)  // 限制连接池大小
         .build()
         .map_err(Into::into)
 }
 
 /// 创建跟随重定向的HTTP客户端
 pub fn new_client_follow() -> Result<Client> {
-    Client::builder()
-        .timeout(Duration::from_secs(8))  // 减少超时时间
-        .connect_timeout(Duration::from_secs(5))  // 添加连接超时
-        .pool_max_idle_per_host(4)  // 限制连接池大小
+    client_builder()
         .build()
         .map_err(Into::into)
 }
 
+/// 发送请求并记录上游请求指标
+pub trait SendObserved {
+    /// 与 `send()` 相同，额外按调用函数和目标主机记录耗时与错误数
+    async fn send_observed(self, function: &str) -> reqwest::Result<Response>;
+}
+
+impl SendObserved for RequestBuilder {
+    async fn send_observed(self, function: &str) -> reqwest::Result<Response> {
+        let (client, request) = self.build_split();
+        let request = request?;
+        let host = request.url().host_str().unwrap_or("unknown").to_string();
+
+        let started = Instant::now();
+        let result = client.execute(request).await;
+        let elapsed = started.elapsed().as_secs_f64();
+
+        metrics::observe_upstream(
+            function,
+            &host,
+            elapsed,
+            result.as_ref().map(|resp| resp.status()).map_err(|_| ()),
+        );
+        match &result {
+            Ok(resp) => debug!(function, host = %host, status = resp.status().as_u16(), elapsed, "upstream request"),
+            Err(e) => warn!(function, host = %host, elapsed, error = %e, "upstream request failed"),
+        }
+        result
+    }
+}
+
 /// 从HeaderMap中获取指定名称的header值
 pub fn header_str(headers: &HeaderMap, name: &str) -> Option<String> {
     headers
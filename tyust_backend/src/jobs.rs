@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use tracing::{info, warn};
+
+use crate::{
+    api_types::Score,
+    auth,
+    avatar::{self, AVATAR_KEY_PREFIX},
+    cache::{self, CacheKey, CacheKind},
+    config, db,
+    events::{self, StudentEvent},
+    handlers, storage,
+    tyust_api::tyust_get_scores,
+};
+
+/// 资料在这段时间内更新过的学生不参与头像清理，避免删除刚上传、尚未写入数据库的头像
+const AVATAR_GC_GRACE_HOURS: i64 = 1;
+
+/// 清理过期的上游认证缓存
+pub async fn cleanup_auth_cache() -> Result<String> {
+    let db_pool = db::get_db_pool().await;
+    let removed = db::cleanup_expired_auth_cache(db_pool).await?;
+    Ok(format!("removed {} expired auth cache entries", removed))
+}
+
+/// 清理不再使用的头像文件
+///
+/// 每个学生目录下只保留当前头像及其缩略图；旧版本的共享头像文件没有学生引用时删除。
+pub async fn collect_avatar_garbage() -> Result<String> {
+    let db_pool = db::get_db_pool().await;
+    let updated_before = (chrono::Utc::now() - chrono::Duration::hours(AVATAR_GC_GRACE_HOURS)).naive_utc();
+
+    let mut removed = 0;
+    for (student_id, key) in db::list_avatar_owners(db_pool, updated_before).await? {
+        if let Some(hash) = avatar::stored_hash(&student_id, &key) {
+            removed += avatar::collect_garbage(&student_id, hash).await;
+        }
+    }
+
+    let storage = storage::get();
+    for key in storage.list(AVATAR_KEY_PREFIX).await? {
+        if !avatar::is_legacy_avatar(&key) || db::count_users_with_avatar(db_pool, &key).await? > 0 {
+            continue;
+        }
+        match storage.delete(&key).await {
+            Ok(()) => removed += 1,
+            Err(e) => warn!(key = %key, error = %e, "Failed to remove legacy avatar"),
+        }
+    }
+    Ok(format!("removed {} unused avatar files", removed))
+}
+
+/// 为登录状态有效的学生拉取成绩，与上一次的结果比较找出新出的成绩
+pub async fn poll_scores() -> Result<String> {
+    let db_pool = db::get_db_pool().await;
+    let students = db::list_students_with_live_auth_cache(db_pool).await?;
+    let concurrency = config::get().scheduler.score_polling_concurrency;
+
+    let results: Vec<(String, Result<Vec<Score>>)> = futures::stream::iter(students)
+        .map(|student_id| async move {
+            let result = poll_student_scores(&student_id).await;
+            (student_id, result)
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    let (mut failed, mut notified, mut new_scores) = (0, 0, 0);
+    for (student_id, result) in &results {
+        match result {
+            Ok(scores) if !scores.is_empty() => {
+                notified += 1;
+                new_scores += scores.len();
+            }
+            Ok(_) => {}
+            Err(e) => {
+                failed += 1;
+                warn!(student_id = %student_id, error = %format!("{:#}", e), "Failed to poll scores");
+            }
+        }
+    }
+    Ok(format!(
+        "polled {} students ({} failed), {} new scores for {} students",
+        results.len(),
+        failed,
+        new_scores,
+        notified
+    ))
+}
+
+/// 拉取一个学生的成绩并更新快照和缓存，返回新出（或有变化）的成绩
+///
+/// 第一次拉取时只保存快照，不把已有成绩当作新成绩。
+async fn poll_student_scores(student_id: &str) -> Result<Vec<Score>> {
+    let Some(auth_cache) = auth::load_auth_cache(student_id)
+        .await?
+        .filter(auth::is_auth_cache_valid)
+    else {
+        return Ok(Vec::new());
+    };
+
+    let scores: Vec<Score> = tyust_get_scores(
+        &auth_cache.jwglxt_jsession,
+        &auth_cache.access_token,
+        &auth_cache.route,
+    )
+    .await?
+    .into_iter()
+    .map(Score::from)
+    .collect();
+    cache::set(&CacheKey::new(CacheKind::Scores, student_id), &scores).await;
+
+    let db_pool = db::get_db_pool().await;
+    let previous: Option<Vec<Score>> = db::get_score_snapshot(db_pool, student_id)
+        .await?
+        .and_then(|snapshot| serde_json::from_value(snapshot).ok());
+    if previous.as_ref() == Some(&scores) {
+        return Ok(Vec::new());
+    }
+    db::save_score_snapshot(db_pool, student_id, &serde_json::to_value(&scores)?).await?;
+
+    let new_scores: Vec<Score> = match previous {
+        Some(previous) => scores.into_iter().filter(|score| !previous.contains(score)).collect(),
+        None => Vec::new(),
+    };
+    if !new_scores.is_empty() {
+        info!(student_id = %student_id, count = new_scores.len(), "New scores found");
+        events::publish_to(student_id, &StudentEvent::NewScores { scores: new_scores.clone() }).await?;
+    }
+    Ok(new_scores)
+}
+
+/// 到开学日期时切换到管理员预先设置的下一学期
+pub async fn roll_over_term() -> Result<String> {
+    let db_pool = db::get_db_pool().await;
+    let today = chrono::Local::now().date_naive().format("%Y-%m-%d").to_string();
+    match db::activate_due_semester(db_pool, &today).await? {
+        Some(semester) => {
+            handlers::init_semester_config().await.map_err(|e| anyhow!(e))?;
+            info!(semester = %semester.semester_name, start_date = %semester.semester_start_date, "Switched to scheduled semester");
+            events::broadcast(&StudentEvent::ScheduleChanged {
+                semester_name: semester.semester_name.clone(),
+                semester_start_date: semester.semester_start_date.clone(),
+            })
+            .await?;
+            Ok(format!("switched to {}", semester.semester_name))
+        }
+        None => Ok("no scheduled semester due".to_string()),
+    }
+}
+
+/// 删除超过保留时间的推送事件，超过保留时间后断线重连不再补发
+pub async fn cleanup_events() -> Result<String> {
+    let db_pool = db::get_db_pool().await;
+    let retention = chrono::Duration::hours(config::get().events.retention_hours);
+    let removed = db::delete_student_events_before(db_pool, (chrono::Utc::now() - retention).naive_utc()).await?;
+    Ok(format!("removed {} expired events", removed))
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::{Error, ErrorKind},
+    Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
+};
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::config::{self, JwtKeySet};
+
+/// 学生 token 的密钥
+static STUDENT_KEYS: Lazy<KeyRing> = Lazy::new(|| KeyRing::new(&config::get().jwt.student));
+
+/// 管理员 token 的密钥
+static ADMIN_KEYS: Lazy<KeyRing> = Lazy::new(|| KeyRing::new(&config::get().jwt.admin));
+
+/// 一组 HS256 密钥
+///
+/// 使用 `signing_kid` 对应的密钥签发 token，并在 header 中写入 kid；
+/// 验证时按 kid 选择密钥，因此轮换期间旧密钥签发的 token 仍然有效。
+/// 没有 kid 的旧 token 会依次尝试所有密钥。
+pub struct KeyRing {
+    signing_kid: String,
+    encoding_key: EncodingKey,
+    decoding_keys: HashMap<String, DecodingKey>,
+}
+
+impl KeyRing {
+    fn new(config: &JwtKeySet) -> Self {
+        let keys = config.parsed_keys();
+        let signing_secret = keys
+            .iter()
+            .find(|(kid, _)| *kid == config.signing_kid)
+            .map(|(_, secret)| *secret)
+            .expect("signing key validated at startup");
+
+        Self {
+            signing_kid: config.signing_kid.clone(),
+            encoding_key: EncodingKey::from_secret(signing_secret.as_bytes()),
+            decoding_keys: keys
+                .into_iter()
+                .map(|(kid, secret)| (kid.to_string(), DecodingKey::from_secret(secret.as_bytes())))
+                .collect(),
+        }
+    }
+
+    /// 签发 token
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, Error> {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(self.signing_kid.clone());
+        encode(&header, claims, &self.encoding_key)
+    }
+
+    /// 验证 token
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> Result<TokenData<T>, Error> {
+        let validation = Validation::new(Algorithm::HS256);
+
+        match decode_header(token)?.kid {
+            Some(kid) => {
+                let key = self
+                    .decoding_keys
+                    .get(&kid)
+                    .ok_or_else(|| Error::from(ErrorKind::InvalidSignature))?;
+                decode(token, key, &validation)
+            }
+            None => {
+                let mut last_error = Error::from(ErrorKind::InvalidSignature);
+                for key in self.decoding_keys.values() {
+                    match decode(token, key, &validation) {
+                        Ok(data) => return Ok(data),
+                        Err(e) => last_error = e,
+                    }
+                }
+                Err(last_error)
+            }
+        }
+    }
+}
+
+/// 学生 token 密钥
+pub fn student() -> &'static KeyRing {
+    &STUDENT_KEYS
+}
+
+/// 管理员 token 密钥
+pub fn admin() -> &'static KeyRing {
+    &ADMIN_KEYS
+}
@@ -1,79 +1,197 @@
 use axum::{
-    Router, middleware,
+    Router, extract::DefaultBodyLimit, middleware,
     routing::{get, post},
 };
-use tower_http::{cors::CorsLayer, services::ServeDir};
+use std::{future::IntoFuture, net::SocketAddr};
+use tower_http::services::ServeDir;
+use tracing::{info, warn};
 
 mod api_types;
+mod audit;
 mod auth;
+mod avatar;
+mod cache;
+mod config;
+mod credential_crypto;
 mod db;
 mod de_crypto;
 mod entity;
+mod error;
+mod events;
+mod extract;
 mod handlers;
+mod health;
 mod http_helper;
+mod jobs;
+mod jwt_keys;
+mod metrics;
+mod notifications;
+mod openapi;
+mod redis_cache;
+mod rate_limit;
+mod scheduler;
+mod storage;
+mod telemetry;
 mod tyust_api;
+mod wechat;
 mod admin_handlers;
 
-use auth::{auth_middleware, cleanup_expired_auth_cache};
-use handlers::{get_schedule, get_user_info, init_semester_config, login, logout, get_courses, get_scores, get_raw_scores, init_login, get_login_code, get_semester_config, update_avatar};
-use admin_handlers::{admin_login, get_students, get_semester, set_semester, get_statistics, update_admin_password, update_admin_username, admin_auth_middleware};
+use auth::auth_middleware;
+use handlers::{get_schedule, get_user_info, init_semester_config, login, wechat_login, unbind_wechat, logout, list_sessions, revoke_session, refresh_token, get_courses, get_scores, get_raw_scores, init_login, get_login_code, get_semester_config, update_avatar};
+use admin_handlers::{admin_login, get_students, get_semester, set_semester, get_statistics, get_login_trend, get_login_peak_hours, update_admin_password, update_admin_username, admin_auth_middleware, list_admins, create_admin, set_admin_status, reset_admin_password, assign_admin_role, get_audit_logs, list_jobs, run_job, publish_announcement, admin_refresh, admin_logout, admin_logout_all, force_logout_student, set_student_status};
+use api_types::AdminPermission;
+use rate_limit::{login_rate_limit, LoginKind};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // 加载 .env 文件
     dotenv::from_filename(".env").ok();
     
+    // 加载配置（配置文件 + 环境变量覆盖），配置有误时直接退出
+    let config_file = config::init()?;
+    let app_config = config::get();
+    
+    // 初始化日志（需在加载配置之后）
+    telemetry::init_tracing(&app_config.log);
+    match &config_file {
+        Some(path) => info!(path = %path.display(), "Configuration loaded"),
+        None => info!("No configuration file found, using defaults and environment overrides"),
+    }
+    
     // 初始化数据库连接池
     db::init_db_pool().await?;
     let db_pool = db::get_db_pool().await;
-    println!("✅ Database initialized successfully");
+    info!("Database initialized");
+    
+    // 子命令：`tyust migrate` 应用迁移后退出，`tyust migrate status` 查看迁移状态，
+    // `tyust migrate avatars` 将本地头像上传到配置的文件存储；
+    // 迁移数据库只需要数据库连接，不依赖缓存和文件存储
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => {}
+        ["migrate"] => {
+            apply_migrations(db_pool).await?;
+            return Ok(());
+        }
+        ["migrate", "status"] => {
+            for migration in db::migration_status(db_pool).await? {
+                match migration.applied_at {
+                    Some(applied_at) => info!(version = migration.version, description = %migration.description, %applied_at, "Applied"),
+                    None => info!(version = migration.version, description = %migration.description, "Pending"),
+                }
+            }
+            return Ok(());
+        }
+        ["migrate", "avatars"] => {
+            storage::init(&app_config.storage, &app_config.server.static_dir)?;
+            let uploaded = avatar::upload_local_avatars(&app_config.server.static_dir).await?;
+            info!(uploaded, backend = ?app_config.storage.backend, "Uploaded local avatars to storage");
+            return Ok(());
+        }
+        _ => anyhow::bail!("unknown command: {}\nusage: tyust [migrate [status|avatars]]", args.join(" ")),
+    }
+    
+    // 初始化缓存
+    cache::init(&app_config.cache).await?;
+    info!(backend = ?app_config.cache.backend, "Cache initialized");
     
-    // 初始化数据库表
-    db::init_tables(db_pool).await?;
-    println!("✅ Database tables initialized successfully");
+    // 初始化文件存储
+    storage::init(&app_config.storage, &app_config.server.static_dir)?;
+    info!(backend = ?app_config.storage.backend, "Storage initialized");
     
-    // 清理过期的认证缓存
-    if let Ok(count) = db::cleanup_expired_auth_cache(db_pool).await {
-        if count > 0 {
-            println!("🧹 Cleaned up {} expired auth cache entries", count);
+    // 应用数据库迁移，关闭自动迁移时只检查是否有未应用的迁移
+    if app_config.database.auto_migrate {
+        apply_migrations(db_pool).await?;
+    } else {
+        let pending = db::migration_status(db_pool)
+            .await?
+            .into_iter()
+            .filter(|migration| migration.applied_at.is_none())
+            .count();
+        if pending > 0 {
+            anyhow::bail!("{} database migration(s) pending, run `tyust migrate` first", pending);
         }
     }
+    db::ensure_default_admin(db_pool).await?;
+    
+    // 加密旧的明文上游凭证，并迁移到当前主密钥
+    let migration = db::encrypt_auth_cache(db_pool).await?;
+    if migration.encrypted > 0 || migration.rewrapped > 0 || migration.dropped > 0 {
+        info!(
+            encrypted = migration.encrypted,
+            rewrapped = migration.rewrapped,
+            dropped = migration.dropped,
+            "Migrated auth cache credentials"
+        );
+    }
     
     // 初始化学期配置
     if let Err(e) = init_semester_config().await {
-        eprintln!("⚠️  Warning: Failed to load semester config: {}", e);
-        println!("📝 Using default semester configuration");
+        warn!(error = %e, "Failed to load semester config, using default semester configuration");
     } else {
-        println!("✅ Semester configuration loaded successfully");
+        info!("Semester configuration loaded");
     }
     
+    // 登记并启动定时任务（过期缓存清理、头像清理、成绩轮询、学期切换）
+    scheduler::register_jobs().await?;
+    scheduler::spawn();
+    
+    // 监听其他实例发布的推送事件
+    events::spawn_listener();
+    
     // 创建需要认证的路由
     let protected_routes = Router::new()
         .route("/schedule", get(get_schedule))
         .route("/user/info", get(get_user_info))
         .route("/auth/logout", post(logout))
+        .route("/auth/sessions", get(list_sessions))
+        .route("/auth/wechat/unbind", post(unbind_wechat))
+        .route("/auth/sessions/{session_id}/revoke", post(revoke_session))
         .route("/scores", get(get_scores))
         .route("/raw-scores", get(get_raw_scores))
-        .route("/update-avatar", post(update_avatar))
+        .route("/events", get(events::stream_events))
+        .route("/events/ws", get(events::events_websocket))
+        .route("/wechat/subscriptions", get(notifications::list_subscriptions).post(notifications::report_subscriptions))
+        .route("/update-avatar", post(update_avatar).layer(DefaultBodyLimit::max(avatar_body_limit(app_config))))
         .layer(middleware::from_fn(auth_middleware));
 
-    // 创建管理员路由
+    // 创建管理员路由（每个路由声明所需的权限）
+    let require = |permission: AdminPermission| {
+        middleware::from_fn_with_state(permission, admin_auth_middleware)
+    };
     let admin_routes = Router::new()
-        .route("/admin/students", get(get_students))
-        .route("/admin/semester", get(get_semester))
-        .route("/admin/semester", post(set_semester))
-        .route("/admin/statistics", get(get_statistics))
-        .route("/admin/password", post(update_admin_password))
-        .route("/admin/username", post(update_admin_username))
-        .layer(middleware::from_fn(admin_auth_middleware));
+        .route("/admin/students", get(get_students).route_layer(require(AdminPermission::ViewStudents)))
+        .route("/admin/students/{student_id}/logout", post(force_logout_student).route_layer(require(AdminPermission::ManageStudents)))
+        .route("/admin/students/{student_id}/status", post(set_student_status).route_layer(require(AdminPermission::ManageStudents)))
+        .route("/admin/semester", get(get_semester).route_layer(require(AdminPermission::ViewSemester)))
+        .route("/admin/semester", post(set_semester).route_layer(require(AdminPermission::ManageSemester)))
+        .route("/admin/statistics", get(get_statistics).route_layer(require(AdminPermission::ViewStatistics)))
+        .route("/admin/analytics/logins", get(get_login_trend).route_layer(require(AdminPermission::ViewStatistics)))
+        .route("/admin/analytics/peak-hours", get(get_login_peak_hours).route_layer(require(AdminPermission::ViewStatistics)))
+        .route("/admin/password", post(update_admin_password).route_layer(require(AdminPermission::ManageOwnAccount)))
+        .route("/admin/username", post(update_admin_username).route_layer(require(AdminPermission::ManageOwnAccount)))
+        .route("/admin/logout", post(admin_logout).route_layer(require(AdminPermission::ManageOwnAccount)))
+        .route("/admin/logout-all", post(admin_logout_all).route_layer(require(AdminPermission::ManageOwnAccount)))
+        .route("/admin/admins", get(list_admins).route_layer(require(AdminPermission::ManageAdmins)))
+        .route("/admin/admins", post(create_admin).route_layer(require(AdminPermission::ManageAdmins)))
+        .route("/admin/admins/{id}/status", post(set_admin_status).route_layer(require(AdminPermission::ManageAdmins)))
+        .route("/admin/admins/{id}/password", post(reset_admin_password).route_layer(require(AdminPermission::ManageAdmins)))
+        .route("/admin/admins/{id}/role", post(assign_admin_role).route_layer(require(AdminPermission::ManageAdmins)))
+        .route("/admin/audit", get(get_audit_logs).route_layer(require(AdminPermission::ViewAuditLog)))
+        .route("/admin/jobs", get(list_jobs).route_layer(require(AdminPermission::ManageJobs)))
+        .route("/admin/jobs/{name}/run", post(run_job).route_layer(require(AdminPermission::ManageJobs)))
+        .route("/admin/announcements", post(publish_announcement).route_layer(require(AdminPermission::PublishAnnouncements)));
 
     // 创建管理员公开路由（不需要认证）
     let admin_public_routes = Router::new()
-        .route("/admin/login", post(admin_login));
+        .route("/admin/login", post(admin_login).route_layer(middleware::from_fn_with_state(LoginKind::Admin, login_rate_limit)))
+        .route("/admin/refresh", post(admin_refresh));
 
     // 创建API路由组
     let api_routes = Router::new()
-        .route("/auth/login", post(login))
+        .route("/auth/login", post(login).route_layer(middleware::from_fn_with_state(LoginKind::Student, login_rate_limit)))
+        .route("/auth/refresh", post(refresh_token))
+        .route("/auth/wechat-login", post(wechat_login).route_layer(middleware::from_fn_with_state(LoginKind::Student, login_rate_limit)))
         .route("/login-init", get(init_login))
         .route("/login-code", get(get_login_code))
         .route("/courses", get(get_courses)) // 不使用中间件，自己处理认证
@@ -83,40 +201,66 @@ async fn main() -> anyhow::Result<()> {
         .merge(admin_public_routes);
 
     // 创建静态文件服务路由
-    let static_files_service = ServeDir::new("static");
+    let static_files_service = ServeDir::new(&app_config.server.static_dir);
     
     // 创建应用
     let app = Router::new()
         .nest("/api", api_routes)
         .nest_service("/static", static_files_service)
-        .layer(CorsLayer::permissive()) // 允许所有CORS请求
-        .into_make_service();
+        .route("/metrics", get(metrics::metrics_handler))
+        .merge(openapi::routes())
+        .layer(middleware::from_fn(metrics::track_requests))
+        .layer(middleware::from_fn(error::locale_middleware));
+    let app = telemetry::with_request_tracing(app)
+        .merge(health::routes())
+        .layer(app_config.server.cors_layer())
+        .into_make_service_with_connect_info::<SocketAddr>();
 
-    println!("🚀 Server starting on http://0.0.0.0:3000");
-    println!("📚 Available endpoints:");
-    println!("  POST /api/auth/login - 用户登录");
-    println!("  GET  /api/login-init - 初始化验证码登录");
-    println!("  GET  /api/login-code - 获取验证码图片");
-    println!("  POST /api/login-verify - 验证码登录");
-    println!("  GET  /api/schedule - 获取课表 (需要认证)");
-    println!("  GET  /api/user/info - 获取用户信息 (需要认证)");
-    println!("  POST /api/auth/logout - 用户登出 (需要认证)");
-    println!("  GET  /api/courses - 获取课程列表 (需要认证)");
-    println!("  GET  /api/scores - 获取有效成绩 (需要认证)");
-    println!("  GET  /api/raw-scores - 获取原始成绩 (需要认证)");
-    println!("  POST /api/update-avatar - 更新用户头像 (需要认证)");
-    println!("  GET  /static/avatars/* - 用户头像文件");
-    println!("");
-    println!("  管理员接口:");
-    println!("  POST /api/admin/login - 管理员登录");
-    println!("  GET  /api/admin/students - 获取学生列表 (需要认证)");
-    println!("  GET  /api/admin/semester - 获取学期配置 (需要认证)");
-    println!("  POST /api/admin/semester - 设置学期配置 (需要认证)");
-    println!("  GET  /api/admin/statistics - 获取统计信息 (需要认证)");
-    println!("  POST /api/admin/password - 修改管理员密码 (需要认证)");
-    println!("  POST /api/admin/username - 修改管理员用户名 (需要认证)");
+    let bind_addr = app_config.server.socket_addr();
+    info!("Server starting on http://{}", bind_addr);
+    info!("API documentation: http://{}{} (OpenAPI spec at {})", bind_addr, openapi::DOCS_PATH, openapi::SPEC_PATH);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
-    axum::serve(listener, app).await?;
+    let listener = tokio::net::TcpListener::bind(bind_addr).await?;
+    
+    // 收到停止信号后不再接受新连接，最多等待 server.shutdown_grace_secs 让进行中的请求完成
+    let (signal_tx, signal_rx) = tokio::sync::oneshot::channel();
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            health::shutdown_signal().await;
+            let _ = signal_tx.send(());
+        })
+        .into_future();
+    tokio::pin!(server);
+    tokio::select! {
+        result = &mut server => result?,
+        _ = signal_rx => {
+            let grace_period = app_config.server.shutdown_grace_period();
+            info!(grace_secs = grace_period.as_secs(), "Shutting down, waiting for in-flight requests");
+            match tokio::time::timeout(grace_period, &mut server).await {
+                Ok(result) => result?,
+                Err(_) => warn!("Grace period elapsed, dropping remaining connections"),
+            }
+        }
+    }
+    
+    db_pool.close().await;
+    info!("Server stopped");
     Ok(())
-}
\ No newline at end of file
+}
+
+/// 应用尚未应用的数据库迁移
+async fn apply_migrations(db_pool: &sqlx::PgPool) -> anyhow::Result<()> {
+    let applied = db::run_migrations(db_pool).await?;
+    if applied.is_empty() {
+        info!("Database schema is up to date");
+    }
+    for migration in applied {
+        info!(version = migration.version, description = %migration.description, "Applied database migration");
+    }
+    Ok(())
+}
+
+/// 头像上传接口的请求体上限：base64 编码后约为原始大小的 4/3，另留出 JSON 或 multipart 的开销
+fn avatar_body_limit(app_config: &config::AppConfig) -> usize {
+    app_config.avatar.max_upload_bytes / 3 * 4 + 64 * 1024
+}
@@ -0,0 +1,196 @@
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use lazy_static::lazy_static;
+use tracing::error;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::db;
+
+/// HTTP 请求耗时的直方图分桶（秒）
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+lazy_static! {
+    static ref REGISTRY: Registry = Registry::new_custom(Some("tyust".to_string()), None)
+        .expect("invalid metrics registry");
+
+    static ref HTTP_REQUESTS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("http_requests_total", "按路由统计的 HTTP 请求数"),
+        &["method", "route", "status"],
+    ));
+    static ref HTTP_LATENCY: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new("http_request_duration_seconds", "按路由统计的 HTTP 请求耗时")
+            .buckets(LATENCY_BUCKETS.to_vec()),
+        &["method", "route"],
+    ));
+
+    static ref UPSTREAM_LATENCY: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new("upstream_request_duration_seconds", "上游教务系统请求耗时")
+            .buckets(LATENCY_BUCKETS.to_vec()),
+        &["function", "host"],
+    ));
+    static ref UPSTREAM_ERRORS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("upstream_errors_total", "上游教务系统请求错误数（kind=transport 为网络错误，kind=status 为 5xx 响应）"),
+        &["function", "host", "kind"],
+    ));
+
+    static ref LOGINS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("logins_total", "登录次数"),
+        &["kind", "result"],
+    ));
+    static ref LOGIN_RATE_LIMITED: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("login_rate_limited_total", "被限流拒绝的登录请求数（scope 为 ip、account 或 lockout）"),
+        &["kind", "scope"],
+    ));
+
+    static ref CACHE_LOOKUPS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("cache_lookups_total", "缓存查询次数"),
+        &["kind", "result"],
+    ));
+
+    static ref JOB_RUNS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("scheduled_job_runs_total", "定时任务执行次数"),
+        &["job", "status"],
+    ));
+    static ref JOB_DURATION: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new("scheduled_job_duration_seconds", "定时任务执行耗时")
+            .buckets(vec![0.1, 0.5, 1.0, 5.0, 15.0, 60.0, 300.0, 900.0]),
+        &["job"],
+    ));
+
+    static ref SUBSCRIBE_MESSAGES: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("wechat_subscribe_messages_total", "微信订阅消息发送次数"),
+        &["template", "status"],
+    ));
+
+    static ref DB_POOL_CONNECTIONS: IntGaugeVec = register(IntGaugeVec::new(
+        Opts::new("db_pool_connections", "数据库连接池连接数"),
+        &["state"],
+    ));
+    static ref DB_POOL_MAX_CONNECTIONS: IntGauge = register(IntGauge::new(
+        "db_pool_max_connections",
+        "数据库连接池最大连接数",
+    ));
+    static ref AUTH_CACHE_ENTRIES: IntGauge = register(IntGauge::new(
+        "auth_cache_entries",
+        "未过期的 user_auth_cache 条目数",
+    ));
+}
+
+fn register<M>(metric: prometheus::Result<M>) -> M
+where
+    M: prometheus::core::Collector + Clone + 'static,
+{
+    let metric = metric.expect("invalid metric definition");
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .expect("duplicate metric registration");
+    metric
+}
+
+/// 记录每个请求的路由、状态码和耗时
+///
+/// 使用匹配到的路由模板（如 `/api/admin/admins/{id}/status`）作为标签，避免路径参数导致标签爆炸。
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().to_string();
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = started.elapsed().as_secs_f64();
+
+    HTTP_REQUESTS
+        .with_label_values(&[method.as_str(), route.as_str(), response.status().as_str()])
+        .inc();
+    HTTP_LATENCY
+        .with_label_values(&[method.as_str(), route.as_str()])
+        .observe(elapsed);
+
+    response
+}
+
+/// 记录一次上游请求的耗时和结果
+pub fn observe_upstream(function: &str, host: &str, elapsed: f64, result: Result<StatusCode, ()>) {
+    UPSTREAM_LATENCY
+        .with_label_values(&[function, host])
+        .observe(elapsed);
+
+    let kind = match result {
+        Err(()) => Some("transport"),
+        Ok(status) if status.is_server_error() => Some("status"),
+        Ok(_) => None,
+    };
+    if let Some(kind) = kind {
+        UPSTREAM_ERRORS.with_label_values(&[function, host, kind]).inc();
+    }
+}
+
+/// 记录一次登录结果，kind 为 student 或 admin
+pub fn record_login(kind: &str, success: bool) {
+    let result = if success { "success" } else { "failure" };
+    LOGINS.with_label_values(&[kind, result]).inc();
+}
+
+/// 记录一次被限流拒绝的登录请求
+pub fn record_rate_limited(kind: &str, scope: &str) {
+    LOGIN_RATE_LIMITED.with_label_values(&[kind, scope]).inc();
+}
+
+/// 记录一次缓存查询是否命中
+pub fn record_cache_lookup(kind: &str, hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    CACHE_LOOKUPS.with_label_values(&[kind, result]).inc();
+}
+
+/// 记录一次定时任务执行，status 为 succeeded 或 failed
+pub fn record_job_run(job: &str, status: &str, elapsed: f64) {
+    JOB_RUNS.with_label_values(&[job, status]).inc();
+    JOB_DURATION.with_label_values(&[job]).observe(elapsed);
+}
+
+/// 记录一次订阅消息发送，status 为 sent、unsubscribed 或 failed
+pub fn record_subscribe_message(template: &str, status: &str) {
+    SUBSCRIBE_MESSAGES.with_label_values(&[template, status]).inc();
+}
+
+/// Prometheus 抓取接口
+///
+/// 连接池和认证缓存这类状态型指标在抓取时实时采样。
+pub async fn metrics_handler() -> Response {
+    let db_pool = db::get_db_pool().await;
+
+    let size = db_pool.size() as i64;
+    let idle = db_pool.num_idle() as i64;
+    DB_POOL_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+    DB_POOL_CONNECTIONS.with_label_values(&["in_use"]).set(size - idle);
+    DB_POOL_MAX_CONNECTIONS.set(db_pool.options().get_max_connections() as i64);
+
+    match db::count_live_auth_cache(db_pool).await {
+        Ok(count) => AUTH_CACHE_ENTRIES.set(count),
+        Err(e) => error!(error = %e, "Failed to count auth cache entries"),
+    }
+
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer) {
+        error!(error = %e, "Failed to encode metrics");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    (
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        buffer,
+    )
+        .into_response()
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use axum::{extract::Extension, Json};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    api_types::{ApiResponse, EmptyResponse},
+    auth::StudentSession,
+    config::{self, SubscribeTemplate},
+    db,
+    error::ApiError,
+    events::StudentEvent,
+    extract::ApiJson,
+    metrics,
+    wechat::{self, WechatError},
+};
+
+/// 事件发布后向授权了对应模板的学生发送微信订阅消息
+///
+/// 在后台执行，发送失败只记录日志；student_id 为空时发给所有授权了该模板的学生。
+pub fn notify(student_id: Option<&str>, event: &StudentEvent) {
+    let config = &config::get().wechat;
+    if !config.enabled || config.templates.get(event.event_type()).is_none() {
+        return;
+    }
+    let student_id = student_id.map(str::to_string);
+    let event = event.clone();
+    tokio::spawn(async move {
+        if let Err(e) = send_to_subscribers(student_id.as_deref(), &event).await {
+            error!(event_type = event.event_type(), error = %e, "Failed to send subscribe messages");
+        }
+    });
+}
+
+async fn send_to_subscribers(student_id: Option<&str>, event: &StudentEvent) -> Result<()> {
+    let config = &config::get().wechat;
+    let name = event.event_type();
+    let Some(template) = config.templates.get(name) else {
+        return Ok(());
+    };
+    let fields = render_fields(template, event);
+
+    let db_pool = db::get_db_pool().await;
+    let students = db::list_wechat_subscribers(db_pool, name, student_id).await?;
+    if students.is_empty() {
+        return Ok(());
+    }
+    let sent = futures::stream::iter(students)
+        .map(|student_id| {
+            let fields = &fields;
+            async move { send_to_student(&student_id, name, template, fields).await }
+        })
+        .buffer_unordered(config.send_concurrency)
+        .filter(|sent| std::future::ready(*sent))
+        .count()
+        .await;
+    info!(event_type = name, sent, "Sent subscribe messages");
+    Ok(())
+}
+
+/// 扣减一次授权并发送，返回是否发送成功
+async fn send_to_student(student_id: &str, name: &str, template: &SubscribeTemplate, fields: &[(String, String)]) -> bool {
+    let db_pool = db::get_db_pool().await;
+    let openid = match db::take_wechat_subscription(db_pool, student_id, name).await {
+        Ok(Some(openid)) => openid,
+        Ok(None) => return false,
+        Err(e) => {
+            warn!(student_id = %student_id, error = %e, "Failed to take subscription");
+            return false;
+        }
+    };
+
+    let result = wechat::send_subscribe_message(&openid, &template.template_id, &template.page, fields).await;
+    let status = match &result {
+        Ok(()) => "sent",
+        Err(e) if e.downcast_ref::<WechatError>().is_some_and(WechatError::is_unsubscribed) => {
+            // 用户已取消订阅，不再尝试直到重新授权
+            if let Err(e) = db::clear_wechat_subscription(db_pool, student_id, name).await {
+                warn!(student_id = %student_id, error = %e, "Failed to clear subscription");
+            }
+            "unsubscribed"
+        }
+        Err(e) => {
+            // 消息未送达，授权次数未被微信消耗
+            warn!(student_id = %student_id, template = name, error = %format!("{:#}", e), "Failed to send subscribe message");
+            if let Err(e) = db::refund_wechat_subscription(db_pool, student_id, name).await {
+                warn!(student_id = %student_id, error = %e, "Failed to refund subscription");
+            }
+            "failed"
+        }
+    };
+    metrics::record_subscribe_message(name, status);
+    result.is_ok()
+}
+
+/// 按模板配置生成消息内容，替换占位符并按字段类型截断
+fn render_fields(template: &SubscribeTemplate, event: &StudentEvent) -> Vec<(String, String)> {
+    let values = placeholder_values(event);
+    template
+        .fields
+        .iter()
+        .map(|(field, pattern)| {
+            let mut value = pattern.clone();
+            for (name, replacement) in &values {
+                value = value.replace(&format!("{{{}}}", name), replacement);
+            }
+            (field.clone(), fit_field(field, &value))
+        })
+        .collect()
+}
+
+/// 事件可用的占位符（与 `SubscribeTemplates::entries` 中列出的一致）
+fn placeholder_values(event: &StudentEvent) -> Vec<(&'static str, String)> {
+    let mut values = vec![("time", chrono::Local::now().format("%Y-%m-%d %H:%M").to_string())];
+    match event {
+        StudentEvent::NewScores { scores } => {
+            let first = scores.first();
+            values.push(("count", scores.len().to_string()));
+            values.push(("course", first.map(|score| score.course.clone()).unwrap_or_default()));
+            values.push(("score", first.map(|score| score.score.clone()).unwrap_or_default()));
+            values.push(("semester", first.map(|score| score.semester.clone()).unwrap_or_default()));
+            let courses: Vec<&str> = scores.iter().map(|score| score.course.as_str()).collect();
+            values.push(("courses", courses.join("、")));
+        }
+        StudentEvent::Announcement { title, content } => {
+            values.push(("title", title.clone()));
+            values.push(("content", content.clone()));
+        }
+        StudentEvent::ScheduleChanged {
+            semester_name,
+            semester_start_date,
+        } => {
+            values.push(("semester_name", semester_name.clone()));
+            values.push(("semester_start_date", semester_start_date.clone()));
+        }
+    }
+    values
+}
+
+/// 微信按字段类型（字段名去掉数字后缀，如 thing1 -> thing）限制内容长度，超出时发送失败
+fn fit_field(field: &str, value: &str) -> String {
+    let value = value.trim();
+    if value.is_empty() {
+        // 字段内容不能为空
+        return "-".to_string();
+    }
+    let limit = match field.trim_end_matches(|c: char| c.is_ascii_digit()) {
+        "thing" => 20,
+        "name" => 10,
+        "phrase" | "short_thing" => 5,
+        "character_string" | "number" | "letter" => 32,
+        _ => return value.to_string(),
+    };
+    if value.chars().count() <= limit {
+        return value.to_string();
+    }
+    let mut truncated: String = value.chars().take(limit - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// 订阅消息模板及当前学生的授权情况
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SubscriptionInfo {
+    pub name: String,        // 事件类型：new_scores、announcement 或 schedule_changed
+    pub template_id: String, // 调用 wx.requestSubscribeMessage 时使用的模板ID
+    pub remaining: i32,      // 还可以发送的消息数，每授权一次加一
+    pub updated_at: Option<chrono::NaiveDateTime>,   // 最近一次上报授权结果的时间
+    pub last_sent_at: Option<chrono::NaiveDateTime>, // 最近一次发送消息的时间
+}
+
+async fn subscription_infos(student_id: &str) -> Result<Vec<SubscriptionInfo>, ApiError> {
+    let config = &config::get().wechat;
+    if !config.enabled {
+        return Ok(Vec::new());
+    }
+    let db_pool = db::get_db_pool().await;
+    let subscriptions = db::list_wechat_subscriptions(db_pool, student_id).await.map_err(|e| {
+        error!(student_id = %student_id, error = %e, "Failed to list subscriptions");
+        ApiError::DatabaseError
+    })?;
+
+    Ok(config
+        .templates
+        .entries()
+        .into_iter()
+        .filter(|(_, template, _)| template.is_configured())
+        .map(|(name, template, _)| {
+            let subscription = subscriptions.iter().find(|subscription| subscription.template == name);
+            SubscriptionInfo {
+                name: name.to_string(),
+                template_id: template.template_id.clone(),
+                remaining: subscription.map_or(0, |subscription| subscription.remaining),
+                updated_at: subscription.map(|subscription| subscription.updated_at),
+                last_sent_at: subscription.and_then(|subscription| subscription.last_sent_at),
+            }
+        })
+        .collect())
+}
+
+/// 获取可订阅的消息模板和已授权次数
+///
+/// 未启用微信订阅消息时返回空列表。
+#[utoipa::path(
+    get,
+    path = "/api/wechat/subscriptions",
+    tag = "student",
+    responses(
+        (status = 200, description = "成功", body = ApiResponse<Vec<SubscriptionInfo>>),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
+pub async fn list_subscriptions(
+    Extension(session): Extension<StudentSession>,
+) -> Result<Json<ApiResponse<Vec<SubscriptionInfo>>>, ApiError> {
+    Ok(Json(ApiResponse::success(subscription_infos(&session.student_id).await?)))
+}
+
+/// 上报订阅消息授权结果
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReportSubscriptionRequest {
+    pub code: String,                     // wx.login 得到的 code，用于确定发送目标 openid
+    pub results: HashMap<String, String>, // wx.requestSubscribeMessage 的结果：模板ID -> accept、reject、ban 或 filter
+}
+
+/// 上报订阅消息授权结果
+///
+/// 每次 accept 增加一次可发送次数，reject、ban 和 filter 清零；未配置的模板ID会被忽略。
+#[utoipa::path(
+    post,
+    path = "/api/wechat/subscriptions",
+    tag = "student",
+    request_body = ReportSubscriptionRequest,
+    responses(
+        (status = 200, description = "成功，返回更新后的授权情况", body = ApiResponse<Vec<SubscriptionInfo>>),
+        (status = 400, description = "请求参数错误或 code 无效", body = EmptyResponse),
+        (status = 401, description = "未登录或凭证失效", body = EmptyResponse),
+        (status = 500, description = "服务器内部错误或微信接口不可用", body = EmptyResponse),
+        (status = 503, description = "未启用微信功能", body = EmptyResponse),
+    ),
+    security(("student_token" = []), ("student_token_header" = [])),
+)]
+pub async fn report_subscriptions(
+    Extension(session): Extension<StudentSession>,
+    ApiJson(params): ApiJson<ReportSubscriptionRequest>,
+) -> Result<Json<ApiResponse<Vec<SubscriptionInfo>>>, ApiError> {
+    let openid = wechat::openid_for_code(&params.code).await?;
+
+    let db_pool = db::get_db_pool().await;
+    for (name, template, _) in config::get().wechat.templates.entries() {
+        if !template.is_configured() {
+            continue;
+        }
+        let Some(result) = params.results.get(&template.template_id) else {
+            continue;
+        };
+        let accepted = result == "accept";
+        db::record_wechat_subscription(db_pool, &session.student_id, name, &openid, accepted)
+            .await
+            .map_err(|e| {
+                error!(student_id = %session.student_id, error = %e, "Failed to record subscription");
+                ApiError::DatabaseError
+            })?;
+    }
+
+    Ok(Json(ApiResponse::success(subscription_infos(&session.student_id).await?)))
+}
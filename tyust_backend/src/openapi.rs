@@ -0,0 +1,117 @@
+use axum::{routing::get, Json, Router};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+use utoipa_scalar::{Scalar, Servable};
+
+use crate::{admin_handlers, events, handlers, notifications};
+
+/// OpenAPI 文档地址
+pub const SPEC_PATH: &str = "/api/openapi.json";
+
+/// 交互式文档页面地址
+pub const DOCS_PATH: &str = "/docs";
+
+/// 接口文档，由各处理函数上的 `#[utoipa::path]` 和数据结构上的 `ToSchema` 生成，
+/// 用到的数据结构会自动加入 components
+///
+/// 新增路由时需同时加入 paths 列表，否则不会出现在文档中。
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "TYUST API",
+        description = "太原科技大学教务小程序及管理后台接口。\n\n\
+            所有 JSON 接口都返回统一的 `ApiResponse` 结构：成功时 `code` 为 0、`data` 为数据；\
+            失败时 `code` 与 HTTP 状态码相同（400 参数错误、401 未登录或凭证失效、403 无权限或账号被禁用、\
+            404 资源不存在、413 上传文件过大、415 文件格式不支持、429 请求过于频繁、500 服务器错误、503 功能未启用），\
+            `error` 为稳定的错误码（如 `invalid_token`、`upstream_unavailable`），`message` 为错误说明，`data` 为 null。\n\n\
+            错误说明的语言由 `Accept-Language` 请求头决定，支持 `zh-CN`（默认）和 `en`；客户端应根据 `error` 判断错误类型。"
+    ),
+    paths(
+        handlers::login,
+        handlers::wechat_login,
+        handlers::unbind_wechat,
+        handlers::refresh_token,
+        handlers::logout,
+        handlers::list_sessions,
+        handlers::revoke_session,
+        handlers::init_login,
+        handlers::get_login_code,
+        handlers::get_user_info,
+        handlers::update_avatar,
+        handlers::get_schedule,
+        handlers::get_courses,
+        handlers::get_semester_config,
+        handlers::get_scores,
+        handlers::get_raw_scores,
+        events::stream_events,
+        events::events_websocket,
+        notifications::list_subscriptions,
+        notifications::report_subscriptions,
+        admin_handlers::admin_login,
+        admin_handlers::admin_refresh,
+        admin_handlers::admin_logout,
+        admin_handlers::admin_logout_all,
+        admin_handlers::update_admin_password,
+        admin_handlers::update_admin_username,
+        admin_handlers::get_students,
+        admin_handlers::force_logout_student,
+        admin_handlers::set_student_status,
+        admin_handlers::get_semester,
+        admin_handlers::set_semester,
+        admin_handlers::get_statistics,
+        admin_handlers::get_login_trend,
+        admin_handlers::get_login_peak_hours,
+        admin_handlers::list_admins,
+        admin_handlers::create_admin,
+        admin_handlers::set_admin_status,
+        admin_handlers::reset_admin_password,
+        admin_handlers::assign_admin_role,
+        admin_handlers::get_audit_logs,
+        admin_handlers::list_jobs,
+        admin_handlers::run_job,
+        admin_handlers::publish_announcement,
+    ),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "auth", description = "学生登录与会话"),
+        (name = "student", description = "学生资料、课表和成绩"),
+        (name = "admin-auth", description = "管理员登录与账户"),
+        (name = "admin", description = "管理后台"),
+    )
+)]
+pub struct ApiDoc;
+
+/// 认证方式：学生和管理员使用不同密钥签发的 JWT
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        let bearer = || {
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            )
+        };
+        components.add_security_scheme("student_token", bearer());
+        components.add_security_scheme("admin_token", bearer());
+        // 旧版小程序通过 token 请求头传递学生 token
+        components.add_security_scheme(
+            "student_token_header",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("token"))),
+        );
+    }
+}
+
+/// 文档路由：OpenAPI JSON 和 Scalar 交互式文档
+pub fn routes() -> Router {
+    let spec = ApiDoc::openapi();
+    let json = spec.clone();
+    Router::new()
+        .route(SPEC_PATH, get(move || std::future::ready(Json(json.clone()))))
+        .merge(Scalar::with_url(DOCS_PATH, spec))
+}
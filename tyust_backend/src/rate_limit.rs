@@ -0,0 +1,317 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use tracing::warn;
+
+use crate::{
+    admin_handlers::AdminLoginRequest,
+    api_types::LoginParams,
+    audit::ClientIp,
+    config::{self, LockoutConfig, LoginRateLimit},
+    error::ApiError,
+};
+
+/// 登录请求体的最大长度，超过时直接拒绝
+const MAX_LOGIN_BODY_BYTES: usize = 64 * 1024;
+
+/// 计数表超过该大小时清理已过期的条目
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// 需要限流的登录接口
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginKind {
+    Student,
+    Admin,
+}
+
+impl LoginKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            LoginKind::Student => "student",
+            LoginKind::Admin => "admin",
+        }
+    }
+
+    /// 按登录接口的请求参数解析出账号，请求体不是该接口的参数（如微信登录）时返回 None
+    fn account(self, body: &[u8]) -> Option<String> {
+        let account = match self {
+            LoginKind::Student => serde_json::from_slice::<LoginParams>(body).ok()?.student_id,
+            LoginKind::Admin => serde_json::from_slice::<AdminLoginRequest>(body).ok()?.username,
+        };
+        Some(account.trim().to_string()).filter(|account| !account.is_empty())
+    }
+
+    fn limit(self) -> &'static LoginRateLimit {
+        let rate_limit = &config::get().rate_limit;
+        match self {
+            LoginKind::Student => &rate_limit.student_login,
+            LoginKind::Admin => &rate_limit.admin_login,
+        }
+    }
+}
+
+/// 固定窗口计数器
+struct FixedWindow {
+    started: Instant,
+    count: u32,
+}
+
+/// 某个管理员账号的连续失败记录
+struct FailureRecord {
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+/// 按 `kind:scope:key` 记录的请求次数
+static WINDOWS: Lazy<Mutex<HashMap<String, FixedWindow>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 按管理员用户名记录的连续失败次数
+static ADMIN_FAILURES: Lazy<Mutex<HashMap<String, FailureRecord>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 在窗口内计一次请求，超出限制时返回需要等待的时间
+fn hit(key: String, max: u32, window: Duration) -> Option<Duration> {
+    hit_at(&mut WINDOWS.lock().unwrap(), key, max, window, Instant::now())
+}
+
+fn hit_at(
+    windows: &mut HashMap<String, FixedWindow>,
+    key: String,
+    max: u32,
+    window: Duration,
+    now: Instant,
+) -> Option<Duration> {
+    if max == 0 {
+        return None;
+    }
+    if windows.len() > PRUNE_THRESHOLD {
+        windows.retain(|_, entry| now.duration_since(entry.started) < window);
+    }
+
+    let entry = windows.entry(key).or_insert(FixedWindow { started: now, count: 0 });
+    if now.duration_since(entry.started) >= window {
+        entry.started = now;
+        entry.count = 0;
+    }
+    if entry.count >= max {
+        return Some(window - now.duration_since(entry.started));
+    }
+    entry.count += 1;
+    None
+}
+
+/// 管理员账号当前是否处于锁定中，返回剩余锁定时间
+fn admin_locked_for(username: &str) -> Option<Duration> {
+    let now = Instant::now();
+    let failures = ADMIN_FAILURES.lock().unwrap();
+    failures
+        .get(username)
+        .and_then(|record| record.locked_until)
+        .filter(|until| *until > now)
+        .map(|until| until - now)
+}
+
+/// 记录一次管理员登录失败，达到阈值后按指数退避锁定
+fn record_admin_failure(username: &str, lockout: &LockoutConfig) {
+    let now = Instant::now();
+    let mut failures = ADMIN_FAILURES.lock().unwrap();
+    if failures.len() > PRUNE_THRESHOLD {
+        failures.retain(|_, record| record.locked_until.is_some_and(|until| until > now));
+    }
+
+    let record = failures
+        .entry(username.to_string())
+        .or_insert(FailureRecord { failures: 0, locked_until: None });
+    record.failures += 1;
+    if let Some(duration) = lockout_duration(record.failures, lockout) {
+        record.locked_until = Some(now + duration);
+        warn!(
+            username,
+            failures = record.failures,
+            lockout_secs = duration.as_secs(),
+            "Admin account locked after repeated login failures"
+        );
+    }
+}
+
+/// 连续失败 failures 次后的锁定时间，未达到阈值时返回 None
+fn lockout_duration(failures: u32, lockout: &LockoutConfig) -> Option<Duration> {
+    if failures < lockout.max_failures {
+        return None;
+    }
+    let exponent = (failures - lockout.max_failures).min(31);
+    let secs = lockout
+        .base_lockout_secs
+        .saturating_mul(1u64 << exponent)
+        .min(lockout.max_lockout_secs);
+    Some(Duration::from_secs(secs))
+}
+
+/// 管理员登录成功后清除失败记录
+fn reset_admin_failures(username: &str) {
+    ADMIN_FAILURES.lock().unwrap().remove(username);
+}
+
+/// 429 响应
+fn too_many_requests(retry_after: Duration) -> Response {
+    (
+        [(header::RETRY_AFTER, retry_after_secs(retry_after).to_string())],
+        ApiError::TooManyRequests,
+    )
+        .into_response()
+}
+
+/// Retry-After 的秒数，向上取整且至少为 1
+fn retry_after_secs(retry_after: Duration) -> u64 {
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    secs.max(1)
+}
+
+/// 登录接口限流中间件
+///
+/// 按IP和账号分别限制固定窗口内的请求次数；管理员登录还会在连续失败后锁定账号。
+/// 账号从 JSON 请求体中读取，因此需要先缓冲请求体再交给登录处理函数；请求体过大时返回 413。
+pub async fn login_rate_limit(
+    State(kind): State<LoginKind>,
+    ip: ClientIp,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limit = kind.limit();
+    let (parts, body) = request.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_LOGIN_BODY_BYTES).await else {
+        return ApiError::RequestTooLarge.into_response();
+    };
+    let account = kind.account(&bytes);
+    let request = Request::from_parts(parts, Body::from(bytes));
+
+    if kind == LoginKind::Admin
+        && let Some(retry_after) = account.as_deref().and_then(admin_locked_for)
+    {
+        crate::metrics::record_rate_limited(kind.as_str(), "lockout");
+        return too_many_requests(retry_after);
+    }
+    if let Some(ip) = &ip.0
+        && let Some(retry_after) = hit(format!("{}:ip:{}", kind.as_str(), ip), limit.per_ip, limit.window())
+    {
+        crate::metrics::record_rate_limited(kind.as_str(), "ip");
+        return too_many_requests(retry_after);
+    }
+    if let Some(account) = &account
+        && let Some(retry_after) = hit(
+            format!("{}:account:{}", kind.as_str(), account),
+            limit.per_account,
+            limit.window(),
+        )
+    {
+        crate::metrics::record_rate_limited(kind.as_str(), "account");
+        return too_many_requests(retry_after);
+    }
+
+    let response = next.run(request).await;
+
+    if kind == LoginKind::Admin
+        && let Some(account) = &account
+    {
+        match response.status() {
+            StatusCode::OK => reset_admin_failures(account),
+            StatusCode::UNAUTHORIZED => record_admin_failure(account, &config::get().rate_limit.admin_lockout),
+            _ => {}
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn window_allows_max_requests_then_reports_remaining_time() {
+        let mut windows = HashMap::new();
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(hit_at(&mut windows, "ip:1".to_string(), 3, WINDOW, start), None);
+        }
+        let later = start + Duration::from_secs(20);
+        assert_eq!(
+            hit_at(&mut windows, "ip:1".to_string(), 3, WINDOW, later),
+            Some(Duration::from_secs(40))
+        );
+        // 其他键单独计数
+        assert_eq!(hit_at(&mut windows, "ip:2".to_string(), 3, WINDOW, later), None);
+    }
+
+    #[test]
+    fn window_resets_after_it_elapses() {
+        let mut windows = HashMap::new();
+        let start = Instant::now();
+        assert_eq!(hit_at(&mut windows, "key".to_string(), 1, WINDOW, start), None);
+        assert!(hit_at(&mut windows, "key".to_string(), 1, WINDOW, start + WINDOW / 2).is_some());
+        assert_eq!(hit_at(&mut windows, "key".to_string(), 1, WINDOW, start + WINDOW), None);
+        assert!(hit_at(&mut windows, "key".to_string(), 1, WINDOW, start + WINDOW).is_some());
+    }
+
+    #[test]
+    fn zero_limit_is_unlimited() {
+        let mut windows = HashMap::new();
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert_eq!(hit_at(&mut windows, "key".to_string(), 0, WINDOW, now), None);
+        }
+        assert!(windows.is_empty());
+    }
+
+    #[test]
+    fn retry_after_rounds_up_to_whole_seconds() {
+        assert_eq!(retry_after_secs(Duration::from_secs(5)), 5);
+        assert_eq!(retry_after_secs(Duration::from_millis(4_001)), 5);
+        assert_eq!(retry_after_secs(Duration::from_millis(10)), 1);
+        assert_eq!(retry_after_secs(Duration::ZERO), 1);
+    }
+
+    #[test]
+    fn lockout_doubles_after_threshold_and_is_capped() {
+        let lockout = LockoutConfig {
+            max_failures: 3,
+            base_lockout_secs: 60,
+            max_lockout_secs: 600,
+        };
+        let secs = |failures| lockout_duration(failures, &lockout).map(|duration| duration.as_secs());
+        assert_eq!(secs(1), None);
+        assert_eq!(secs(2), None);
+        assert_eq!(secs(3), Some(60));
+        assert_eq!(secs(4), Some(120));
+        assert_eq!(secs(5), Some(240));
+        assert_eq!(secs(6), Some(480));
+        assert_eq!(secs(7), Some(600));
+        assert_eq!(secs(u32::MAX), Some(600));
+    }
+
+    #[test]
+    fn account_is_read_from_typed_login_params() {
+        let student = br#"{"stuId":" 2021001 ","password":"x"}"#;
+        assert_eq!(LoginKind::Student.account(student).as_deref(), Some("2021001"));
+        let snake_case = br#"{"student_id":"2021001","password":"x"}"#;
+        assert_eq!(LoginKind::Student.account(snake_case), None);
+        let wechat = br#"{"code":"abc"}"#;
+        assert_eq!(LoginKind::Student.account(wechat), None);
+
+        let admin = br#"{"username":"root","password":"x"}"#;
+        assert_eq!(LoginKind::Admin.account(admin).as_deref(), Some("root"));
+        let blank = br#"{"username":"  ","password":"x"}"#;
+        assert_eq!(LoginKind::Admin.account(blank), None);
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use redis::{aio::ConnectionManager, AsyncCommands};
+
+use crate::cache::{CacheFuture, CacheStore};
+
+/// 每次 SCAN 返回的键数量提示
+const SCAN_COUNT: usize = 500;
+
+/// Redis 缓存，多个服务实例共享同一份缓存和失效
+pub struct RedisCache {
+    connection: ConnectionManager,
+}
+
+impl RedisCache {
+    /// 连接 Redis，连接断开后 ConnectionManager 会自动重连
+    pub async fn connect(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url).context("invalid Redis URL")?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("failed to connect to Redis")?;
+        Ok(Self { connection })
+    }
+}
+
+impl CacheStore for RedisCache {
+    fn get<'a>(&'a self, key: &'a str) -> CacheFuture<'a, Option<String>> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            Ok(connection.get(key).await?)
+        })
+    }
+
+    fn set<'a>(&'a self, key: &'a str, value: String, ttl: Duration) -> CacheFuture<'a, ()> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let _: () = connection.set_ex(key, value, ttl.as_secs().max(1)).await?;
+            Ok(())
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> CacheFuture<'a, ()> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let _: () = connection.del(key).await?;
+            Ok(())
+        })
+    }
+
+    fn delete_prefix<'a>(&'a self, prefix: &'a str) -> CacheFuture<'a, ()> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let pattern = format!("{}*", escape_pattern(prefix));
+            let mut cursor: u64 = 0;
+            loop {
+                let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                    .arg(cursor)
+                    .arg("MATCH")
+                    .arg(&pattern)
+                    .arg("COUNT")
+                    .arg(SCAN_COUNT)
+                    .query_async(&mut connection)
+                    .await?;
+                if !keys.is_empty() {
+                    let _: () = connection.del(keys).await?;
+                }
+                if next == 0 {
+                    return Ok(());
+                }
+                cursor = next;
+            }
+        })
+    }
+}
+
+/// 转义 glob 模式中的特殊字符，保证按字面前缀匹配
+fn escape_pattern(prefix: &str) -> String {
+    let mut escaped = String::with_capacity(prefix.len());
+    for c in prefix.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
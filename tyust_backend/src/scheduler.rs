@@ -0,0 +1,244 @@
+use std::{str::FromStr, time::Instant};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::{config, db, jobs, metrics};
+
+/// 本实例的标识，记录在任务租约中，便于排查是哪个实例在执行任务
+static INSTANCE_ID: Lazy<String> = Lazy::new(|| {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "tyust".to_string());
+    format!("{}-{}", host, &uuid::Uuid::new_v4().simple().to_string()[..8])
+});
+
+/// 定时任务
+///
+/// 任务状态保存在 scheduled_jobs 表中，每个实例定期尝试获取到期任务的租约，
+/// 同一任务同时只会在一个实例上执行。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobName {
+    AuthCacheCleanup, // 清理过期的上游认证缓存
+    AvatarGc,         // 清理不再使用的头像文件
+    ScorePolling,     // 拉取成绩并记录新出的成绩
+    TermRollover,     // 切换到预先设置的下一学期
+    EventCleanup,     // 删除超过保留时间的推送事件
+}
+
+impl JobName {
+    pub const ALL: [JobName; 5] = [
+        JobName::AuthCacheCleanup,
+        JobName::AvatarGc,
+        JobName::ScorePolling,
+        JobName::TermRollover,
+        JobName::EventCleanup,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobName::AuthCacheCleanup => "auth_cache_cleanup",
+            JobName::AvatarGc => "avatar_gc",
+            JobName::ScorePolling => "score_polling",
+            JobName::TermRollover => "term_rollover",
+            JobName::EventCleanup => "event_cleanup",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|job| job.as_str() == name)
+    }
+
+    /// 配置中的 cron 表达式，为空时只能手动触发
+    fn schedule_expression(self) -> &'static str {
+        let config = &config::get().scheduler;
+        let expression = match self {
+            JobName::AuthCacheCleanup => &config.auth_cache_cleanup,
+            JobName::AvatarGc => &config.avatar_gc,
+            JobName::ScorePolling => &config.score_polling,
+            JobName::TermRollover => &config.term_rollover,
+            JobName::EventCleanup => &config.event_cleanup,
+        };
+        expression.trim()
+    }
+
+    /// 下一次计划执行的时间（UTC），未配置计划时返回 None
+    fn next_run_at(self) -> Option<chrono::NaiveDateTime> {
+        let expression = self.schedule_expression();
+        if expression.is_empty() {
+            return None;
+        }
+        let schedule = cron::Schedule::from_str(expression).ok()?;
+        schedule
+            .upcoming(chrono::Local)
+            .next()
+            .map(|time| time.naive_utc())
+    }
+
+    /// 执行任务，返回结果摘要
+    async fn run(self) -> Result<String> {
+        match self {
+            JobName::AuthCacheCleanup => jobs::cleanup_auth_cache().await,
+            JobName::AvatarGc => jobs::collect_avatar_garbage().await,
+            JobName::ScorePolling => jobs::poll_scores().await,
+            JobName::TermRollover => jobs::roll_over_term().await,
+            JobName::EventCleanup => jobs::cleanup_events().await,
+        }
+    }
+}
+
+/// 定时任务状态（管理接口返回）
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobStatus {
+    pub name: JobName,
+    pub schedule: Option<String>,                          // cron 表达式，为空表示只能手动触发
+    pub running: bool,                                     // 是否有实例正在执行
+    pub running_on: Option<String>,                        // 正在执行的实例
+    pub run_requested: bool,                               // 是否有待执行的手动触发
+    pub next_run_at: Option<chrono::NaiveDateTime>,        // 下次计划执行时间（UTC）
+    pub last_started_at: Option<chrono::NaiveDateTime>,
+    pub last_finished_at: Option<chrono::NaiveDateTime>,
+    pub last_status: Option<String>,                       // running、succeeded 或 failed
+    pub last_message: Option<String>,                      // 执行结果摘要或错误信息
+    pub last_duration_ms: Option<i64>,
+}
+
+/// 在 scheduled_jobs 表中登记所有任务，并按当前配置更新下次执行时间
+pub async fn register_jobs() -> Result<()> {
+    let db_pool = db::get_db_pool().await;
+    for job in JobName::ALL {
+        db::register_scheduled_job(db_pool, job.as_str(), job.next_run_at()).await?;
+    }
+    Ok(())
+}
+
+/// 启动调度循环（scheduler.enabled 关闭时不执行任务，但管理员仍可触发，由其他实例执行）
+pub fn spawn() {
+    if !config::get().scheduler.enabled {
+        info!("Scheduler disabled on this instance");
+        return;
+    }
+    info!(instance = %*INSTANCE_ID, "Scheduler started");
+    tokio::spawn(run_loop());
+}
+
+async fn run_loop() {
+    let config = &config::get().scheduler;
+    let mut interval = tokio::time::interval(config.poll_interval());
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        let db_pool = db::get_db_pool().await;
+        for job in JobName::ALL {
+            let lease_until = lease_deadline();
+            match db::claim_scheduled_job(db_pool, job.as_str(), &INSTANCE_ID, lease_until).await {
+                Ok(true) => {
+                    tokio::spawn(execute(job));
+                }
+                Ok(false) => {}
+                Err(e) => warn!(job = job.as_str(), error = %e, "Failed to claim scheduled job"),
+            }
+        }
+    }
+}
+
+fn lease_deadline() -> chrono::NaiveDateTime {
+    let lease = chrono::Duration::from_std(config::get().scheduler.lease()).unwrap_or(chrono::Duration::minutes(5));
+    (chrono::Utc::now() + lease).naive_utc()
+}
+
+/// 执行已获取租约的任务，执行期间定期续租；租约被其他实例接管时放弃执行
+async fn execute(job: JobName) {
+    let name = job.as_str();
+    let db_pool = db::get_db_pool().await;
+    let started = Instant::now();
+    info!(job = name, "Scheduled job started");
+
+    let renew = async {
+        let mut interval = tokio::time::interval(config::get().scheduler.lease() / 3);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match db::renew_scheduled_job(db_pool, name, &INSTANCE_ID, lease_deadline()).await {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(e) => warn!(job = name, error = %e, "Failed to renew job lease"),
+            }
+        }
+    };
+    let result = tokio::select! {
+        result = job.run() => result,
+        _ = renew => {
+            warn!(job = name, "Job lease lost, abandoning run");
+            return;
+        }
+    };
+
+    let elapsed = started.elapsed();
+    let (status, message) = match &result {
+        Ok(summary) => {
+            info!(job = name, elapsed_ms = elapsed.as_millis() as u64, summary = %summary, "Scheduled job finished");
+            ("succeeded", summary.clone())
+        }
+        Err(e) => {
+            error!(job = name, elapsed_ms = elapsed.as_millis() as u64, error = %format!("{:#}", e), "Scheduled job failed");
+            ("failed", format!("{:#}", e))
+        }
+    };
+    metrics::record_job_run(name, status, elapsed.as_secs_f64());
+
+    if let Err(e) = db::finish_scheduled_job(
+        db_pool,
+        name,
+        &INSTANCE_ID,
+        status,
+        &message,
+        elapsed.as_millis() as i64,
+        job.next_run_at(),
+    )
+    .await
+    {
+        error!(job = name, error = %e, "Failed to record job result");
+    }
+}
+
+/// 所有任务的状态
+pub async fn job_statuses() -> Result<Vec<JobStatus>, sqlx::Error> {
+    let db_pool = db::get_db_pool().await;
+    let states = db::list_scheduled_jobs(db_pool).await?;
+    let now = chrono::Utc::now().naive_utc();
+
+    Ok(JobName::ALL
+        .into_iter()
+        .map(|job| {
+            let state = states.iter().find(|state| state.name == job.as_str());
+            let schedule = Some(job.schedule_expression())
+                .filter(|expression| !expression.is_empty())
+                .map(str::to_string);
+            let running_on = state
+                .filter(|state| state.locked_until.is_some_and(|until| until > now))
+                .and_then(|state| state.locked_by.clone());
+            JobStatus {
+                name: job,
+                schedule,
+                running: running_on.is_some(),
+                running_on,
+                run_requested: state.is_some_and(|state| state.run_requested_at.is_some()),
+                next_run_at: state.and_then(|state| state.next_run_at),
+                last_started_at: state.and_then(|state| state.last_started_at),
+                last_finished_at: state.and_then(|state| state.last_finished_at),
+                last_status: state.and_then(|state| state.last_status.clone()),
+                last_message: state.and_then(|state| state.last_message.clone()),
+                last_duration_ms: state.and_then(|state| state.last_duration_ms),
+            }
+        })
+        .collect())
+}
+
+/// 请求尽快执行一次任务，由下一个检查到该请求的实例执行
+pub async fn trigger(job: JobName) -> Result<bool, sqlx::Error> {
+    let db_pool = db::get_db_pool().await;
+    db::request_scheduled_job_run(db_pool, job.as_str()).await
+}
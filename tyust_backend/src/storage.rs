@@ -0,0 +1,248 @@
+use std::{
+    future::Future,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    pin::Pin,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use axum::http::Method;
+use futures::TryStreamExt;
+use object_store::{
+    aws::{AmazonS3, AmazonS3Builder},
+    path::Path as ObjectPath,
+    signer::Signer,
+    Attribute, Attributes, ObjectStore, PutOptions, PutPayload,
+};
+use once_cell::sync::OnceCell;
+use tracing::warn;
+
+use crate::config::{StorageBackend, StorageConfig, StorageUrlMode};
+
+/// 本地存储文件的访问地址前缀，对应 /static 路由
+const LOCAL_URL_PREFIX: &str = "/static";
+
+static STORAGE: OnceCell<Box<dyn FileStorage>> = OnceCell::new();
+
+pub type StorageFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// 上传文件存储
+///
+/// 文件以 `avatars/<学生目录>/<文件名>` 形式的键保存，数据库中只记录键，
+/// 访问地址由 `url` 按配置生成（公开地址或预签名地址）。
+pub trait FileStorage: Send + Sync {
+    /// 写入文件，键已存在时覆盖
+    fn put<'a>(&'a self, key: &'a str, data: Vec<u8>, content_type: &'a str) -> StorageFuture<'a, ()>;
+    /// 删除文件，文件不存在时不报错
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()>;
+    /// 列出所有以 prefix 开头的键
+    fn list<'a>(&'a self, prefix: &'a str) -> StorageFuture<'a, Vec<String>>;
+    /// 文件的访问地址
+    fn url<'a>(&'a self, key: &'a str) -> StorageFuture<'a, String>;
+}
+
+/// 根据配置初始化文件存储
+pub fn init(config: &StorageConfig, static_dir: &str) -> Result<()> {
+    let storage: Box<dyn FileStorage> = match config.backend {
+        StorageBackend::Local => Box::new(LocalStorage::new(static_dir)),
+        StorageBackend::S3 => Box::new(S3Storage::new(config)?),
+    };
+    STORAGE
+        .set(storage)
+        .map_err(|_| anyhow!("storage already initialized"))
+}
+
+pub fn get() -> &'static dyn FileStorage {
+    STORAGE.get().expect("Storage not initialized").as_ref()
+}
+
+/// 数据库中保存的是存储键；旧数据中的完整地址（以 `/` 开头或带协议）原样返回
+pub fn is_storage_key(value: &str) -> bool {
+    !value.is_empty() && !value.starts_with('/') && !value.contains("://")
+}
+
+/// 将数据库中的头像字段转换为客户端可访问的地址，生成失败时返回 None
+pub async fn resolve_url(stored: Option<&str>) -> Option<String> {
+    let stored = stored?;
+    if !is_storage_key(stored) {
+        return Some(stored.to_string());
+    }
+    match get().url(stored).await {
+        Ok(url) => Some(url),
+        Err(e) => {
+            warn!(key = stored, error = %e, "Failed to build file URL");
+            None
+        }
+    }
+}
+
+/// 写入 server.static_dir 的本地存储，仅适用于单实例部署
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// 键对应的本地路径，拒绝可能跳出根目录的键
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let safe = !key.is_empty()
+            && !key.starts_with('/')
+            && key.split('/').all(|part| !part.is_empty() && part != "." && part != "..");
+        if !safe {
+            return Err(anyhow!("invalid storage key '{}'", key));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+impl FileStorage for LocalStorage {
+    fn put<'a>(&'a self, key: &'a str, data: Vec<u8>, _content_type: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            tokio::task::spawn_blocking(move || write_atomically(&path, &data)).await??;
+            Ok(())
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path(key)?).await {
+                Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn list<'a>(&'a self, prefix: &'a str) -> StorageFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            // 只支持按目录列出，与头像目录结构一致
+            let dir = prefix.trim_end_matches('/');
+            let mut entries = match tokio::fs::read_dir(self.path(dir)?).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => return Err(e.into()),
+            };
+            let mut keys = Vec::new();
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_file() {
+                    keys.push(format!("{}/{}", dir, entry.file_name().to_string_lossy()));
+                }
+            }
+            Ok(keys)
+        })
+    }
+
+    fn url<'a>(&'a self, key: &'a str) -> StorageFuture<'a, String> {
+        Box::pin(async move { Ok(format!("{}/{}", LOCAL_URL_PREFIX, key)) })
+    }
+}
+
+/// 先写临时文件再重命名，避免读取到写了一半的文件
+fn write_atomically(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4()));
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+/// S3 兼容的对象存储，多个服务实例共享同一个存储桶
+pub struct S3Storage {
+    store: AmazonS3,
+    url_mode: StorageUrlMode,
+    signed_url_ttl: Duration,
+    public_base_url: String,
+}
+
+impl S3Storage {
+    pub fn new(config: &StorageConfig) -> Result<Self> {
+        let s3 = &config.s3;
+        let mut builder = AmazonS3Builder::new()
+            .with_region(&s3.region)
+            .with_bucket_name(&s3.bucket)
+            .with_allow_http(s3.allow_http);
+        if !s3.endpoint.is_empty() {
+            builder = builder.with_endpoint(&s3.endpoint);
+        }
+        if !s3.access_key_id.is_empty() {
+            builder = builder
+                .with_access_key_id(&s3.access_key_id)
+                .with_secret_access_key(&s3.secret_access_key);
+        }
+        let store = builder.build().context("invalid S3 storage configuration")?;
+
+        // 未配置公开地址时使用 path-style 地址，MinIO 默认即为该形式
+        let public_base_url = if s3.public_base_url.is_empty() {
+            format!("{}/{}", s3.endpoint.trim_end_matches('/'), s3.bucket)
+        } else {
+            s3.public_base_url.trim_end_matches('/').to_string()
+        };
+        Ok(Self {
+            store,
+            url_mode: config.url_mode,
+            signed_url_ttl: Duration::from_secs(config.signed_url_ttl_secs),
+            public_base_url,
+        })
+    }
+}
+
+impl FileStorage for S3Storage {
+    fn put<'a>(&'a self, key: &'a str, data: Vec<u8>, content_type: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            let mut attributes = Attributes::new();
+            attributes.insert(Attribute::ContentType, content_type.to_string().into());
+            let options = PutOptions {
+                attributes,
+                ..Default::default()
+            };
+            self.store
+                .put_opts(&ObjectPath::from(key), PutPayload::from(data), options)
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StorageFuture<'a, ()> {
+        Box::pin(async move {
+            match self.store.delete(&ObjectPath::from(key)).await {
+                Err(e) if !matches!(e, object_store::Error::NotFound { .. }) => Err(e.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn list<'a>(&'a self, prefix: &'a str) -> StorageFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let prefix = ObjectPath::from(prefix.trim_end_matches('/'));
+            let keys = self
+                .store
+                .list(Some(&prefix))
+                .map_ok(|meta| meta.location.to_string())
+                .try_collect()
+                .await?;
+            Ok(keys)
+        })
+    }
+
+    fn url<'a>(&'a self, key: &'a str) -> StorageFuture<'a, String> {
+        Box::pin(async move {
+            match self.url_mode {
+                StorageUrlMode::Public => Ok(format!("{}/{}", self.public_base_url, key)),
+                StorageUrlMode::Signed => {
+                    let url = self
+                        .store
+                        .signed_url(Method::GET, &ObjectPath::from(key), self.signed_url_ttl)
+                        .await?;
+                    Ok(url.to_string())
+                }
+            }
+        })
+    }
+}
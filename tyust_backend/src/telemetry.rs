@@ -0,0 +1,62 @@
+use axum::{http::Request, Router};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::Level;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+use crate::config::LogConfig;
+
+/// 请求ID使用的响应/请求头
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 初始化全局日志
+///
+/// 日志级别取自配置的 `log.level`，设置了 RUST_LOG 环境变量时以其为准；
+/// `log.format` 为 json 时输出 JSON 格式的结构化日志，否则输出便于阅读的文本格式。
+pub fn init_tracing(config: &LogConfig) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.level));
+    let json = config.format == "json";
+
+    let registry = tracing_subscriber::registry().with(filter);
+    if json {
+        registry
+            .with(fmt::layer().json().flatten_event(true).with_current_span(true).with_span_list(false))
+            .init();
+    } else {
+        registry.with(fmt::layer().with_target(false)).init();
+    }
+}
+
+/// 为路由添加请求ID和请求日志
+///
+/// 客户端传入的 X-Request-Id 会被沿用，否则生成新的 UUID；该ID会写入响应头，
+/// 并作为 `request` span 的字段出现在该请求产生的所有日志中。
+pub fn with_request_tracing(router: Router) -> Router {
+    let header = axum::http::HeaderName::from_static(REQUEST_ID_HEADER);
+
+    router.layer(
+        ServiceBuilder::new()
+            .layer(SetRequestIdLayer::new(header.clone(), MakeRequestUuid))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(|request: &Request<_>| {
+                        let request_id = request
+                            .extensions()
+                            .get::<RequestId>()
+                            .and_then(|id| id.header_value().to_str().ok())
+                            .unwrap_or("-");
+                        tracing::info_span!(
+                            "request",
+                            request_id = %request_id,
+                            method = %request.method(),
+                            uri = %request.uri(),
+                        )
+                    })
+                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
+            )
+            .layer(PropagateRequestIdLayer::new(header)),
+    )
+}
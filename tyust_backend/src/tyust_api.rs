@@ -1,17 +1,19 @@
+use crate::config::{self, UpstreamConfig};
 use crate::de_crypto;
 use crate::de_crypto::get_crypto_and_password;
 use crate::entity::{self, RonghemenhuUserInfoResponse, TyustCourseResponse, TyustScoreResponse};
 use crate::http_helper::{
-    build_cookie_header, extract_query_param, get_cookie_value, header_str, new_client_follow,
-    new_client_no_redirect,
+    SendObserved, build_cookie_header, extract_query_param, get_cookie_value, header_str,
+    new_client_follow, new_client_no_redirect,
 };
-use anyhow::{Context, Ok, Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use once_cell::sync::Lazy;
 use rand::RngCore;
 use regex::Regex;
 use reqwest::header::{COOKIE, HeaderMap, HeaderValue, LOCATION, REFERER, SET_COOKIE, USER_AGENT};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use tracing::instrument;
 use std::collections::HashMap;
 use url::Url;
 
@@ -21,10 +23,16 @@ static CLIENT_FOLLOW: Lazy<reqwest::Client> =
 static CLIENT_NO_REDIRECT: Lazy<reqwest::Client> =
     Lazy::new(|| new_client_no_redirect().expect("Failed to create no-redirect client"));
 
+// 上游教务系统地址（见 `upstream` 配置段）
+fn upstream() -> &'static UpstreamConfig {
+    &config::get().upstream
+}
+
+#[instrument(skip_all, err)]
 pub async fn tyust_get_session() -> Result<(String, String)> {
     let resp = CLIENT_FOLLOW
-        .get("https://sso1.tyust.edu.cn/login")
-        .send()
+        .get(upstream().sso("/login"))
+        .send_observed("tyust_get_session")
         .await
         .context("GET sso login")?;
     let set_cookie = header_str(resp.headers(), SET_COOKIE.as_str())
@@ -41,14 +49,32 @@ pub async fn tyust_get_session() -> Result<(String, String)> {
     Ok((session, execution_code))
 }
 
+/// 探测统一身份认证是否可访问（就绪检查用），返回 5xx 时视为不可用
+pub async fn tyust_probe_sso() -> Result<()> {
+    let resp = CLIENT_NO_REDIRECT
+        .get(upstream().sso("/login"))
+        .send_observed("tyust_probe_sso")
+        .await
+        .context("GET sso login")?;
+    if resp.status().is_server_error() {
+        return Err(anyhow!("sso returned {}", resp.status()));
+    }
+    Ok(())
+}
+
+#[instrument(skip_all, err)]
 pub async fn tyust_get_ronghemenhu_jsessionid(code: &str) -> Result<String> {
-    let url = "https://ronghemenhu.tyust.edu.cn/portal/publish/web/login/loginByOauth";
+    let url = upstream().portal("/portal/publish/web/login/loginByOauth");
     let payload = json!({
         "code": code,
         "username": "",
         "password": ""
     });
-    let resp = CLIENT_NO_REDIRECT.post(url).json(&payload).send().await?;
+    let resp = CLIENT_NO_REDIRECT
+        .post(&url)
+        .json(&payload)
+        .send_observed("tyust_get_ronghemenhu_jsessionid")
+        .await?;
     let set_cookie = header_str(resp.headers(), SET_COOKIE.as_str())
         .ok_or_else(|| anyhow!("Set-Cookie missing for JSESSIONID"))?;
     let jsessionid = get_cookie_value(&set_cookie, "JSESSIONID")
@@ -57,6 +83,7 @@ pub async fn tyust_get_ronghemenhu_jsessionid(code: &str) -> Result<String> {
     Ok(jsessionid)
 }
 
+#[instrument(skip_all, err)]
 pub async fn tyust_get_login_code(
     username: &str,
     session: &str,
@@ -64,7 +91,7 @@ pub async fn tyust_get_login_code(
     crypto: &str,
     password_str: &str,
 ) -> Result<(String, String, String, String)> {
-    let login_url = "https://sso1.tyust.edu.cn/login";
+    let login_url = upstream().sso("/login");
 
     let mut headers = HeaderMap::new();
     let mut cookies = HashMap::new();
@@ -81,17 +108,17 @@ pub async fn tyust_get_login_code(
         ("password", password_str.to_string()),
     ];
     let resp_no = CLIENT_NO_REDIRECT
-        .post(login_url)
+        .post(&login_url)
         .headers(headers.clone())
         .form(&form)
-        .send()
+        .send_observed("tyust_get_login_code")
         .await
         .context("post login (no redirect)")?;
     let (ticket, sourceid_tgc, rg_objectid) = handle_login_information(resp_no.headers())?;
     let next_location = resp_no.headers().get("Location").unwrap().to_str()?;
     let res = CLIENT_NO_REDIRECT
         .get(next_location)
-        .send()
+        .send_observed("tyust_get_login_code")
         .await
         .context("get next redirect")?;
     let next_location = res.headers().get("Location").unwrap().to_str()?;
@@ -102,7 +129,7 @@ pub async fn tyust_get_login_code(
     let resp_follow = CLIENT_FOLLOW
         .get(next_location)
         .headers(headers)
-        .send()
+        .send_observed("tyust_get_login_code")
         .await
         .context("post login (follow)")?;
 
@@ -114,6 +141,7 @@ pub async fn tyust_get_login_code(
 
 #[allow(unused)]
 // get_ronghemenhu_jsessionid
+#[instrument(skip_all, err)]
 pub async fn tyust_get_user_info(jsessionid: &str) -> Result<RonghemenhuUserInfoResponse> {
     let mut headers = HeaderMap::new();
     let mut cookies = HashMap::new();
@@ -121,9 +149,9 @@ pub async fn tyust_get_user_info(jsessionid: &str) -> Result<RonghemenhuUserInfo
     headers.insert(COOKIE, build_cookie_header(&cookies)?);
 
     let resp = CLIENT_FOLLOW
-        .get("https://ronghemenhu.tyust.edu.cn/portal/publish/web/login/user")
+        .get(upstream().portal("/portal/publish/web/login/user"))
         .headers(headers)
-        .send()
+        .send_observed("tyust_get_user_info")
         .await?;
     Ok(resp.json::<RonghemenhuUserInfoResponse>().await?)
 }
@@ -134,6 +162,7 @@ pub fn generate_device_id() -> String {
     b.iter().map(|x| format!("{:02x}", x)).collect()
 }
 
+#[instrument(skip_all, err)]
 pub async fn tyust_get_access_token(
     session: &str,
     sourceid_tgc: &str,
@@ -146,10 +175,11 @@ pub async fn tyust_get_access_token(
     cookies.insert("rg_objectid".into(), rg_objectid.into());
     headers.insert(COOKIE, build_cookie_header(&cookies)?);
 
+    let callback_url = upstream().zero(&format!("/login/casCallback/{}/", upstream().zero_external_id));
     let resp = CLIENT_NO_REDIRECT
-        .get("https://sso1.tyust.edu.cn/login?service=https://zero.tyust.edu.cn/login/casCallback/r3IveGXj/")
+        .get(format!("{}?service={}", upstream().sso("/login"), callback_url))
         .headers(headers)
-        .send()
+        .send_observed("tyust_get_access_token")
         .await?;
     let loc = header_str(resp.headers(), LOCATION.as_str())
         .ok_or_else(|| anyhow!("Location missing for ticket"))?;
@@ -157,17 +187,17 @@ pub async fn tyust_get_access_token(
         extract_query_param(&loc, "ticket").ok_or_else(|| anyhow!("ticket missing in redirect"))?;
 
     let payload = json!({
-        "externalId": "r3IveGXj",
+        "externalId": upstream().zero_external_id,
         "data": serde_json::to_string(&json!({
-            "callbackUrl": "https://zero.tyust.edu.cn/login/casCallback/r3IveGXj/",
+            "callbackUrl": callback_url,
             "ticket": ticket,
             "deviceId": generate_device_id(),
         }))?
     });
     let resp2 = CLIENT_FOLLOW
-        .post("https://zero.tyust.edu.cn/api/access/auth/finish")
+        .post(upstream().zero("/api/access/auth/finish"))
         .json(&payload)
-        .send()
+        .send_observed("tyust_get_access_token")
         .await?;
     let v = resp2.json::<serde_json::Value>().await?;
     let token = v["data"]["token"]
@@ -177,6 +207,7 @@ pub async fn tyust_get_access_token(
     Ok(token)
 }
 
+#[instrument(skip_all, err)]
 pub async fn tyust_get_route(access_token: &str) -> Result<String> {
     let mut headers = HeaderMap::new();
     let mut cookies = HashMap::new();
@@ -184,9 +215,9 @@ pub async fn tyust_get_route(access_token: &str) -> Result<String> {
     headers.insert(COOKIE, build_cookie_header(&cookies)?);
 
     let resp = CLIENT_NO_REDIRECT
-        .get("https://newjwc.tyust.edu.cn/sso/jasiglogin/jwglxt")
+        .get(upstream().jwglxt("/sso/jasiglogin/jwglxt"))
         .headers(headers)
-        .send()
+        .send_observed("tyust_get_route")
         .await?;
     let set_cookie = header_str(resp.headers(), SET_COOKIE.as_str())
         .ok_or_else(|| anyhow!("route Set-Cookie missing"))?;
@@ -222,6 +253,7 @@ fn handle_login_information(headers: &HeaderMap) -> Result<(String, String, Stri
     Ok((ticket, sourceid_tgc, rg_objectid))
 }
 
+#[instrument(skip_all, err)]
 pub async fn follow_redirects_for_jsession(
     mut url: String,
     mut cookies: HashMap<String, String>,
@@ -230,7 +262,11 @@ pub async fn follow_redirects_for_jsession(
     for _ in 0..hops {
         let mut headers = HeaderMap::new();
         headers.insert(COOKIE, build_cookie_header(&cookies)?);
-        let resp = CLIENT_NO_REDIRECT.get(&url).headers(headers).send().await?;
+        let resp = CLIENT_NO_REDIRECT
+            .get(&url)
+            .headers(headers)
+            .send_observed("follow_redirects_for_jsession")
+            .await?;
 
         // 收集新的 cookie 它会先返回一个验证的jsession然后再跳转返回真正需要的jsession
         let mut skip_first = false;
@@ -270,6 +306,7 @@ pub async fn follow_redirects_for_jsession(
     Err(anyhow!("JSESSIONID not obtained after redirects"))
 }
 
+#[instrument(skip_all, err)]
 pub async fn tyust_get_jwglxt_jsession(
     session: &str,
     sourceid_tgc: &str,
@@ -284,13 +321,17 @@ pub async fn tyust_get_jwglxt_jsession(
     cookies.insert("__access_token".into(), access_token.into());
     cookies.insert("route".into(), route.into());
 
-    let start =
-        "https://sso1.tyust.edu.cn/login?service=https://newjwc.tyust.edu.cn/sso/jasiglogin/jwglxt";
-    let jsession = follow_redirects_for_jsession(start.to_string(), cookies, 10).await?;
+    let start = format!(
+        "{}?service={}",
+        upstream().sso("/login"),
+        upstream().jwglxt("/sso/jasiglogin/jwglxt")
+    );
+    let jsession = follow_redirects_for_jsession(start, cookies, 10).await?;
 
     Ok(jsession)
 }
 
+#[instrument(skip_all, err)]
 pub async fn tyust_get_current_course(
     jwglxt_jsession: &str,
     access_token: &str,
@@ -306,15 +347,15 @@ pub async fn tyust_get_current_course(
         USER_AGENT,
         HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/115.0"),
     );
-    headers.insert(REFERER, HeaderValue::from_static("https://newjwc.tyust.edu.cn/jwglxt/kbcx/xskbcx_cxXskbcxIndex.html?gnmkdm=N253508&layout=default"));
+    headers.insert(REFERER, HeaderValue::from_str(&upstream().jwglxt("/jwglxt/kbcx/xskbcx_cxXskbcxIndex.html?gnmkdm=N253508&layout=default"))?);
     let params = [("gnmkdm", "N253508")];
     let form = [("xnm", "2025"), ("xqm", "3"), ("kzlx", "ck"), ("xsdm", "")];
     let resp = CLIENT_NO_REDIRECT
-        .post("https://newjwc.tyust.edu.cn/jwglxt/kbcx/xskbcx_cxXsgrkb.html")
+        .post(upstream().jwglxt("/jwglxt/kbcx/xskbcx_cxXsgrkb.html"))
         .headers(headers)
         .query(&params)
         .form(&form)
-        .send()
+        .send_observed("tyust_get_current_course")
         .await
         .context("post xskbcx")?;
 
@@ -323,6 +364,7 @@ pub async fn tyust_get_current_course(
 }
 
 /// 获取有效成绩
+#[instrument(skip_all, err)]
 pub async fn tyust_get_scores(
     jwglxt_jsession: &str,
     access_token: &str,
@@ -338,7 +380,7 @@ pub async fn tyust_get_scores(
         USER_AGENT,
         HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/115.0"),
     );
-    headers.insert(REFERER, HeaderValue::from_static("https://newjwc.tyust.edu.cn/jwglxt/cjcx/cjcx_cxDgXscj.html?gnmkdm=N305005&layout=default"));
+    headers.insert(REFERER, HeaderValue::from_str(&upstream().jwglxt("/jwglxt/cjcx/cjcx_cxDgXscj.html?gnmkdm=N305005&layout=default"))?);
 
     let params = [("gnmkdm", "N305005")];
     let form = [
@@ -354,11 +396,11 @@ pub async fn tyust_get_scores(
     ];
 
     let resp = CLIENT_NO_REDIRECT
-        .post("https://newjwc.tyust.edu.cn/jwglxt/cjcx/cjcx_cxDgXscj.html")
+        .post(upstream().jwglxt("/jwglxt/cjcx/cjcx_cxDgXscj.html"))
         .headers(headers)
         .query(&params)
         .form(&form)
-        .send()
+        .send_observed("tyust_get_scores")
         .await
         .context("post cjcx")?;
 
@@ -367,6 +409,7 @@ pub async fn tyust_get_scores(
 }
 
 /// 获取原始成绩
+#[instrument(skip_all, err)]
 pub async fn tyust_get_raw_scores(
     jwglxt_jsession: &str,
     route: &str,
@@ -383,7 +426,7 @@ pub async fn tyust_get_raw_scores(
         USER_AGENT,
         HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/142.0.0.0 Safari/537.36 Edg/142.0.0.0"),
     );
-    headers.insert(REFERER, HeaderValue::from_static("https://newjwc.tyust.edu.cn/jwglxt/xsxxxggl/xsgrxxwh_cxXsgrxx.html?gnmkdm=N100801&layout=default"));
+    headers.insert(REFERER, HeaderValue::from_str(&upstream().jwglxt("/jwglxt/xsxxxggl/xsgrxxwh_cxXsgrxx.html?gnmkdm=N100801&layout=default"))?);
 
     let params = [("gnmkdm", "N305005"), ("doType", "query")];
     let timestamp = chrono::Utc::now().timestamp_millis().to_string();
@@ -401,11 +444,11 @@ pub async fn tyust_get_raw_scores(
     ];
 
     let resp = CLIENT_NO_REDIRECT
-        .post("https://newjwc.tyust.edu.cn/jwglxt/cjcx/cjcx_cxDgXscj.html")
+        .post(upstream().jwglxt("/jwglxt/cjcx/cjcx_cxDgXscj.html"))
         .headers(headers)
         .query(&params)
         .form(&form)
-        .send()
+        .send_observed("tyust_get_raw_scores")
         .await
         .context("post cjcx raw")?;
 
@@ -0,0 +1,211 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use tracing::{debug, error, warn};
+
+use crate::{
+    config::{self, WechatConfig},
+    error::ApiError,
+    http_helper::{new_client_follow, SendObserved},
+};
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| new_client_follow().expect("Failed to create wechat client"));
+
+/// 提前刷新 access token 的时间，避免使用即将过期的 token
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// 本实例缓存的 access token
+///
+/// 使用稳定版接口（stable_token）获取，多个实例各自获取时得到的是同一个 token，不会互相使旧 token 失效。
+static ACCESS_TOKEN: Lazy<Mutex<Option<CachedToken>>> = Lazy::new(|| Mutex::new(None));
+
+struct CachedToken {
+    token: String,
+    refresh_at: Instant,
+}
+
+fn wechat() -> &'static WechatConfig {
+    &config::get().wechat
+}
+
+/// 微信接口返回的错误码
+#[derive(Debug, Clone)]
+pub struct WechatError {
+    pub errcode: i64,
+    pub errmsg: String,
+}
+
+impl WechatError {
+    /// access token 无效或已过期
+    fn is_token_invalid(&self) -> bool {
+        matches!(self.errcode, 40001 | 40014 | 42001)
+    }
+
+    /// 用户未授权该模板或已取消订阅，授权次数已用完
+    pub fn is_unsubscribed(&self) -> bool {
+        self.errcode == 43101
+    }
+
+    /// wx.login 的 code 无效、已使用或已过期
+    pub fn is_invalid_code(&self) -> bool {
+        matches!(self.errcode, 40029 | 40163 | 40226)
+    }
+}
+
+impl fmt::Display for WechatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wechat api error {}: {}", self.errcode, self.errmsg)
+    }
+}
+
+impl std::error::Error for WechatError {}
+
+/// 解析微信接口响应，errcode 不为 0 时返回 `WechatError`
+async fn decode<T: DeserializeOwned>(resp: reqwest::Response) -> Result<T> {
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(anyhow!("wechat api returned {}", status));
+    }
+    let value: Value = resp.json().await.context("read wechat response")?;
+    let errcode = value.get("errcode").and_then(Value::as_i64).unwrap_or(0);
+    if errcode != 0 {
+        let errmsg = value.get("errmsg").and_then(Value::as_str).unwrap_or_default().to_string();
+        return Err(WechatError { errcode, errmsg }.into());
+    }
+    serde_json::from_value(value).context("decode wechat response")
+}
+
+#[derive(Deserialize)]
+struct StableTokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// 获取接口调用凭证，force_refresh 为 true 时要求微信重新生成
+async fn access_token(force_refresh: bool) -> Result<String> {
+    let mut cached = ACCESS_TOKEN.lock().await;
+    if let Some(token) = cached.as_ref().filter(|token| !force_refresh && token.refresh_at > Instant::now()) {
+        return Ok(token.token.clone());
+    }
+
+    let config = wechat();
+    let resp = CLIENT
+        .post(config.api("/cgi-bin/stable_token"))
+        .json(&json!({
+            "grant_type": "client_credential",
+            "appid": config.app_id,
+            "secret": config.app_secret,
+            "force_refresh": force_refresh,
+        }))
+        .send_observed("wechat_stable_token")
+        .await
+        .context("POST stable_token")?;
+    let token: StableTokenResponse = decode(resp).await?;
+    debug!(expires_in = token.expires_in, force_refresh, "Fetched wechat access token");
+
+    let lifetime = Duration::from_secs(token.expires_in).saturating_sub(TOKEN_REFRESH_MARGIN);
+    *cached = Some(CachedToken {
+        token: token.access_token.clone(),
+        refresh_at: Instant::now() + lifetime,
+    });
+    Ok(token.access_token)
+}
+
+/// 发送订阅消息
+///
+/// data 为模板字段到内容的映射；access token 被微信判定为无效时强制刷新后重试一次。
+pub async fn send_subscribe_message(
+    openid: &str,
+    template_id: &str,
+    page: &str,
+    data: &[(String, String)],
+) -> Result<()> {
+    let config = wechat();
+    let data: serde_json::Map<String, Value> = data
+        .iter()
+        .map(|(field, value)| (field.clone(), json!({ "value": value })))
+        .collect();
+    let mut body = json!({
+        "touser": openid,
+        "template_id": template_id,
+        "miniprogram_state": config.miniprogram_state,
+        "lang": "zh_CN",
+        "data": data,
+    });
+    if !page.is_empty() {
+        body["page"] = json!(page);
+    }
+
+    let mut force_refresh = false;
+    loop {
+        let token = access_token(force_refresh).await?;
+        let resp = CLIENT
+            .post(config.api("/cgi-bin/message/subscribe/send"))
+            .query(&[("access_token", token.as_str())])
+            .json(&body)
+            .send_observed("wechat_send_subscribe_message")
+            .await
+            .map_err(reqwest::Error::without_url)
+            .context("POST subscribe/send")?;
+        match decode::<Value>(resp).await {
+            Err(e) if !force_refresh && e.downcast_ref::<WechatError>().is_some_and(WechatError::is_token_invalid) => {
+                warn!(error = %e, "Wechat access token rejected, refreshing");
+                force_refresh = true;
+            }
+            result => return result.map(|_| ()),
+        }
+    }
+}
+
+/// code2session 的结果
+#[derive(Debug, Deserialize)]
+pub struct WechatSession {
+    pub openid: String,
+}
+
+/// 用 wx.login 得到的 code 换取小程序用户的 openid
+pub async fn code_to_session(code: &str) -> Result<WechatSession> {
+    let config = wechat();
+    let resp = CLIENT
+        .get(config.api("/sns/jscode2session"))
+        .query(&[
+            ("appid", config.app_id.as_str()),
+            ("secret", config.app_secret.as_str()),
+            ("js_code", code),
+            ("grant_type", "authorization_code"),
+        ])
+        .send_observed("wechat_code_to_session")
+        .await
+        // 请求地址中带有 AppSecret，不能出现在错误信息里
+        .map_err(reqwest::Error::without_url)
+        .context("GET jscode2session")?;
+    decode(resp).await
+}
+
+/// 供接口处理函数用 code 换取 openid，code 无效时返回 `WechatCodeInvalid`，微信接口不可用时返回 `WechatUnavailable`
+pub async fn openid_for_code(code: &str) -> Result<String, ApiError> {
+    if !wechat().enabled {
+        return Err(ApiError::WechatDisabled);
+    }
+    let code = code.trim();
+    if code.is_empty() {
+        return Err(ApiError::InvalidRequest);
+    }
+    match code_to_session(code).await {
+        Ok(session) => Ok(session.openid),
+        Err(e) if e.downcast_ref::<WechatError>().is_some_and(WechatError::is_invalid_code) => {
+            Err(ApiError::WechatCodeInvalid)
+        }
+        Err(e) => {
+            error!(error = %format!("{:#}", e), "code2session failed");
+            Err(ApiError::WechatUnavailable)
+        }
+    }
+}
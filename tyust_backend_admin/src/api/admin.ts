@@ -6,11 +6,29 @@ export interface LoginParams {
   password: string
 }
 
+export type AdminRole = 'super_admin' | 'operator' | 'counselor'
+
+export type AdminPermission =
+  | 'view_students'
+  | 'view_statistics'
+  | 'view_semester'
+  | 'manage_semester'
+  | 'manage_admins'
+  | 'manage_own_account'
+  | 'view_audit_log'
+  | 'manage_students'
+  | 'manage_jobs'
+  | 'publish_announcements'
+
 export interface LoginResponse {
   code: number
   data: {
     token: string
+    refresh_token: string
+    expires_in: number
     username: string
+    role: AdminRole
+    permissions: AdminPermission[]
   }
   message: string
 }
@@ -23,18 +41,71 @@ export function login(data: LoginParams) {
   })
 }
 
+// 注销当前会话
+export function logout(refreshToken: string) {
+  return request({
+    url: '/admin/logout',
+    method: 'post',
+    data: { refresh_token: refreshToken }
+  })
+}
+
+// 在所有设备上登出
+export function logoutAll() {
+  return request({
+    url: '/admin/logout-all',
+    method: 'post'
+  })
+}
+
 // 获取学生列表
 export interface Student {
   studentId: string
   name: string
-  class: string
-  token: string
+  class: string | null
+  avatarUrl: string | null
+  createdAt: string | null
+  lastActiveAt: string | null
+  disabled: boolean
+}
+
+export type StudentSortField = 'last_active' | 'student_id' | 'name' | 'class' | 'created_at'
+
+export interface StudentListQuery {
+  page?: number
+  page_size?: number
+  search?: string
+  sort_by?: StudentSortField
+  order?: 'asc' | 'desc'
 }
 
-export function getStudentList() {
+export function getStudentList(params: StudentListQuery = {}) {
   return request({
     url: '/admin/students',
-    method: 'get'
+    method: 'get',
+    params
+  })
+}
+
+// 强制学生登出
+export function forceLogoutStudent(studentId: string) {
+  return request({
+    url: `/admin/students/${encodeURIComponent(studentId)}/logout`,
+    method: 'post'
+  })
+}
+
+// 禁用或启用学生
+export interface SetStudentStatusParams {
+  disabled: boolean
+  reason?: string
+}
+
+export function setStudentStatus(studentId: string, data: SetStudentStatusParams) {
+  return request({
+    url: `/admin/students/${encodeURIComponent(studentId)}/status`,
+    method: 'post',
+    data
   })
 }
 
@@ -55,6 +126,7 @@ export function getSemesterConfig() {
 export interface SetSemesterParams {
   semester_name: string
   start_date: string
+  scheduled?: boolean // 作为下一学期保存，到开学日期自动切换
 }
 
 export function setSemesterConfig(data: SetSemesterParams) {
@@ -79,6 +151,52 @@ export function getStatistics() {
   })
 }
 
+// 登录趋势
+export type Granularity = 'day' | 'week' | 'month'
+
+export interface LoginPeriodStats {
+  period: string
+  active_users: number
+  attempts: number
+  successes: number
+  success_rate: number
+  avg_duration_ms: number | null
+}
+
+// period 为 timezone 时区的时间
+export interface LoginTrend {
+  timezone: string
+  periods: LoginPeriodStats[]
+}
+
+export function getLoginTrend(params: { granularity?: Granularity; from?: string; to?: string } = {}) {
+  return request({
+    url: '/admin/analytics/logins',
+    method: 'get',
+    params
+  })
+}
+
+// 登录高峰时段
+export interface HourlyLogins {
+  hour: number
+  logins: number
+}
+
+// hour 为 timezone 时区的小时
+export interface PeakHours {
+  timezone: string
+  hours: HourlyLogins[]
+}
+
+export function getLoginPeakHours(days = 30) {
+  return request({
+    url: '/admin/analytics/peak-hours',
+    method: 'get',
+    params: { days }
+  })
+}
+
 // 修改管理员密码
 export interface UpdatePasswordParams {
   old_password: string
@@ -105,4 +223,141 @@ export function updateAdminUsername(data: UpdateUsernameParams) {
     method: 'post',
     data
   })
-}
\ No newline at end of file
+}
+
+// 管理员账户管理
+export interface AdminAccount {
+  id: number
+  username: string
+  role: AdminRole
+  is_active: boolean
+  created_at: string
+  updated_at: string
+}
+
+export function getAdminList() {
+  return request({
+    url: '/admin/admins',
+    method: 'get'
+  })
+}
+
+export interface CreateAdminParams {
+  username: string
+  password: string
+  role: AdminRole
+}
+
+export function createAdmin(data: CreateAdminParams) {
+  return request({
+    url: '/admin/admins',
+    method: 'post',
+    data
+  })
+}
+
+export function setAdminStatus(id: number, isActive: boolean) {
+  return request({
+    url: `/admin/admins/${id}/status`,
+    method: 'post',
+    data: { is_active: isActive }
+  })
+}
+
+export function resetAdminPassword(id: number, newPassword: string) {
+  return request({
+    url: `/admin/admins/${id}/password`,
+    method: 'post',
+    data: { new_password: newPassword }
+  })
+}
+
+export function assignAdminRole(id: number, role: AdminRole) {
+  return request({
+    url: `/admin/admins/${id}/role`,
+    method: 'post',
+    data: { role }
+  })
+}
+
+// 审计日志
+export interface PageResult<T> {
+  items: T[]
+  total: number
+  page: number
+  page_size: number
+}
+
+export interface AuditLogEntry {
+  id: number
+  admin_id: number | null
+  admin_username: string | null
+  action: string
+  target: string | null
+  before: unknown
+  after: unknown
+  ip: string | null
+  created_at: string
+}
+
+export interface AuditLogQuery {
+  page?: number
+  page_size?: number
+  admin_id?: number
+  action?: string
+  target?: string
+  from?: string
+  to?: string
+}
+
+export function getAuditLogs(params: AuditLogQuery) {
+  return request({
+    url: '/admin/audit',
+    method: 'get',
+    params
+  })
+}
+
+// 定时任务
+export type JobName = 'auth_cache_cleanup' | 'avatar_gc' | 'score_polling' | 'term_rollover' | 'event_cleanup'
+
+export interface JobStatus {
+  name: JobName
+  schedule: string | null
+  running: boolean
+  running_on: string | null
+  run_requested: boolean
+  next_run_at: string | null
+  last_started_at: string | null
+  last_finished_at: string | null
+  last_status: 'running' | 'succeeded' | 'failed' | null
+  last_message: string | null
+  last_duration_ms: number | null
+}
+
+export function getJobs() {
+  return request({
+    url: '/admin/jobs',
+    method: 'get'
+  })
+}
+
+export function runJob(name: JobName) {
+  return request({
+    url: `/admin/jobs/${name}/run`,
+    method: 'post'
+  })
+}
+
+export interface PublishAnnouncementParams {
+  title: string
+  content: string
+}
+
+export function publishAnnouncement(data: PublishAnnouncementParams) {
+  return request({
+    url: '/admin/announcements',
+    method: 'post',
+    data
+  })
+}
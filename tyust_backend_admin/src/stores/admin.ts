@@ -1,11 +1,13 @@
 import { defineStore } from 'pinia'
 import { ref } from 'vue'
-import { login as loginApi } from '@/api/admin'
-import type { LoginParams } from '@/api/admin'
+import { login as loginApi, logout as logoutApi, logoutAll as logoutAllApi } from '@/api/admin'
+import type { AdminPermission, AdminRole, LoginParams } from '@/api/admin'
 
 export const useAdminStore = defineStore('admin', () => {
   const token = ref<string>(localStorage.getItem('admin_token') || '')
   const username = ref<string>(localStorage.getItem('admin_username') || '')
+  const role = ref<AdminRole | ''>((localStorage.getItem('admin_role') as AdminRole) || '')
+  const permissions = ref<AdminPermission[]>(JSON.parse(localStorage.getItem('admin_permissions') || '[]'))
   const isLoggedIn = ref<boolean>(!!token.value)
 
   // 登录
@@ -15,10 +17,15 @@ export const useAdminStore = defineStore('admin', () => {
       if (res.code === 0 && res.data) {
         token.value = res.data.token
         username.value = res.data.username
+        role.value = res.data.role
+        permissions.value = res.data.permissions
         isLoggedIn.value = true
         
         localStorage.setItem('admin_token', res.data.token)
+        localStorage.setItem('admin_refresh_token', res.data.refresh_token)
         localStorage.setItem('admin_username', res.data.username)
+        localStorage.setItem('admin_role', res.data.role)
+        localStorage.setItem('admin_permissions', JSON.stringify(res.data.permissions))
         
         return true
       }
@@ -29,21 +36,49 @@ export const useAdminStore = defineStore('admin', () => {
     }
   }
 
-  // 登出
-  function logout() {
+  // 登出（同时吊销服务端的 refresh token）
+  async function logout() {
+    const refreshToken = localStorage.getItem('admin_refresh_token')
+    if (token.value && refreshToken) {
+      await logoutApi(refreshToken).catch(() => {})
+    }
+    clearSession()
+  }
+
+  // 在所有设备上登出
+  async function logoutAll() {
+    await logoutAllApi()
+    clearSession()
+  }
+
+  function clearSession() {
     token.value = ''
     username.value = ''
+    role.value = ''
+    permissions.value = []
     isLoggedIn.value = false
     
     localStorage.removeItem('admin_token')
+    localStorage.removeItem('admin_refresh_token')
     localStorage.removeItem('admin_username')
+    localStorage.removeItem('admin_role')
+    localStorage.removeItem('admin_permissions')
+  }
+
+  // 是否拥有指定权限
+  function hasPermission(permission: AdminPermission) {
+    return permissions.value.includes(permission)
   }
 
   return {
     token,
     username,
+    role,
+    permissions,
     isLoggedIn,
     login,
-    logout
+    logout,
+    logoutAll,
+    hasPermission
   }
 })
\ No newline at end of file
@@ -1,12 +1,43 @@
 import axios from 'axios'
 import type { AxiosInstance, AxiosRequestConfig, AxiosResponse } from 'axios'
 
+const baseURL = 'http://localhost:3000/api'
+
 // 创建 axios 实例
 const service: AxiosInstance = axios.create({
-  baseURL: 'http://localhost:3000/api',
+  baseURL,
   timeout: 15000
 })
 
+// 正在进行的刷新请求，避免并发请求重复刷新
+let refreshing: Promise<string | null> | null = null
+
+// 使用 refresh token 换取新的 access token
+function refreshAccessToken(): Promise<string | null> {
+  const refreshToken = localStorage.getItem('admin_refresh_token')
+  if (!refreshToken) {
+    return Promise.resolve(null)
+  }
+  if (!refreshing) {
+    refreshing = axios
+      .post(`${baseURL}/admin/refresh`, { refresh_token: refreshToken })
+      .then((res) => {
+        const data = res.data?.data
+        if (!data) {
+          return null
+        }
+        localStorage.setItem('admin_token', data.token)
+        localStorage.setItem('admin_refresh_token', data.refresh_token)
+        return data.token as string
+      })
+      .catch(() => null)
+      .finally(() => {
+        refreshing = null
+      })
+  }
+  return refreshing
+}
+
 // 请求拦截器
 service.interceptors.request.use(
   (config) => {
@@ -37,12 +68,22 @@ service.interceptors.response.use(
     console.error('Business error:', res.message || 'Unknown error')
     return Promise.reject(new Error(res.message || 'Request failed'))
   },
-  (error) => {
+  async (error) => {
     console.error('Response error:', error.message)
     
-    // 401 未授权
+    // 401 未授权：先尝试刷新会话，失败后跳转登录页
+    const original = error.config as (AxiosRequestConfig & { _retried?: boolean }) | undefined
     if (error.response?.status === 401) {
+      if (original && !original._retried && !original.url?.includes('/admin/login')) {
+        original._retried = true
+        const token = await refreshAccessToken()
+        if (token) {
+          original.headers = { ...original.headers, Authorization: `Bearer ${token}` }
+          return service(original)
+        }
+      }
       localStorage.removeItem('admin_token')
+      localStorage.removeItem('admin_refresh_token')
       localStorage.removeItem('admin_info')
       window.location.href = '/login'
     }
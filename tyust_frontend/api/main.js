@@ -9,6 +9,26 @@ export function loginRequest(data) {
   })
 }
 
+// 微信登录（code 为 wx.login 得到的 code，需先使用学号密码登录绑定微信）
+export function wechatLoginRequest(data) {
+  return createRequest({
+    url: '/auth/wechat-login',
+    method: 'POST',
+    data,
+    needLogin: false,
+    loading: false,
+    silent: true
+  })
+}
+
+// 解除微信绑定
+export function unbindWechatRequest() {
+  return createRequest({
+    url: '/auth/wechat/unbind',
+    method: 'POST'
+  })
+}
+
 export function getScoreListRequest(data) {
   return createRequest({
     url: '/scores',
@@ -64,4 +84,20 @@ export function updateAvatarRequest(data) {
     method: 'POST',
     data
   })
-}
\ No newline at end of file
+}
+// 获取可订阅的消息模板和已授权次数
+export function getSubscriptionsRequest() {
+  return createRequest({
+    url: '/wechat/subscriptions',
+    method: 'GET'
+  })
+}
+
+// 上报订阅消息授权结果（code 为 wx.login 得到的 code，results 为 wx.requestSubscribeMessage 的结果）
+export function reportSubscriptionsRequest(data) {
+  return createRequest({
+    url: '/wechat/subscriptions',
+    method: 'POST',
+    data
+  })
+}
@@ -1,4 +1,4 @@
-import { loginRequest } from "../../api/main";
+import { loginRequest, wechatLoginRequest } from "../../api/main";
 const auth = require("../../utils/auth");
 
 Page({
@@ -11,11 +11,11 @@ Page({
 
   onLoad(options) {
     this.initAccount();
-    this.checkLoginStatus();
+    this.checkLoginStatus(options.logout !== "1");
   },
 
-  // 检查登录状态
-  checkLoginStatus() {
+  // 检查登录状态，未登录时尝试微信登录
+  checkLoginStatus(allowWechatLogin) {
     // 如果已经登录，直接跳转到首页
     if (auth.hasToken()) {
       wx.showToast({
@@ -28,9 +28,41 @@ Page({
           url: "/pages/index/index",
         });
       }, 1500);
+      return;
+    }
+    if (allowWechatLogin) {
+      this.tryWechatLogin();
     }
   },
 
+  // 获取 wx.login 的 code，失败时返回空字符串
+  getWechatCode() {
+    return new Promise((resolve) => {
+      wx.login({
+        success: (res) => resolve(res.code || ""),
+        fail: () => resolve(""),
+      });
+    });
+  },
+
+  // 已绑定微信时直接登录，未绑定或失败时留在登录页
+  tryWechatLogin() {
+    this.getWechatCode().then((code) => {
+      if (!code) {
+        return;
+      }
+      wechatLoginRequest({ code })
+        .then((res) => {
+          if (res.code === 0 && res.data) {
+            this.handleLoginSuccess(res.data, null);
+          }
+        })
+        .catch(() => {
+          console.log("微信未绑定学号，使用学号密码登录");
+        });
+    });
+  },
+
   // 初始化账号
   initAccount() {
     const accountCache = wx.getStorageSync(auth.STORAGE_KEYS.ACCOUNT);
@@ -80,63 +112,75 @@ Page({
       mask: true,
     });
 
-    loginRequest(postData)
-      .then((res) => {
-        wx.hideLoading();
+    // 同时绑定微信，之后打开小程序可以直接登录
+    that.getWechatCode().then((wechatCode) => {
+      loginRequest({ ...postData, wechatCode: wechatCode || undefined })
+        .then((res) => {
+          wx.hideLoading();
+
+          if (res.code == -1) {
+            wx.showToast({
+              title: res.msg,
+              icon: "none",
+            });
+            return;
+          }
 
-        if (res.code == -1) {
+          that.handleLoginSuccess(res.data, postData);
+        })
+        .catch((err) => {
+          wx.hideLoading();
+          console.error("登录失败:", err);
           wx.showToast({
-            title: res.msg,
+            title: err.msg || "登录失败，请重试",
             icon: "none",
           });
-          return;
-        }
+        });
+    });
+  },
 
-        console.log("登录成功:", res.data);
+  // 保存登录结果并进入首页，postData 为空表示微信登录
+  handleLoginSuccess(data, postData) {
+    const that = this;
+    console.log("登录成功:", data);
 
-        // 使用auth工具保存token和用户信息
-        auth.setToken(res.data.token);
-        auth.setUserInfo({
-          studentId: res.data.studentId,
-          name: res.data.name,
-          class: res.data.class || "未知班级",
-          avatarUrl: res.data.avatarUrl || "",
-        });
+    // 使用auth工具保存token和用户信息
+    auth.setToken(data.token);
+    auth.setRefreshToken(data.refreshToken);
+    auth.setUserInfo({
+      studentId: data.studentId,
+      name: data.name,
+      class: data.class || "未知班级",
+      avatarUrl: data.avatarUrl || "",
+    });
 
-        // 获取微信用户信息（包括头像）- 仅当数据库中没有头像时
-        if (!res.data.avatarUrl) {
-          that.getWechatUserInfo();
-        }
+    // 获取微信用户信息（包括头像）- 仅当数据库中没有头像时
+    if (!data.avatarUrl) {
+      that.getWechatUserInfo();
+    }
 
-        // 处理记住密码
-        if (that.data.saveCount) {
-          wx.setStorageSync(auth.STORAGE_KEYS.ACCOUNT, postData);
-        } else {
-          wx.removeStorageSync(auth.STORAGE_KEYS.ACCOUNT);
-        }
+    // 处理记住密码
+    if (postData) {
+      if (that.data.saveCount) {
+        wx.setStorageSync(auth.STORAGE_KEYS.ACCOUNT, postData);
+      } else {
+        wx.removeStorageSync(auth.STORAGE_KEYS.ACCOUNT);
+      }
+    }
 
-        // 立即获取课程表数据
-        that.fetchCourseData(() => {
-          wx.showToast({
-            title: "登录成功",
-            icon: "success",
-          });
+    // 立即获取课程表数据
+    that.fetchCourseData(() => {
+      wx.showToast({
+        title: "登录成功",
+        icon: "success",
+      });
 
-          setTimeout(() => {
-            wx.switchTab({
-              url: "/pages/index/index",
-            });
-          }, 1500);
-        });
-      })
-      .catch((err) => {
-        wx.hideLoading();
-        console.error("登录失败:", err);
-        wx.showToast({
-          title: err.msg || "登录失败，请重试",
-          icon: "none",
+      setTimeout(() => {
+        wx.switchTab({
+          url: "/pages/index/index",
         });
-      });
+      }, 1500);
+    });
   },
 
   // 获取微信用户信息
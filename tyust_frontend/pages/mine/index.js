@@ -221,10 +221,10 @@ Page({
             duration: 1500,
           });
 
-          // 跳转到登录页
+          // 跳转到登录页（主动退出后不自动使用微信登录）
           setTimeout(() => {
             wx.reLaunch({
-              url: "/pages/login/index",
+              url: "/pages/login/index?logout=1",
             });
           }, 1500);
         }
@@ -5,6 +5,7 @@
 // 存储键名常量
 const STORAGE_KEYS = {
   TOKEN: 'token',
+  REFRESH_TOKEN: 'refreshToken',
   STUDENT_ID: 'studentId',
   NAME: 'name',
   CLASS: 'class',
@@ -46,6 +47,24 @@ function removeToken() {
   wx.removeStorageSync(STORAGE_KEYS.TOKEN)
 }
 
+/**
+ * 获取refresh token
+ * @returns {string}
+ */
+function getRefreshToken() {
+  return wx.getStorageSync(STORAGE_KEYS.REFRESH_TOKEN) || ''
+}
+
+/**
+ * 设置refresh token
+ * @param {string} refreshToken
+ */
+function setRefreshToken(refreshToken) {
+  if (refreshToken) {
+    wx.setStorageSync(STORAGE_KEYS.REFRESH_TOKEN, refreshToken)
+  }
+}
+
 /**
  * 获取学生ID
  * @returns {string}
@@ -100,6 +119,7 @@ function setUserInfo(userInfo) {
  */
 function clearAuth(keepAccount = true) {
   wx.removeStorageSync(STORAGE_KEYS.TOKEN)
+  wx.removeStorageSync(STORAGE_KEYS.REFRESH_TOKEN)
   wx.removeStorageSync(STORAGE_KEYS.STUDENT_ID)
   wx.removeStorageSync(STORAGE_KEYS.NAME)
   wx.removeStorageSync(STORAGE_KEYS.CLASS)
@@ -176,6 +196,8 @@ module.exports = {
   getToken,
   setToken,
   removeToken,
+  getRefreshToken,
+  setRefreshToken,
   getStudentId,
   setStudentId,
   getUserInfo,
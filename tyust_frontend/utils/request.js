@@ -4,6 +4,44 @@ const auth = require("./auth");
 // 标记是否正在处理token失效，避免重复跳转
 let isHandlingTokenExpired = false;
 
+// 正在进行的刷新请求，多个请求同时遇到401时共用同一次刷新
+let refreshPromise = null;
+
+// 使用refresh token换取新的token（refresh token只能使用一次，刷新后需保存新的）
+function refreshTokens() {
+  if (refreshPromise) {
+    return refreshPromise;
+  }
+
+  const refreshToken = auth.getRefreshToken();
+  if (!refreshToken) {
+    return Promise.reject({ code: 401, msg: "请先登录" });
+  }
+
+  refreshPromise = new Promise((resolve, reject) => {
+    wx.request({
+      url: `${app.getConfig("baseUrl")}/auth/refresh`,
+      method: "POST",
+      data: { refreshToken },
+      success(res) {
+        const body = res.data || {};
+        if (body.code === 0 && body.data) {
+          auth.setToken(body.data.token);
+          auth.setRefreshToken(body.data.refreshToken);
+          resolve(body.data.token);
+        } else {
+          reject(body);
+        }
+      },
+      fail: reject,
+      complete() {
+        refreshPromise = null;
+      },
+    });
+  });
+  return refreshPromise;
+}
+
 export default function createRequest(options) {
   return new Promise((resolve, reject) => {
     const token = auth.getToken();
@@ -39,6 +77,10 @@ export default function createRequest(options) {
       data: options.data || {},
       success(res) {
         res = res.data;
+        // 静默请求（如后台微信登录）失败时不提示、不跳转，由调用方处理
+        if (options.silent && res.code !== 0) {
+          return reject(res);
+        }
         switch (res.code) {
           // 请求成功
           case 0:
@@ -53,8 +95,23 @@ export default function createRequest(options) {
             reject(res);
             break;
 
-          // 登录已失效，需要重新登录
+          // token过期，先尝试刷新后重试一次
           case 401:
+            if (options.needLogin !== false && !options._retried) {
+              refreshTokens()
+                .then(() => createRequest({ ...options, _retried: true, loading: false }))
+                .then(resolve)
+                .catch((err) => {
+                  handleTokenExpired("登录已失效，请重新登录");
+                  reject(err);
+                });
+              break;
+            }
+            handleTokenExpired("登录已失效，请重新登录");
+            reject(res);
+            break;
+
+          // 账号被禁用，需要重新登录
           case 403:
             handleTokenExpired("登录已失效，请重新登录");
             reject(res);
@@ -71,10 +128,12 @@ export default function createRequest(options) {
         }
       },
       fail(err) {
-        wx.showToast({
-          title: "网络请求失败",
-          icon: "none",
-        });
+        if (!options.silent) {
+          wx.showToast({
+            title: "网络请求失败",
+            icon: "none",
+          });
+        }
         reject(err);
       },
       complete() {